@@ -0,0 +1,253 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Data, DeriveInput, Error, Expr, Fields, Ident, Lit, LitInt, Result, Type, parse_macro_input,
+};
+
+// `#[derive(VertexLayout)]` implements `framework::vertex::VertexLayout`.
+//
+// Container: `#[vertex(step_mode = Instance, location = 3)]`, defaults are `Vertex` and 0.
+// Field: `#[vertex(location = N)]` restarts numbering, `#[vertex(format = Unorm8x4)]` overrides
+// the inferred format (per column for matrices), `#[vertex(columns = N)]` splits a field into
+// N attributes and `#[vertex(skip)]` leaves a field out of the layout.
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct FieldLayout<'a> {
+    ident: &'a Ident,
+    ty: &'a Type,
+    format: Ident,
+    columns: u32,
+    location: u32,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "VertexLayout can't be derived for generic structs",
+        ));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            name,
+            "VertexLayout can only be derived for structs",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(Error::new_spanned(
+            name,
+            "VertexLayout needs a struct with named fields",
+        ));
+    };
+
+    let mut step_mode = Ident::new("Vertex", Span::call_site());
+    let mut location = 0;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("step_mode") {
+                let mode: Ident = meta.value()?.parse()?;
+                if mode != "Vertex" && mode != "Instance" {
+                    return Err(Error::new_spanned(mode, "expected `Vertex` or `Instance`"));
+                }
+                step_mode = mode;
+            } else if meta.path.is_ident("location") {
+                location = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            } else {
+                return Err(meta.error("expected `step_mode` or `location`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut fields = Vec::new();
+    let mut used: Vec<(u32, &Ident)> = Vec::new();
+    for field in &named.named {
+        let ident = field.ident.as_ref().unwrap();
+        let mut skip = false;
+        let mut format = None;
+        let mut columns = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("vertex")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("location") {
+                    location = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                } else if meta.path.is_ident("format") {
+                    format = Some(meta.value()?.parse::<Ident>()?);
+                } else if meta.path.is_ident("columns") {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    let value: u32 = lit.base10_parse()?;
+                    if value == 0 {
+                        return Err(Error::new_spanned(lit, "a field needs at least one column"));
+                    }
+                    columns = Some(value);
+                } else {
+                    return Err(meta.error("expected `location`, `format`, `columns` or `skip`"));
+                }
+                Ok(())
+            })?;
+        }
+        if skip {
+            continue;
+        }
+
+        let (inferred, shape_columns) = infer(&field.ty);
+        let format = match (format, inferred) {
+            (Some(format), _) => format,
+            (None, Some(format)) => Ident::new(format, field.ty.span()),
+            (None, None) => {
+                return Err(Error::new_spanned(
+                    &field.ty,
+                    "can't infer a vertex format for this type, add `#[vertex(format = ...)]`",
+                ));
+            }
+        };
+        let columns = columns.unwrap_or(shape_columns);
+
+        for column in location..location + columns {
+            if let Some((_, other)) = used.iter().find(|(l, _)| *l == column) {
+                return Err(Error::new_spanned(
+                    ident,
+                    format!("location {column} is already used by `{other}`"),
+                ));
+            }
+            used.push((column, ident));
+        }
+        fields.push(FieldLayout {
+            ident,
+            ty: &field.ty,
+            format,
+            columns,
+            location,
+        });
+        location += columns;
+    }
+
+    let krate = quote!(::framework::vertex);
+    let mut attributes = Vec::new();
+    for field in &fields {
+        let FieldLayout {
+            ident, ty, format, ..
+        } = field;
+        let columns = field.columns as u64;
+        for column in 0..field.columns {
+            let location = field.location + column;
+            let column = column as u64;
+            attributes.push(quote! {
+                #krate::VertexAttribute {
+                    offset: ::core::mem::offset_of!(#name, #ident) as u64
+                        + #column * (::core::mem::size_of::<#ty>() as u64 / #columns),
+                    shader_location: #location,
+                    format: #krate::VertexFormat::#format,
+                }
+            });
+        }
+    }
+    let checks = fields.iter().map(|field| {
+        let FieldLayout {
+            ident, ty, format, ..
+        } = field;
+        let columns = field.columns as u64;
+        let message = format!("`{name}::{ident}` doesn't match the size of {columns} x {format}");
+        quote! {
+            ::core::assert!(
+                #krate::VertexFormat::#format.size() * #columns
+                    == ::core::mem::size_of::<#ty>() as u64,
+                #message
+            );
+        }
+    });
+
+    Ok(quote! {
+        impl #krate::VertexLayout for #name {
+            const STEP_MODE: #krate::VertexStepMode = #krate::VertexStepMode::#step_mode;
+            const ATTRIBUTES: &'static [#krate::VertexAttribute] = &[#(#attributes),*];
+        }
+
+        const _: () = {
+            #(#checks)*
+        };
+    })
+}
+
+// Vertex format of one column and the number of columns, e.g. `[[f32; 4]; 4]` is 4 x Float32x4
+fn infer(ty: &Type) -> (Option<&'static str>, u32) {
+    match ty {
+        Type::Array(array) => {
+            let Some(len) = array_len(&array.len) else {
+                return (None, 1);
+            };
+            if let Type::Array(inner) = array.elem.as_ref() {
+                let format = array_len(&inner.len).and_then(|n| vector(&inner.elem, n));
+                return (format, len);
+            }
+            (vector(&array.elem, len), 1)
+        }
+        Type::Path(path) => {
+            let name = path.path.segments.last().unwrap().ident.to_string();
+            match name.as_str() {
+                "Mat2" => (Some("Float32x2"), 2),
+                "Mat3" => (Some("Float32x3"), 3),
+                "Mat4" => (Some("Float32x4"), 4),
+                _ => (glam_vector(&name).or_else(|| vector(ty, 1)), 1),
+            }
+        }
+        _ => (None, 1),
+    }
+}
+
+fn vector(elem: &Type, len: u32) -> Option<&'static str> {
+    let Type::Path(path) = elem else {
+        return None;
+    };
+    let scalar = path.path.get_ident()?.to_string();
+    Some(match (scalar.as_str(), len) {
+        ("f32", 1) => "Float32",
+        ("f32", 2) => "Float32x2",
+        ("f32", 3) => "Float32x3",
+        ("f32", 4) => "Float32x4",
+        ("u32", 1) => "Uint32",
+        ("u32", 2) => "Uint32x2",
+        ("u32", 3) => "Uint32x3",
+        ("u32", 4) => "Uint32x4",
+        ("i32", 1) => "Sint32",
+        ("i32", 2) => "Sint32x2",
+        ("i32", 3) => "Sint32x3",
+        ("i32", 4) => "Sint32x4",
+        _ => return None,
+    })
+}
+
+fn glam_vector(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Vec2" => "Float32x2",
+        "Vec3" => "Float32x3",
+        "Vec4" => "Float32x4",
+        "UVec2" => "Uint32x2",
+        "UVec3" => "Uint32x3",
+        "UVec4" => "Uint32x4",
+        "IVec2" => "Sint32x2",
+        "IVec3" => "Sint32x3",
+        "IVec4" => "Sint32x4",
+        _ => return None,
+    })
+}
+
+fn array_len(len: &Expr) -> Option<u32> {
+    let Expr::Lit(lit) = len else {
+        return None;
+    };
+    let Lit::Int(int) = &lit.lit else {
+        return None;
+    };
+    int.base10_parse().ok()
+}
@@ -1,73 +1,205 @@
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use tracing::{error, info, warn};
+use wgpu::{PollType, TextureFormat};
 use winit::application::ApplicationHandler;
-use winit::event::{DeviceEvent, ElementState, WindowEvent};
+use winit::dpi::PhysicalSize;
+use winit::event::{DeviceEvent, ElementState, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowAttributes, WindowId};
 
 use crate::GpuContext;
-use crate::example::Example;
-use crate::input::Input;
+use crate::capture::{TextureReadback, capture_path};
+use crate::error::{Error, Result};
+use crate::example::{Example, TryExample};
+use crate::gpu::GpuContextBuilder;
+use crate::input::{BindingsFile, Gamepads, Input};
+use crate::replay::{Replay, set_seed};
+use crate::shader::ShaderWatcher;
+use crate::ui::{Ui, UiState};
 
 pub fn run<E: Example>(title: &str) {
-    tracing_subscriber::fmt()
+    run_with::<E>(title, GpuContextBuilder::new());
+}
+
+pub fn run_with<E: Example>(title: &str, builder: GpuContextBuilder) {
+    if let Err(err) = try_run_with::<E>(title, builder) {
+        error!("{err}");
+        std::process::exit(1);
+    }
+}
+
+pub fn try_run<E: Example>(title: &str) -> Result<()> {
+    try_run_with::<E>(title, GpuContextBuilder::new())
+}
+
+// Environment variables and command-line arguments override the builder settings
+pub fn try_run_with<E: Example>(title: &str, builder: GpuContextBuilder) -> Result<()> {
+    let _ = tracing_subscriber::fmt()
         .with_max_level(tracing::Level::INFO)
-        .init();
+        .try_init();
 
-    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    let builder = builder.with_env().with_args();
+    let replay = Replay::from_args()?;
+    let event_loop = EventLoop::new()?;
 
     let mut app = App::<E>::Loading {
         title: title.to_owned(),
+        builder: Box::new(builder),
+        replay,
     };
-    event_loop
-        .run_app(&mut app)
-        .expect("Failed to run event loop");
+    event_loop.run_app(&mut app)?;
+
+    match app {
+        App::Failed(err) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+const RECOVER_ATTEMPTS: u32 = 5;
+const RECOVER_DELAY: Duration = Duration::from_millis(200);
+
+pub const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+pub const HEADLESS_FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+
+pub fn run_headless<E: Example>(size: PhysicalSize<u32>, frames: u32) -> (GpuContext, E) {
+    run_headless_with::<E>(size, frames, HEADLESS_FRAME_TIME, &Input::default())
+}
+
+pub fn run_headless_with<E: Example>(
+    size: PhysicalSize<u32>,
+    frames: u32,
+    frame_time: Duration,
+    input: &Input,
+) -> (GpuContext, E) {
+    try_run_headless_with::<E>(size, frames, frame_time, input)
+        .unwrap_or_else(|err| panic!("{err}"))
+}
+
+pub fn try_run_headless_with<E: Example>(
+    size: PhysicalSize<u32>,
+    frames: u32,
+    frame_time: Duration,
+    input: &Input,
+) -> Result<(GpuContext, E)> {
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .try_init();
+
+    set_seed(0);
+    let mut ctx = GpuContext::try_new_headless(size, HEADLESS_FORMAT)?;
+    let mut example = E::try_init(&ctx)?;
+    let mut input = input.clone();
+    E::bindings(input.bindings_mut());
+
+    for _ in 0..frames {
+        example.update(&ctx, frame_time, &input);
+
+        let Some((_, view, mut encoder)) = ctx.acquire_frame() else {
+            continue;
+        };
+        example.render(&ctx, &view, &mut encoder);
+        // Headless frames end up in golden images, which shouldn't depend on debug text
+        ctx.overlay.clear();
+        ctx.profiler.resolve(&mut encoder);
+        ctx.queue.submit([encoder.finish()]);
+        ctx.profiler.end_frame(&ctx.device);
+    }
+
+    ctx.device.poll(PollType::wait_indefinitely())?;
+
+    Ok((ctx, example))
 }
 
 enum App<E: Example> {
     Loading {
         title: String,
+        builder: Box<GpuContextBuilder>,
+        replay: Replay,
     },
     Ready {
         window: Arc<Window>,
         ctx: Box<GpuContext>,
         example: Box<E>,
-        input: Input,
+        input: Box<Input>,
         need_resize: bool,
+        capture_requested: bool,
         last_frame: Instant,
+        // Toggled by the `toggle_ui` and `toggle_stats` actions, F1 and F3 by default
+        show_ui: bool,
+        show_stats: bool,
+        ui_state: UiState,
+        shader_watcher: ShaderWatcher,
+        bindings_file: BindingsFile,
+        gamepads: Box<Gamepads>,
+        replay: Replay,
     },
+    Failed(Error),
+}
+
+impl<E: Example> App<E> {
+    fn init(
+        event_loop: &ActiveEventLoop,
+        title: &str,
+        builder: GpuContextBuilder,
+        replay: Replay,
+    ) -> Result<Self> {
+        let window_attrs = WindowAttributes::default()
+            .with_title(title)
+            .with_visible(false);
+
+        let window = Arc::new(event_loop.create_window(window_attrs)?);
+
+        center_window(&window);
+
+        event_loop.set_control_flow(ControlFlow::Wait);
+
+        let ctx = Box::new(builder.try_build(window.clone())?);
+        let example = Box::new(E::try_init(&ctx)?);
+        let bindings_file = BindingsFile::from_args();
+        let mut input = Box::<Input>::default();
+        input.set_window_size(window.inner_size());
+        input.set_bindings(bindings_file.load());
+        E::bindings(input.bindings_mut());
+
+        Ok(Self::Ready {
+            window,
+            ctx,
+            example,
+            input,
+            need_resize: false,
+            capture_requested: false,
+            last_frame: Instant::now(),
+            show_ui: true,
+            show_stats: false,
+            ui_state: UiState::default(),
+            shader_watcher: ShaderWatcher::default(),
+            bindings_file,
+            gamepads: Box::new(Gamepads::new()),
+            replay,
+        })
+    }
 }
 
 impl<E: Example> ApplicationHandler for App<E> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if let Self::Loading { title } = self {
-            let window_attrs = WindowAttributes::default()
-                .with_title(title.clone())
-                .with_visible(false);
-
-            let window = Arc::new(
-                event_loop
-                    .create_window(window_attrs)
-                    .expect("Failed to create window"),
-            );
-
-            center_window(&window);
-
-            event_loop.set_control_flow(ControlFlow::Wait);
-
-            let ctx = Box::new(GpuContext::new(window.clone()));
-            let example = Box::new(E::init(&ctx));
-
-            *self = Self::Ready {
-                window,
-                ctx,
-                example,
-                input: Input::default(),
-                need_resize: false,
-                last_frame: Instant::now(),
-            };
+        if let Self::Loading {
+            title,
+            builder,
+            replay,
+        } = self
+        {
+            let builder = *std::mem::take(builder);
+            let replay = std::mem::take(replay);
+            match Self::init(event_loop, title, builder, replay) {
+                Ok(app) => *self = app,
+                Err(err) => {
+                    *self = Self::Failed(err);
+                    event_loop.exit();
+                    return;
+                }
+            }
         }
 
         let Self::Ready {
@@ -76,6 +208,9 @@ impl<E: Example> ApplicationHandler for App<E> {
             example,
             input,
             last_frame,
+            replay,
+            show_ui,
+            ui_state,
             ..
         } = self
         else {
@@ -86,8 +221,12 @@ impl<E: Example> ApplicationHandler for App<E> {
         *last_frame = Instant::now();
         input.clear_delta();
 
-        example.update(ctx, dt, input);
-        render_frame(ctx, example.as_mut(), window);
+        let ui_state = show_ui.then_some(ui_state);
+        if !update_example(ctx, example.as_mut(), window, replay, dt, input, ui_state) {
+            event_loop.exit();
+            return;
+        }
+        render_frame(ctx, example.as_mut(), window, false);
         window.set_visible(true);
     }
 
@@ -103,7 +242,15 @@ impl<E: Example> ApplicationHandler for App<E> {
             example,
             input,
             need_resize,
+            capture_requested,
             last_frame,
+            replay,
+            show_ui,
+            show_stats,
+            ui_state,
+            shader_watcher,
+            bindings_file,
+            gamepads,
         } = self
         else {
             return;
@@ -111,6 +258,46 @@ impl<E: Example> ApplicationHandler for App<E> {
 
         match event {
             WindowEvent::RedrawRequested => {
+                if ctx.is_lost()
+                    && let Err(err) = recover_device(ctx, example.as_mut())
+                {
+                    *self = Self::Failed(err);
+                    event_loop.exit();
+                    return;
+                }
+
+                if shader_watcher.poll() {
+                    info!("Reloading shaders");
+                    if let Err(err) = example.try_reload_shaders(ctx) {
+                        error!("{err}");
+                        shader_watcher.set_error(err.to_string());
+                    }
+                }
+                if let Some(err) = shader_watcher.error() {
+                    for line in err.lines() {
+                        ctx.overlay.push_line(line);
+                    }
+                }
+                if let Some(mut bindings) = bindings_file.poll() {
+                    E::bindings(&mut bindings);
+                    input.set_bindings(bindings);
+                }
+                gamepads.poll(input);
+
+                if input.action_just_pressed("exit") {
+                    event_loop.exit();
+                    return;
+                }
+                if input.action_just_pressed("capture") {
+                    *capture_requested = true;
+                }
+                if input.action_just_pressed("toggle_ui") {
+                    *show_ui = !*show_ui;
+                }
+                if input.action_just_pressed("toggle_stats") {
+                    *show_stats = !*show_stats;
+                }
+
                 let now = Instant::now();
                 let dt = now - *last_frame;
                 *last_frame = now;
@@ -122,13 +309,24 @@ impl<E: Example> ApplicationHandler for App<E> {
                     *need_resize = false;
                 }
 
-                example.update(ctx, dt, input);
+                let ui_state = show_ui.then_some(&mut *ui_state);
+                if !update_example(ctx, example.as_mut(), window, replay, dt, input, ui_state) {
+                    event_loop.exit();
+                    return;
+                }
                 input.clear_delta();
 
-                render_frame(ctx, example.as_mut(), window);
+                let capture = std::mem::take(capture_requested);
+                if *show_stats {
+                    for line in ctx.profiler.lines() {
+                        ctx.overlay.push_line(line);
+                    }
+                }
+                render_frame(ctx, example.as_mut(), window, capture);
                 window.request_redraw();
             }
-            WindowEvent::Resized(_) => {
+            WindowEvent::Resized(size) => {
+                input.set_window_size(size);
                 *need_resize = true;
                 window.request_redraw();
             }
@@ -136,18 +334,32 @@ impl<E: Example> ApplicationHandler for App<E> {
                 event_loop.exit();
             }
             WindowEvent::KeyboardInput { event, .. } => {
-                if let (PhysicalKey::Code(KeyCode::Escape), ElementState::Pressed) =
-                    (event.physical_key, event.state)
-                {
-                    event_loop.exit();
-                    return;
-                }
                 if let Some(key) = crate::input::extract_key(event.physical_key) {
                     match event.state {
                         ElementState::Pressed => input.press_key(key),
                         ElementState::Released => input.release_key(key),
                     }
                 }
+                if let (Some(text), ElementState::Pressed) = (&event.text, event.state) {
+                    input.push_text(text);
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                input.set_modifiers(modifiers.state());
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                input.set_cursor_position(Some((position.x, position.y)));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                input.set_cursor_position(None);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                // Touchpads report pixels, roughly 20 of them make up a wheel notch
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / 20.0,
+                };
+                input.add_scroll_delta(lines);
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 let btn = match button {
@@ -181,18 +393,97 @@ impl<E: Example> ApplicationHandler for App<E> {
             input.set_mouse_delta(dx, dy);
         }
     }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Self::Ready { replay, .. } = self {
+            replay.finish();
+        }
+    }
 }
 
-fn render_frame<E: Example>(ctx: &mut GpuContext, example: &mut E, window: &Window) {
+// A reset driver can take a moment to come back, so creating the device is retried with a growing
+// delay before giving up
+pub fn recover_device<E: Example>(ctx: &mut GpuContext, example: &mut E) -> Result<()> {
+    let mut delay = RECOVER_DELAY;
+    for attempt in 1.. {
+        warn!("Recreating GPU context after device loss (attempt {attempt}/{RECOVER_ATTEMPTS})");
+        match ctx.recreate() {
+            Ok(()) => break,
+            Err(err) if attempt < RECOVER_ATTEMPTS => {
+                warn!("Failed to recreate GPU context: {err}, retrying in {delay:?}");
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    example.recreate(ctx)
+}
+
+fn update_example<E: Example>(
+    ctx: &GpuContext,
+    example: &mut E,
+    window: &Window,
+    replay: &mut Replay,
+    dt: Duration,
+    input: &Input,
+    ui_state: Option<&mut UiState>,
+) -> bool {
+    let Some((dt, replayed)) = replay.next_frame(dt, input) else {
+        info!("Replay finished");
+        return false;
+    };
+    // The aspect ratio comes from the surface, so the window follows the recorded size too
+    if let Some(replayed) = &replayed
+        && replayed.window_size() != input.window_size()
+    {
+        let _ = window.request_inner_size(replayed.window_size());
+    }
+    let input = replayed.as_ref().unwrap_or(input);
+
+    if let Some(ui_state) = ui_state {
+        example.ui(&mut Ui::new(ctx, input, ui_state));
+    }
+    example.update(ctx, dt, input);
+    true
+}
+
+fn render_frame<E: Example>(ctx: &mut GpuContext, example: &mut E, window: &Window, capture: bool) {
     let Some((frame, view, mut encoder)) = ctx.acquire_frame() else {
         return;
     };
 
     example.render(ctx, &view, &mut encoder);
 
+    // Captures are copied before the overlay is drawn, so they only show the example
+    let mut readbacks = Vec::new();
+    if capture {
+        let frame_target = frame.as_ref().map(|frame| ("frame", &frame.texture));
+        for (name, texture) in frame_target.into_iter().chain(example.capture_targets()) {
+            match TextureReadback::new(ctx, texture, &mut encoder) {
+                Ok(readback) => readbacks.push((name, readback)),
+                Err(err) => warn!("Skipping capture of {name}: {err}"),
+            }
+        }
+    }
+    ctx.overlay.render(ctx, &view, &mut encoder);
+
+    ctx.profiler.resolve(&mut encoder);
     ctx.queue.submit([encoder.finish()]);
-    window.pre_present_notify();
-    ctx.queue.present(frame);
+    ctx.profiler.end_frame(&ctx.device);
+
+    for (name, readback) in readbacks {
+        let path = capture_path(name, readback.format());
+        match readback.save(ctx, &path) {
+            Ok(()) => info!("Saved capture to {}", path.display()),
+            Err(err) => warn!("Failed to save capture {}: {err}", path.display()),
+        }
+    }
+
+    if let Some(frame) = frame {
+        window.pre_present_notify();
+        ctx.queue.present(frame);
+    }
 }
 
 fn center_window(window: &Window) {
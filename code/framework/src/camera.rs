@@ -1,9 +1,194 @@
-use std::f32::consts::FRAC_PI_2;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
 
-use glam::{Mat4, Vec3};
-use winit::keyboard::KeyCode;
+use glam::camera::rh::proj::directx;
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
 
 use crate::Input;
+use crate::texture::DepthMode;
+
+// Actions from `Bindings`, the right mouse button looks around or rotates, the middle one pans
+const LOOK: &str = "look";
+const PAN: &str = "pan";
+// Pan distance per pixel relative to `sensitivity * distance`, roughly keeps the target under
+// the cursor with a 45 degree field of view
+const PAN_SCALE: f32 = 0.4;
+const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+// Radians per second with the right stick pushed all the way
+const STICK_LOOK_SPEED: f32 = 2.5;
+
+// From -1 to 1 between two opposite actions
+fn axis(input: &Input, negative: &str, positive: &str) -> f32 {
+    input.action_value(positive) - input.action_value(negative)
+}
+
+pub trait CameraController {
+    fn update(&mut self, dt: f32, input: &Input);
+    fn position(&self) -> Vec3;
+    // The orbit center, or a point straight ahead of a fly camera
+    fn target(&self) -> Vec3;
+    fn up(&self) -> Vec3 {
+        Vec3::Y
+    }
+    // Jumps to a view without smoothing, e.g. to take over from another controller
+    fn look_at(&mut self, eye: Vec3, target: Vec3);
+    fn view_matrix(&self) -> Mat4 {
+        glam::camera::rh::view::look_at_mat4(self.position(), self.target(), self.up())
+    }
+}
+
+// Same convention as `Camera`: yaw 0 and pitch 0 look down -Z
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        -yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        -yaw.cos() * pitch.cos(),
+    )
+}
+
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or(Vec3::NEG_Z);
+    (
+        (-direction.x).atan2(-direction.z),
+        direction.y.clamp(-1.0, 1.0).asin(),
+    )
+}
+
+// How far the shown view moves towards the goal this frame. `smoothing` is the time in seconds to
+// cover about 63% of the way, 0 follows the input exactly
+fn smoothing_factor(smoothing: f32, dt: f32) -> f32 {
+    if smoothing > 0.0 {
+        1.0 - (-dt / smoothing).exp()
+    } else {
+        1.0
+    }
+}
+
+fn zoom(distance: f32, input: &Input, speed: f32, range: (f32, f32)) -> f32 {
+    let factor = (1.0 - speed).powf(input.scroll_delta() as f32);
+    (distance * factor).clamp(range.0, range.1)
+}
+
+// View space to clip space, with depth in [0, 1] like wgpu expects. `fov_y` is the vertical field
+// of view in radians, `height` the vertical extent of the orthographic box in world units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    // Depth 1 at `near` and 0 at infinity, see `depth_mode`
+    InfiniteReverse { fov_y: f32, near: f32 },
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective {
+            fov_y: FRAC_PI_4,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov_y, near, far } => {
+                directx::perspective(fov_y, aspect, near, far)
+            }
+            Projection::InfiniteReverse { fov_y, near } => {
+                directx::perspective_infinite_reverse(fov_y, aspect, near)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let (x, y) = (height * aspect / 2.0, height / 2.0);
+                directx::orthographic(-x, x, -y, y, near, far)
+            }
+        }
+    }
+
+    // Depth test that matches the depth range of `matrix`
+    pub fn depth_mode(&self) -> DepthMode {
+        match self {
+            Projection::InfiniteReverse { .. } => DepthMode::Reverse,
+            _ => DepthMode::Standard,
+        }
+    }
+
+    // Ray through a point of a `viewport` of that many pixels, with `pixel` counted from the top
+    // left corner. Perspective rays start at the eye, orthographic ones on the near plane
+    pub fn ray(&self, view: Mat4, pixel: Vec2, viewport: Vec2) -> Ray {
+        let ndc = pixel / viewport * Vec2::new(2.0, -2.0) + Vec2::new(-1.0, 1.0);
+        let aspect = viewport.x / viewport.y;
+        let (origin, direction) = match *self {
+            Projection::Perspective { fov_y, .. } | Projection::InfiniteReverse { fov_y, .. } => {
+                let half = (fov_y / 2.0).tan();
+                let direction = Vec3::new(ndc.x * half * aspect, ndc.y * half, -1.0);
+                (Vec3::ZERO, direction)
+            }
+            Projection::Orthographic { height, near, .. } => {
+                let half = height / 2.0;
+                let origin = Vec3::new(ndc.x * half * aspect, ndc.y * half, -near);
+                (origin, Vec3::NEG_Z)
+            }
+        };
+        let world = view.inverse();
+        Ray {
+            origin: world.transform_point3(origin),
+            direction: world.transform_vector3(direction).normalize(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    // Unit length
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+// Planes point inwards as (normal, distance) with a unit normal: left, right, bottom, top, then
+// the depth 0 and depth 1 planes, which are near and far or the other way round with reverse-Z.
+// The far plane of an infinite projection has a zero normal and lets everything through. Corners are
+// the depth 0 plane then the depth 1 plane, each counter-clockwise from the bottom left as seen by
+// the camera; the ones at infinity are not finite
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+    pub corners: [Vec3; 8],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_proj: Mat4) -> Self {
+        // Clip space keeps -w <= x <= w, -w <= y <= w and 0 <= z <= w
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 { plane / length } else { plane }
+        });
+
+        let inverse = view_proj.inverse();
+        let corners = std::array::from_fn(|i| {
+            let (x, y) = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)][i % 4];
+            let depth = if i < 4 { 0.0 } else { 1.0 };
+            inverse.project_point3(Vec3::new(x, y, depth))
+        });
+        Self { planes, corners }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.intersects_sphere(point, 0.0)
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
 
 pub struct Camera {
     pub position: Vec3,
@@ -11,6 +196,7 @@ pub struct Camera {
     pub pitch: f32,
     pub speed: f32,
     pub sensitivity: f32,
+    pub projection: Projection,
 }
 
 impl Camera {
@@ -21,15 +207,12 @@ impl Camera {
             pitch,
             speed: 5.0,
             sensitivity: 0.003,
+            projection: Projection::default(),
         }
     }
 
     pub fn direction(&self) -> Vec3 {
-        Vec3::new(
-            -self.yaw.sin() * self.pitch.cos(),
-            self.pitch.sin(),
-            -self.yaw.cos() * self.pitch.cos(),
-        )
+        direction(self.yaw, self.pitch)
     }
 
     pub fn forward(&self) -> Vec3 {
@@ -48,39 +231,362 @@ impl Camera {
         glam::camera::rh::view::look_to_mat4(self.position, self.direction(), Vec3::Y)
     }
 
+    pub fn view_proj(&self, aspect: f32) -> Mat4 {
+        self.projection.matrix(aspect) * self.view_matrix()
+    }
+
+    pub fn frustum(&self, aspect: f32) -> Frustum {
+        Frustum::from_matrix(self.view_proj(aspect))
+    }
+
+    // Picking ray under the cursor, `pixel` as in `Input::cursor_position`
+    pub fn ray(&self, pixel: Vec2, viewport: Vec2) -> Ray {
+        self.projection.ray(self.view_matrix(), pixel, viewport)
+    }
+
     pub fn update(&mut self, dt: f32, input: &Input) {
-        if input.mouse_button_pressed(1) {
+        if input.action_pressed(LOOK) {
             let (dx, dy) = input.mouse_delta();
             self.yaw -= dx as f32 * self.sensitivity;
             self.pitch -= dy as f32 * self.sensitivity;
-            self.pitch = self.pitch.clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+            self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        }
+        let look_x = axis(input, "look_left", "look_right");
+        let look_y = axis(input, "look_down", "look_up");
+        if look_x != 0.0 || look_y != 0.0 {
+            self.yaw -= look_x * STICK_LOOK_SPEED * dt;
+            self.pitch += look_y * STICK_LOOK_SPEED * dt;
+            self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        }
+
+        // Keys always move at full speed, a half tilted stick at half speed
+        let velocity = self.forward() * axis(input, "move_back", "move_forward")
+            + self.right() * axis(input, "move_left", "move_right")
+            + Vec3::Y * axis(input, "move_down", "move_up");
+        self.position += velocity.clamp_length_max(1.0) * self.speed * dt;
+    }
+}
+
+impl CameraController for Camera {
+    fn update(&mut self, dt: f32, input: &Input) {
+        Camera::update(self, dt, input);
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn target(&self) -> Vec3 {
+        self.position + self.direction()
+    }
+
+    fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        self.position = eye;
+        (self.yaw, self.pitch) = yaw_pitch(target - eye);
+        self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+
+    fn view_matrix(&self) -> Mat4 {
+        Camera::view_matrix(self)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Orbit {
+    target: Vec3,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+// Circles around `target`: right drag rotates, middle drag pans and the wheel zooms. The public
+// fields are where the camera is heading, the view follows them with `smoothing`
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub sensitivity: f32,
+    // Fraction of the distance covered per wheel notch
+    pub zoom_speed: f32,
+    pub smoothing: f32,
+    shown: Orbit,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vec3, distance: f32, yaw: f32, pitch: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw,
+            pitch,
+            min_distance: 0.1,
+            max_distance: 100.0,
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+            smoothing: 0.08,
+            shown: Orbit {
+                target,
+                distance,
+                yaw,
+                pitch,
+            },
         }
+    }
+
+    pub fn looking_at(eye: Vec3, target: Vec3) -> Self {
+        let mut camera = Self::new(target, 1.0, 0.0, 0.0);
+        camera.look_at(eye, target);
+        camera
+    }
 
-        let forward = self.forward();
-        let right = self.right();
-        let mut velocity = Vec3::ZERO;
+    fn direction(&self) -> Vec3 {
+        direction(self.shown.yaw, self.shown.pitch)
+    }
 
-        if input.key_pressed(KeyCode::KeyW) {
-            velocity += forward;
+    fn handle_input(&mut self, input: &Input) {
+        let (dx, dy) = input.mouse_delta();
+        let (dx, dy) = (dx as f32, dy as f32);
+        if input.action_pressed(LOOK) {
+            self.yaw -= dx * self.sensitivity;
+            self.pitch = (self.pitch - dy * self.sensitivity).clamp(-PITCH_LIMIT, PITCH_LIMIT);
         }
-        if input.key_pressed(KeyCode::KeyS) {
-            velocity -= forward;
+        if input.action_pressed(PAN) {
+            let right = Vec3::new(self.yaw.cos(), 0.0, -self.yaw.sin());
+            let up = right.cross(direction(self.yaw, self.pitch));
+            let scale = self.sensitivity * self.distance * PAN_SCALE;
+            self.target += (up * dy - right * dx) * scale;
         }
-        if input.key_pressed(KeyCode::KeyD) {
-            velocity += right;
+        let range = (self.min_distance, self.max_distance);
+        self.distance = zoom(self.distance, input, self.zoom_speed, range);
+    }
+
+    fn follow(&mut self, dt: f32) {
+        let t = smoothing_factor(self.smoothing, dt);
+        let shown = &mut self.shown;
+        shown.target = shown.target.lerp(self.target, t);
+        shown.distance += (self.distance - shown.distance) * t;
+        shown.yaw += (self.yaw - shown.yaw) * t;
+        shown.pitch += (self.pitch - shown.pitch) * t;
+    }
+}
+
+impl CameraController for OrbitCamera {
+    fn update(&mut self, dt: f32, input: &Input) {
+        self.handle_input(input);
+        self.follow(dt);
+    }
+
+    fn position(&self) -> Vec3 {
+        self.shown.target - self.direction() * self.shown.distance
+    }
+
+    fn target(&self) -> Vec3 {
+        self.shown.target
+    }
+
+    fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        let (yaw, pitch) = yaw_pitch(target - eye);
+        self.target = target;
+        self.distance = eye.distance(target).max(self.min_distance);
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        self.shown = Orbit {
+            target: self.target,
+            distance: self.distance,
+            yaw: self.yaw,
+            pitch: self.pitch,
+        };
+    }
+}
+
+// Rotates freely with a quaternion, so the model can be turned upside down and the camera never
+// locks at the poles. Dragging right turns the front of the model to the right
+pub struct ArcballCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    pub rotation: Quat,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub sensitivity: f32,
+    pub zoom_speed: f32,
+    pub smoothing: f32,
+    shown: (Vec3, f32, Quat),
+}
+
+impl ArcballCamera {
+    pub fn looking_at(eye: Vec3, target: Vec3) -> Self {
+        let mut camera = Self {
+            target,
+            distance: 1.0,
+            rotation: Quat::IDENTITY,
+            min_distance: 0.1,
+            max_distance: 100.0,
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+            smoothing: 0.08,
+            shown: (target, 1.0, Quat::IDENTITY),
+        };
+        camera.look_at(eye, target);
+        camera
+    }
+}
+
+impl CameraController for ArcballCamera {
+    fn update(&mut self, dt: f32, input: &Input) {
+        let (dx, dy) = input.mouse_delta();
+        let drag = Vec2::new(dx as f32, dy as f32);
+        if input.action_pressed(LOOK) && drag != Vec2::ZERO {
+            // Around the camera's own axes, perpendicular to the drag
+            let axis = Vec3::new(-drag.y, -drag.x, 0.0).normalize();
+            let turn = Quat::from_axis_angle(axis, drag.length() * self.sensitivity);
+            self.rotation = (self.rotation * turn).normalize();
         }
-        if input.key_pressed(KeyCode::KeyA) {
-            velocity -= right;
+        if input.action_pressed(PAN) {
+            let scale = self.sensitivity * self.distance * PAN_SCALE;
+            self.target += self.rotation * Vec3::new(-drag.x, drag.y, 0.0) * scale;
         }
-        if input.key_pressed(KeyCode::Space) {
-            velocity.y += 1.0;
+        let range = (self.min_distance, self.max_distance);
+        self.distance = zoom(self.distance, input, self.zoom_speed, range);
+
+        let t = smoothing_factor(self.smoothing, dt);
+        let (target, distance, rotation) = &mut self.shown;
+        *target = target.lerp(self.target, t);
+        *distance += (self.distance - *distance) * t;
+        *rotation = rotation.slerp(self.rotation, t);
+    }
+
+    fn position(&self) -> Vec3 {
+        let (target, distance, rotation) = self.shown;
+        target + rotation * Vec3::Z * distance
+    }
+
+    fn target(&self) -> Vec3 {
+        self.shown.0
+    }
+
+    fn up(&self) -> Vec3 {
+        self.shown.2 * Vec3::Y
+    }
+
+    fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        let back = (eye - target).normalize_or(Vec3::Z);
+        let right = Vec3::Y.cross(back).try_normalize().unwrap_or(Vec3::X);
+        let up = back.cross(right);
+        self.target = target;
+        self.distance = eye.distance(target).max(self.min_distance);
+        self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, back));
+        self.shown = (self.target, self.distance, self.rotation);
+    }
+}
+
+// Model viewer style: the camera keeps its height and spins around the target's vertical axis on
+// its own until the user drags it
+pub struct TurntableCamera {
+    pub orbit: OrbitCamera,
+    // Radians per second, negative spins the other way
+    pub spin_speed: f32,
+}
+
+impl TurntableCamera {
+    pub fn looking_at(eye: Vec3, target: Vec3) -> Self {
+        Self {
+            orbit: OrbitCamera::looking_at(eye, target),
+            spin_speed: 0.5,
         }
-        if input.key_pressed(KeyCode::ShiftLeft) {
-            velocity.y -= 1.0;
+    }
+}
+
+impl CameraController for TurntableCamera {
+    fn update(&mut self, dt: f32, input: &Input) {
+        let pitch = self.orbit.pitch;
+        if !input.action_pressed(LOOK) {
+            self.orbit.yaw += self.spin_speed * dt;
         }
+        self.orbit.handle_input(input);
+        self.orbit.pitch = pitch;
+        self.orbit.follow(dt);
+    }
+
+    fn position(&self) -> Vec3 {
+        self.orbit.position()
+    }
+
+    fn target(&self) -> Vec3 {
+        self.orbit.target()
+    }
+
+    fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        self.orbit.look_at(eye, target);
+    }
+}
 
-        if velocity.length_squared() > 0.0 {
-            self.position += velocity.normalize() * self.speed * dt;
+// Several named controllers over one view. Switching hands the current view to the next
+// controller, so the picture doesn't jump
+pub struct CameraSwitcher {
+    controllers: Vec<(&'static str, Box<dyn CameraController>)>,
+    active: usize,
+}
+
+impl CameraSwitcher {
+    pub fn new(name: &'static str, controller: impl CameraController + 'static) -> Self {
+        Self {
+            controllers: vec![(name, Box::new(controller))],
+            active: 0,
         }
     }
+
+    pub fn with(mut self, name: &'static str, controller: impl CameraController + 'static) -> Self {
+        self.controllers.push((name, Box::new(controller)));
+        self
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.controllers.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index == self.active || index >= self.controllers.len() {
+            return;
+        }
+        let (eye, target) = (self.position(), self.target());
+        self.active = index;
+        self.controllers[index].1.look_at(eye, target);
+    }
+
+    fn controller(&self) -> &dyn CameraController {
+        self.controllers[self.active].1.as_ref()
+    }
+}
+
+impl CameraController for CameraSwitcher {
+    fn update(&mut self, dt: f32, input: &Input) {
+        self.controllers[self.active].1.update(dt, input);
+    }
+
+    fn position(&self) -> Vec3 {
+        self.controller().position()
+    }
+
+    fn target(&self) -> Vec3 {
+        self.controller().target()
+    }
+
+    fn up(&self) -> Vec3 {
+        self.controller().up()
+    }
+
+    fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        self.controllers[self.active].1.look_at(eye, target);
+    }
+
+    fn view_matrix(&self) -> Mat4 {
+        self.controller().view_matrix()
+    }
 }
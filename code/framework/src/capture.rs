@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use half::f16;
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, COPY_BYTES_PER_ROW_ALIGNMENT, CommandEncoder,
+    CommandEncoderDescriptor, Extent3d, MapMode, PollType, TexelCopyBufferInfo,
+    TexelCopyBufferLayout, Texture, TextureFormat, TextureUsages,
+};
+
+use crate::GpuContext;
+use crate::error::{Error, Result};
+
+pub struct TextureReadback {
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl TextureReadback {
+    // Fails for textures that can't be copied texel by texel into a buffer: without COPY_SRC,
+    // multisampled, block-compressed or combined depth-stencil
+    pub fn new(ctx: &GpuContext, texture: &Texture, encoder: &mut CommandEncoder) -> Result<Self> {
+        let format = texture.format();
+        if !texture.usage().contains(TextureUsages::COPY_SRC) {
+            return Err(Error::Readback("texture has no COPY_SRC usage".to_owned()));
+        }
+        if texture.sample_count() > 1 {
+            return Err(Error::Readback(
+                "multisampled textures can't be copied, capture the resolve target".to_owned(),
+            ));
+        }
+        let bytes_per_pixel = format
+            .block_copy_size(None)
+            .filter(|_| format.block_dimensions() == (1, 1))
+            .ok_or(Error::UnsupportedFormat(format))?;
+        let unpadded_bytes_per_row = texture.width() * bytes_per_pixel;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = ctx.device.create_buffer(&BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * texture.height()) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(texture.height()),
+                },
+            },
+            Extent3d {
+                width: texture.width(),
+                height: texture.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Ok(Self {
+            buffer,
+            width: texture.width(),
+            height: texture.height(),
+            format,
+            unpadded_bytes_per_row,
+            padded_bytes_per_row,
+        })
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    // Must be called after the encoder passed to `new` has been submitted
+    pub fn read(self, ctx: &GpuContext) -> Result<Vec<u8>> {
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        ctx.device.poll(PollType::wait_indefinitely())?;
+        // E.g. after the device was lost
+        receiver
+            .try_recv()
+            .map_err(|_| Error::Readback("buffer was never mapped".to_owned()))?
+            .map_err(|err| Error::Readback(format!("failed to map the buffer: {err}")))?;
+
+        let data = slice
+            .get_mapped_range()
+            .map_err(|err| Error::Readback(err.to_string()))?;
+        Ok(data
+            .chunks(self.padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..self.unpadded_bytes_per_row as usize])
+            .copied()
+            .collect())
+    }
+
+    pub fn save(self, ctx: &GpuContext, path: &Path) -> Result<()> {
+        let (width, height, format) = (self.width, self.height, self.format);
+        let pixels = self.read(ctx)?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                write_png(path, width, height, &pixels, format.is_srgb())
+            }
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+                let rgba: Vec<u8> = pixels
+                    .chunks(4)
+                    .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+                    .collect();
+                write_png(path, width, height, &rgba, format.is_srgb())
+            }
+            TextureFormat::Rgba16Float => {
+                let texels: Vec<f32> = pixels
+                    .chunks(2)
+                    .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]).to_f32())
+                    .collect();
+                write_exr(path, width, height, &texels)
+            }
+            TextureFormat::Rgba32Float => {
+                let texels: Vec<f32> = pixels
+                    .chunks(4)
+                    .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect();
+                write_exr(path, width, height, &texels)
+            }
+            other => Err(Error::UnsupportedFormat(other)),
+        }
+    }
+}
+
+pub fn save_texture(ctx: &GpuContext, texture: &Texture, path: &Path) -> Result<()> {
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+    let readback = TextureReadback::new(ctx, texture, &mut encoder)?;
+    ctx.queue.submit([encoder.finish()]);
+    readback.save(ctx, path)
+}
+
+pub fn file_extension(format: TextureFormat) -> &'static str {
+    match format {
+        TextureFormat::Rgba16Float | TextureFormat::Rgba32Float => "exr",
+        _ => "png",
+    }
+}
+
+pub fn capture_path(name: &str, format: TextureFormat) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from("screenshots").join(format!("{name}-{timestamp}.{}", file_extension(format)))
+}
+
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8], srgb: bool) -> Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    if srgb {
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    }
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(())
+}
+
+fn write_exr(path: &Path, width: u32, height: u32, texels: &[f32]) -> Result<()> {
+    exr::prelude::write_rgba_file(path, width as usize, height as usize, |x, y| {
+        let i = (y * width as usize + x) * 4;
+        (texels[i], texels[i + 1], texels[i + 2], texels[i + 3])
+    })?;
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use wgpu::{Backends, CreateSurfaceError, PollError, RequestDeviceError, TextureFormat};
+use winit::error::{EventLoopError, OsError};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    EventLoop(EventLoopError),
+    CreateWindow(OsError),
+    CreateSurface(CreateSurfaceError),
+    NoAdapter {
+        backends: Backends,
+        name: Option<String>,
+        available: Vec<String>,
+    },
+    RequestDevice(RequestDeviceError),
+    NoSurfaceFormat,
+    Wgpu(wgpu::Error),
+    Poll(PollError),
+    UnsupportedFormat(TextureFormat),
+    Readback(String),
+    Shader {
+        path: String,
+        message: String,
+    },
+    Binding {
+        label: String,
+        message: String,
+    },
+    Layout {
+        name: String,
+        diff: String,
+    },
+    Model {
+        path: String,
+        message: String,
+    },
+    Image {
+        path: String,
+        message: String,
+    },
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    Exr(exr::error::Error),
+    Replay(serde_json::Error),
+    InputBindings {
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EventLoop(err) => write!(f, "Failed to run event loop: {err}"),
+            Self::CreateWindow(err) => write!(f, "Failed to create window: {err}"),
+            Self::CreateSurface(err) => write!(f, "Failed to create surface: {err}"),
+            Self::NoAdapter {
+                backends,
+                name,
+                available,
+            } => {
+                write!(f, "No suitable adapter found (backends: {backends:?}")?;
+                if let Some(name) = name {
+                    write!(f, ", name: {name:?}")?;
+                }
+                write!(f, "). Available adapters:")?;
+                if available.is_empty() {
+                    write!(f, " none")?;
+                }
+                for adapter in available {
+                    write!(f, "\n  - {adapter}")?;
+                }
+                Ok(())
+            }
+            Self::RequestDevice(err) => write!(f, "Failed to request device: {err}"),
+            Self::NoSurfaceFormat => write!(f, "Surface doesn't support any texture format"),
+            Self::Wgpu(err) => write!(f, "wgpu error: {err}"),
+            Self::Poll(err) => write!(f, "Failed to wait for the GPU: {err}"),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "Capturing {format:?} textures is not supported")
+            }
+            Self::Readback(message) => write!(f, "Failed to read back texture: {message}"),
+            Self::Shader { path, message } => write!(f, "Invalid shader {path}:\n{message}"),
+            Self::Binding { label, message } => write!(f, "Invalid binding in {label}: {message}"),
+            Self::Layout { name, diff } => write!(f, "Struct layout mismatch in {name}:\n{diff}"),
+            Self::Model { path, message } => write!(f, "Invalid model {path}: {message}"),
+            Self::Image { path, message } => write!(f, "Invalid image {path}: {message}"),
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::Png(err) => write!(f, "Failed to encode PNG: {err}"),
+            Self::Exr(err) => write!(f, "Failed to encode OpenEXR: {err}"),
+            Self::Replay(err) => write!(f, "Invalid replay file: {err}"),
+            Self::InputBindings { path, message } => {
+                write!(f, "Invalid input bindings {path}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::EventLoop(err) => Some(err),
+            Self::CreateWindow(err) => Some(err),
+            Self::CreateSurface(err) => Some(err),
+            Self::RequestDevice(err) => Some(err),
+            Self::Wgpu(err) => Some(err),
+            Self::Poll(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::Png(err) => Some(err),
+            Self::Exr(err) => Some(err),
+            Self::Replay(err) => Some(err),
+            Self::NoAdapter { .. }
+            | Self::NoSurfaceFormat
+            | Self::UnsupportedFormat(_)
+            | Self::Readback(_)
+            | Self::Shader { .. }
+            | Self::Binding { .. }
+            | Self::Layout { .. }
+            | Self::Model { .. }
+            | Self::Image { .. }
+            | Self::InputBindings { .. } => None,
+        }
+    }
+}
+
+impl From<EventLoopError> for Error {
+    fn from(err: EventLoopError) -> Self {
+        Self::EventLoop(err)
+    }
+}
+
+impl From<OsError> for Error {
+    fn from(err: OsError) -> Self {
+        Self::CreateWindow(err)
+    }
+}
+
+impl From<CreateSurfaceError> for Error {
+    fn from(err: CreateSurfaceError) -> Self {
+        Self::CreateSurface(err)
+    }
+}
+
+impl From<RequestDeviceError> for Error {
+    fn from(err: RequestDeviceError) -> Self {
+        Self::RequestDevice(err)
+    }
+}
+
+impl From<wgpu::Error> for Error {
+    fn from(err: wgpu::Error) -> Self {
+        Self::Wgpu(err)
+    }
+}
+
+impl From<PollError> for Error {
+    fn from(err: PollError) -> Self {
+        Self::Poll(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(err: png::EncodingError) -> Self {
+        Self::Png(err)
+    }
+}
+
+impl From<exr::error::Error> for Error {
+    fn from(err: exr::error::Error) -> Self {
+        Self::Exr(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Replay(err)
+    }
+}
@@ -1,14 +1,78 @@
 use std::time::Duration;
 
-use wgpu::{CommandEncoder, TextureView};
+use wgpu::{CommandEncoder, ErrorFilter, Texture, TextureView};
 use winit::dpi::PhysicalSize;
 
 use crate::GpuContext;
-use crate::Input;
+use crate::error::{Error, Result};
+use crate::input::{Bindings, Input};
+use crate::ui::Ui;
 
 pub trait Example: 'static {
-    fn init(ctx: &GpuContext) -> Self;
+    fn init(ctx: &GpuContext) -> Result<Self>
+    where
+        Self: Sized;
+    // Called after the device was lost and `ctx` recreated; all GPU resources must be rebuilt
+    fn recreate(&mut self, ctx: &GpuContext) -> Result<()>
+    where
+        Self: Sized,
+    {
+        *self = Self::try_init(ctx)?;
+        Ok(())
+    }
     fn resize(&mut self, _ctx: &GpuContext, _new_size: PhysicalSize<u32>) {}
+    // Default keys for the example's own actions, usually with `Bindings::define` so the bindings
+    // file still wins. Runs again whenever that file is reloaded
+    fn bindings(_bindings: &mut Bindings)
+    where
+        Self: Sized,
+    {
+    }
+    // Runs right before `update`, while the parameter panel is visible (toggled with F1)
+    fn ui(&mut self, _ui: &mut Ui) {}
     fn update(&mut self, _ctx: &GpuContext, _dt: Duration, _input: &Input) {}
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder);
+    // Called after a watched WGSL file changed and passed validation. Examples rebuild their
+    // pipelines from the reloaded modules here and keep everything else. The default rebuilds the
+    // whole example, resetting the camera and UI state, so it's only a last resort. On error the
+    // old pipelines are kept and the error is shown on screen
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()>
+    where
+        Self: Sized,
+    {
+        *self = Self::try_init(ctx)?;
+        Ok(())
+    }
+    fn capture_targets(&self) -> Vec<(&'static str, &Texture)> {
+        Vec::new()
+    }
+}
+
+pub trait TryExample: Sized {
+    fn try_init(ctx: &GpuContext) -> Result<Self>;
+    fn try_reload_shaders(&mut self, ctx: &GpuContext) -> Result<()>;
+}
+
+// `init` and `reload_shaders` with the wgpu validation errors they raise returned too, instead of
+// only logged. Errors from the call itself come first
+impl<E: Example> TryExample for E {
+    fn try_init(ctx: &GpuContext) -> Result<Self> {
+        validated(ctx, || E::init(ctx))
+    }
+
+    fn try_reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        validated(ctx, || self.reload_shaders(ctx))
+    }
+}
+
+fn validated<T>(ctx: &GpuContext, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let scope = ctx.device.push_error_scope(ErrorFilter::Validation);
+    let result = f();
+    let validation = pollster::block_on(scope.pop());
+
+    let value = result?;
+    match validation {
+        Some(err) => Err(Error::Wgpu(err)),
+        None => Ok(value),
+    }
 }
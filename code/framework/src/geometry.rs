@@ -1,3 +1,10 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::mesh::{Indices, Mesh, MeshVertex, generate_tangents};
+
 pub const CUBE_POSITIONS: [[f32; 3]; 24] = [
     [-0.5, -0.5, 0.5],
     [0.5, -0.5, 0.5],
@@ -83,3 +90,320 @@ pub const CUBE_INDICES: [u16; 36] = [
     0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15, 12, 16, 17, 18,
     18, 19, 16, 20, 21, 22, 22, 23, 20,
 ];
+
+// Procedural meshes. Shapes are centered on the origin with Y up, triangles wind counter-clockwise
+// seen from outside. Around Y the seam sits at -Z and u grows to the right seen from +Z, v grows
+// downwards like in image files
+
+pub fn plane(width: f32, depth: f32) -> Mesh {
+    grid(width, depth, 1, 1)
+}
+
+// A plane on XZ facing +Y, split into `columns` x `rows` quads
+pub fn grid(width: f32, depth: f32, columns: u32, rows: u32) -> Mesh {
+    let mut vertices = Vec::new();
+    for row in 0..=rows {
+        let v = row as f32 / rows as f32;
+        for column in 0..=columns {
+            let u = column as f32 / columns as f32;
+            vertices.push(vertex(
+                Vec3::new((u - 0.5) * width, 0.0, (v - 0.5) * depth),
+                Vec3::Y,
+                [u, v],
+            ));
+        }
+    }
+    let indices = grid_indices(rows + 1, columns, false);
+    finish("Grid", vertices, indices)
+}
+
+// `slices` around Y, `stacks` from pole to pole
+pub fn uv_sphere(radius: f32, slices: u32, stacks: u32) -> Mesh {
+    let mut vertices = Vec::new();
+    for stack in 0..=stacks {
+        let v = stack as f32 / stacks as f32;
+        ring(&mut vertices, slices, v, |theta| {
+            let normal = spherical(theta, v * PI);
+            (normal * radius, normal)
+        });
+    }
+    let indices = grid_indices(stacks + 1, slices, true);
+    finish("UV Sphere", vertices, indices)
+}
+
+// Subdivided icosahedron: triangles of nearly equal size, no crowding at the poles
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut positions: Vec<Vec3> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .iter()
+    .map(|&p| Vec3::from(p).normalize())
+    .collect();
+    let mut triangles = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let p = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(p);
+                positions.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    // Spherical UVs need the vertices on the seam doubled and the poles split per triangle
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(triangles.len() * 3);
+    let mut lookup = HashMap::new();
+    for triangle in &triangles {
+        let corners = triangle.map(|i| positions[i as usize]);
+        let is_pole = corners.map(|p| p.x.abs() < 1e-6 && p.z.abs() < 1e-6);
+        let u = corners.map(|p| (f32::atan2(-p.x, -p.z) / TAU).rem_euclid(1.0));
+        let others: Vec<f32> = (0..3).filter(|&i| !is_pole[i]).map(|i| u[i]).collect();
+        let (min, max) = others
+            .iter()
+            .fold((1.0f32, 0.0f32), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+        // A triangle across the seam continues past u = 1 instead of spanning the whole texture
+        let wraps = max - min > 0.5;
+        let u = u.map(|u| if wraps && u < 0.5 { u + 1.0 } else { u });
+        let pole_u =
+            (0..3).filter(|&i| !is_pole[i]).map(|i| u[i]).sum::<f32>() / others.len() as f32;
+
+        for i in 0..3 {
+            let u = if is_pole[i] { pole_u } else { u[i] };
+            let index = *lookup.entry((triangle[i], u.to_bits())).or_insert_with(|| {
+                let p = corners[i];
+                vertices.push(vertex(p * radius, p, [u, p.y.clamp(-1.0, 1.0).acos() / PI]));
+                vertices.len() as u32 - 1
+            });
+            indices.push(index);
+        }
+    }
+    finish("Icosphere", vertices, indices)
+}
+
+// Tube with flat caps, the caps get their own vertices for a hard edge
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Mesh {
+    let mut vertices = Vec::new();
+    for (v, y) in [(0.0, height / 2.0), (1.0, -height / 2.0)] {
+        ring(&mut vertices, segments, v, |theta| {
+            let normal = spherical(theta, FRAC_PI_2);
+            (normal * radius + Vec3::Y * y, normal)
+        });
+    }
+    let mut indices = grid_indices(2, segments, false);
+    cap(
+        &mut vertices,
+        &mut indices,
+        radius,
+        height / 2.0,
+        segments,
+        true,
+    );
+    cap(
+        &mut vertices,
+        &mut indices,
+        radius,
+        -height / 2.0,
+        segments,
+        false,
+    );
+    finish("Cylinder", vertices, indices)
+}
+
+pub fn cone(radius: f32, height: f32, segments: u32) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let slope = |theta: f32| {
+        let outward = spherical(theta, FRAC_PI_2);
+        (outward * height + Vec3::Y * radius).normalize()
+    };
+    // Every side triangle gets its own apex with the normal halfway between its edges
+    for segment in 0..segments {
+        let u = (segment as f32 + 0.5) / segments as f32;
+        vertices.push(vertex(Vec3::Y * height / 2.0, slope(u * TAU), [u, 0.0]));
+    }
+    ring(&mut vertices, segments, 1.0, |theta| {
+        let position = spherical(theta, FRAC_PI_2) * radius - Vec3::Y * height / 2.0;
+        (position, slope(theta))
+    });
+    for segment in 0..segments {
+        let base = segments + segment;
+        indices.extend([segment, base, base + 1]);
+    }
+    cap(
+        &mut vertices,
+        &mut indices,
+        radius,
+        -height / 2.0,
+        segments,
+        false,
+    );
+    finish("Cone", vertices, indices)
+}
+
+// Ring of `major_segments` around Y, tube of `minor_segments` starting at the outer equator
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> Mesh {
+    let mut vertices = Vec::new();
+    for minor in 0..=minor_segments {
+        let v = minor as f32 / minor_segments as f32;
+        let phi = v * TAU;
+        ring(&mut vertices, major_segments, v, |theta| {
+            let outward = spherical(theta, FRAC_PI_2);
+            let normal = outward * phi.cos() - Vec3::Y * phi.sin();
+            (outward * major_radius + normal * minor_radius, normal)
+        });
+    }
+    let indices = grid_indices(minor_segments + 1, major_segments, false);
+    finish("Torus", vertices, indices)
+}
+
+// Cylinder of `height` between two hemispheres of `rings` each, so the total height is
+// `height + 2 * radius`. v follows the arc length to keep the texture undistorted
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let length = PI * radius + height;
+    let mut vertices = Vec::new();
+    for (y, start, arc_start) in [(height / 2.0, 0.0, 0.0), (-height / 2.0, FRAC_PI_2, height)] {
+        for step in 0..=rings {
+            let phi = start + FRAC_PI_2 * step as f32 / rings as f32;
+            let v = (phi * radius + arc_start) / length;
+            ring(&mut vertices, segments, v, |theta| {
+                let normal = spherical(theta, phi);
+                (normal * radius + Vec3::Y * y, normal)
+            });
+        }
+    }
+    let indices = grid_indices(2 * rings + 2, segments, true);
+    finish("Capsule", vertices, indices)
+}
+
+// Unit vector at `theta` around Y (0 = -Z) and `phi` from +Y
+fn spherical(theta: f32, phi: f32) -> Vec3 {
+    Vec3::new(
+        -theta.sin() * phi.sin(),
+        phi.cos(),
+        -theta.cos() * phi.sin(),
+    )
+}
+
+fn vertex(position: Vec3, normal: Vec3, uv: [f32; 2]) -> MeshVertex {
+    MeshVertex {
+        position: position.to_array(),
+        normal: normal.to_array(),
+        uv,
+        tangent: [0.0; 4],
+    }
+}
+
+// `segments + 1` vertices around Y at row `v`, the last one closes the seam with u = 1
+fn ring(vertices: &mut Vec<MeshVertex>, segments: u32, v: f32, at: impl Fn(f32) -> (Vec3, Vec3)) {
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let (position, normal) = at(u * TAU);
+        vertices.push(vertex(position, normal, [u, v]));
+    }
+}
+
+// Two triangles per quad between consecutive rows of `columns + 1` vertices. With `poles` the
+// first and last rows collapse to a point and their degenerate triangles are dropped
+fn grid_indices(rows: u32, columns: u32, poles: bool) -> Vec<u32> {
+    let mut indices = Vec::new();
+    for row in 0..rows - 1 {
+        for column in 0..columns {
+            let top = row * (columns + 1) + column;
+            let bottom = top + columns + 1;
+            if !poles || row + 2 < rows {
+                indices.extend([top, bottom, bottom + 1]);
+            }
+            if !poles || row > 0 {
+                indices.extend([top, bottom + 1, top + 1]);
+            }
+        }
+    }
+    indices
+}
+
+// Flat disc at height `y` facing up or down, UVs map the disc onto the whole texture
+fn cap(
+    vertices: &mut Vec<MeshVertex>,
+    indices: &mut Vec<u32>,
+    radius: f32,
+    y: f32,
+    segments: u32,
+    up: bool,
+) {
+    let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+    let center = vertices.len() as u32;
+    vertices.push(vertex(Vec3::Y * y, normal, [0.5, 0.5]));
+    for segment in 0..segments {
+        let offset = spherical(segment as f32 / segments as f32 * TAU, FRAC_PI_2);
+        let uv = Vec2::new(offset.x, if up { offset.z } else { -offset.z }) * 0.5 + 0.5;
+        vertices.push(vertex(offset * radius + Vec3::Y * y, normal, uv.to_array()));
+    }
+    for segment in 0..segments {
+        let a = center + 1 + segment;
+        let b = center + 1 + (segment + 1) % segments;
+        if up {
+            indices.extend([center, a, b]);
+        } else {
+            indices.extend([center, b, a]);
+        }
+    }
+}
+
+fn finish(name: &str, mut vertices: Vec<MeshVertex>, indices: Vec<u32>) -> Mesh {
+    generate_tangents(&mut vertices, &indices);
+    Mesh {
+        name: name.to_owned(),
+        indices: Indices::new(indices, vertices.len()),
+        vertices,
+        transform: Mat4::IDENTITY,
+        material: None,
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::warn;
+use winit::dpi::PhysicalSize;
+
+use crate::Input;
+use crate::app::{HEADLESS_FRAME_TIME, run_headless_with};
+use crate::capture;
+use crate::example::Example;
+use crate::texture::read_texture;
+
+pub struct GoldenConfig {
+    pub size: PhysicalSize<u32>,
+    pub frames: u32,
+    pub frame_time: Duration,
+    pub input: Input,
+    // Per-pixel YIQ distance in 0..1 below which pixels are considered equal
+    pub threshold: f32,
+    // Fraction of pixels that may exceed `threshold` before the test fails
+    pub max_diff_ratio: f32,
+}
+
+impl Default for GoldenConfig {
+    fn default() -> Self {
+        Self {
+            size: PhysicalSize::new(320, 240),
+            frames: 3,
+            frame_time: HEADLESS_FRAME_TIME,
+            input: Input::default(),
+            threshold: 0.1,
+            max_diff_ratio: 0.001,
+        }
+    }
+}
+
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub fn render_golden<E: Example>(config: &GoldenConfig) -> Image {
+    let (ctx, _example) =
+        run_headless_with::<E>(config.size, config.frames, config.frame_time, &config.input);
+    let texture = ctx
+        .offscreen
+        .as_ref()
+        .expect("Headless context has no offscreen target");
+
+    Image {
+        width: texture.width(),
+        height: texture.height(),
+        pixels: read_texture(&ctx, texture).expect("Failed to read headless frame"),
+    }
+}
+
+pub fn assert_golden<E: Example>(golden: impl AsRef<Path>, config: &GoldenConfig) {
+    let golden = golden.as_ref();
+    let actual = render_golden::<E>(config);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        warn!("Writing golden image {}", golden.display());
+        write_png(golden, &actual);
+        return;
+    }
+    if !golden.exists() {
+        let actual_path = sibling(golden, "actual");
+        write_png(&actual_path, &actual);
+        panic!(
+            "Golden image {} is missing (rendered {}), run with UPDATE_GOLDEN=1 to create it",
+            golden.display(),
+            actual_path.display(),
+        );
+    }
+
+    let expected = read_png(golden);
+    let actual_path = sibling(golden, "actual");
+    let diff_path = sibling(golden, "diff");
+
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        write_png(&actual_path, &actual);
+        panic!(
+            "Golden image {} is {}x{}, rendered {}x{} (see {})",
+            golden.display(),
+            expected.width,
+            expected.height,
+            actual.width,
+            actual.height,
+            actual_path.display(),
+        );
+    }
+
+    let (diff, diff_count) = compare(&expected, &actual, config.threshold);
+    let total = (actual.width * actual.height) as f32;
+
+    if diff_count as f32 > total * config.max_diff_ratio {
+        write_png(&actual_path, &actual);
+        write_png(&diff_path, &diff);
+        panic!(
+            "{diff_count} of {total} pixels differ from golden image {} (see {})",
+            golden.display(),
+            diff_path.display(),
+        );
+    }
+
+    let _ = std::fs::remove_file(actual_path);
+    let _ = std::fs::remove_file(diff_path);
+}
+
+pub fn compare(expected: &Image, actual: &Image, threshold: f32) -> (Image, usize) {
+    // Squared threshold scaled by the largest possible YIQ distance, as in pixelmatch
+    let max_delta = 35215.0 * threshold * threshold;
+    let mut pixels = Vec::with_capacity(actual.pixels.len());
+    let mut diff_count = 0;
+
+    for (a, b) in expected.pixels.chunks(4).zip(actual.pixels.chunks(4)) {
+        if yiq_delta(a, b) > max_delta {
+            diff_count += 1;
+            pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = (255.0 - 0.1 * (255.0 - luma(a))) as u8;
+            pixels.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+
+    let diff = Image {
+        width: actual.width,
+        height: actual.height,
+        pixels,
+    };
+    (diff, diff_count)
+}
+
+fn blend_white(pixel: &[u8]) -> [f32; 3] {
+    let alpha = pixel[3] as f32 / 255.0;
+    [0, 1, 2].map(|i| 255.0 + (pixel[i] as f32 - 255.0) * alpha)
+}
+
+fn luma(pixel: &[u8]) -> f32 {
+    let [r, g, b] = blend_white(pixel);
+    r * 0.2988953 + g * 0.5866225 + b * 0.1144822
+}
+
+fn yiq_delta(a: &[u8], b: &[u8]) -> f32 {
+    let [r1, g1, b1] = blend_white(a);
+    let [r2, g2, b2] = blend_white(b);
+    let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+
+    let y = dr * 0.2988953 + dg * 0.5866225 + db * 0.1144822;
+    let i = dr * 0.595978 - dg * 0.2741761 - db * 0.3218019;
+    let q = dr * 0.2114702 - dg * 0.5226171 + db * 0.3111469;
+
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+pub fn read_png(path: &Path) -> Image {
+    let file = File::open(path).expect("Failed to open PNG");
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::ALPHA);
+    let mut reader = decoder.read_info().expect("Failed to read PNG header");
+    let mut pixels = vec![0; reader.output_buffer_size().expect("PNG is too large")];
+    let info = reader
+        .next_frame(&mut pixels)
+        .expect("Failed to decode PNG");
+    pixels.truncate(info.buffer_size());
+
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::Rgba, png::BitDepth::Eight),
+        "Golden images must be 8-bit RGBA"
+    );
+
+    Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    }
+}
+
+pub fn write_png(path: &Path, image: &Image) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).expect("Failed to create image directory");
+    }
+
+    capture::write_png(path, image.width, image.height, &image.pixels, true)
+        .expect("Failed to write PNG");
+}
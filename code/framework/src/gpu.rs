@@ -1,6 +1,7 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use tracing::warn;
+use tracing::{error, info, warn};
 use wgpu::CurrentSurfaceTexture::{
     Lost, Occluded, Outdated, Suboptimal, Success, Timeout, Validation,
 };
@@ -8,66 +9,281 @@ use wgpu::*;
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
+use crate::error::{Error, Result};
+use crate::overlay::Overlay;
+use crate::profiler::GpuProfiler;
+use crate::texture::MipmapPipelines;
+
 pub struct GpuContext {
     pub device: Device,
     pub queue: Queue,
-    pub surface: Surface<'static>,
+    pub surface: Option<Surface<'static>>,
+    pub offscreen: Option<Texture>,
     pub surface_config: SurfaceConfiguration,
     pub surface_format: TextureFormat,
+    pub profiler: GpuProfiler,
+    pub overlay: Overlay,
+    pub(crate) mipmap_pipelines: MipmapPipelines,
+    builder: GpuContextBuilder,
+    window: Option<Arc<Window>>,
+    lost: Arc<AtomicBool>,
 }
 
 impl GpuContext {
     pub fn new(window: Arc<Window>) -> Self {
+        Self::try_new(window).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_new(window: Arc<Window>) -> Result<Self> {
+        GpuContextBuilder::new().try_build(window)
+    }
+
+    pub fn new_headless(size: PhysicalSize<u32>, format: TextureFormat) -> Self {
+        Self::try_new_headless(size, format).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_new_headless(size: PhysicalSize<u32>, format: TextureFormat) -> Result<Self> {
+        GpuContextBuilder::new()
+            // GL is included so that headless runs can fall back to llvmpipe through EGL
+            .backends(Backends::PRIMARY | Backends::GL)
+            .with_env()
+            .try_build_headless(size, format)
+    }
+
+    // Set from the device-lost callback, e.g. after a driver reset or `Device::destroy`
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    // Rebuilds the adapter, device, queue and surface with the settings of the original builder
+    pub fn recreate(&mut self) -> Result<()> {
+        let builder = self.builder.clone();
+        // The old context stays in place until the new one is built, so a failed attempt can be retried
+        *self = match self.window.clone() {
+            Some(window) => builder.try_build(window)?,
+            None => {
+                let size = PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+                builder.try_build_headless(size, self.surface_format)?
+            }
+        };
+        Ok(())
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.surface_config.width = size.width.max(1);
+        self.surface_config.height = size.height.max(1);
+
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
+        if self.offscreen.is_some() {
+            self.offscreen = Some(create_offscreen_texture(&self.device, &self.surface_config));
+        }
+    }
+
+    pub fn acquire_frame(
+        &mut self,
+    ) -> Option<(Option<SurfaceTexture>, TextureView, CommandEncoder)> {
+        let (frame, view) = match (&self.surface, &self.offscreen) {
+            (Some(surface), _) => {
+                let frame = match surface.get_current_texture() {
+                    Success(frame) => frame,
+                    Suboptimal(frame) => {
+                        warn!("Surface suboptimal, reconfiguring");
+                        surface.configure(&self.device, &self.surface_config);
+                        frame
+                    }
+                    Outdated | Lost => {
+                        warn!("Surface lost or outdated, reconfiguring");
+                        surface.configure(&self.device, &self.surface_config);
+                        return None;
+                    }
+                    Timeout | Occluded => return None,
+                    Validation => {
+                        warn!("Surface texture validation error");
+                        return None;
+                    }
+                };
+                let view = frame.texture.create_view(&TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            (None, Some(texture)) => (None, texture.create_view(&TextureViewDescriptor::default())),
+            (None, None) => return None,
+        };
+
+        let encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Main command encoder"),
+            });
+
+        Some((frame, view, encoder))
+    }
+}
+
+#[derive(Clone)]
+pub struct GpuContextBuilder {
+    backends: Backends,
+    power_preference: PowerPreference,
+    adapter_name: Option<String>,
+    present_mode: PresentMode,
+    desired_maximum_frame_latency: u32,
+    features: Option<Features>,
+    limits: Option<Limits>,
+    surface_format: Option<TextureFormat>,
+}
+
+impl Default for GpuContextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuContextBuilder {
+    pub fn new() -> Self {
+        Self {
+            backends: Backends::PRIMARY,
+            power_preference: PowerPreference::default(),
+            adapter_name: None,
+            present_mode: PresentMode::AutoVsync,
+            desired_maximum_frame_latency: 2,
+            features: None,
+            limits: None,
+            surface_format: None,
+        }
+    }
+
+    pub fn backends(mut self, backends: Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    // Case-insensitive substring of the adapter name, e.g. "llvmpipe"
+    pub fn adapter_name(mut self, name: impl Into<String>) -> Self {
+        self.adapter_name = Some(name.into());
+        self
+    }
+
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn desired_maximum_frame_latency(mut self, frames: u32) -> Self {
+        self.desired_maximum_frame_latency = frames;
+        self
+    }
+
+    pub fn features(mut self, features: Features) -> Self {
+        self.features = Some(features);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    pub fn surface_format(mut self, format: TextureFormat) -> Self {
+        self.surface_format = Some(format);
+        self
+    }
+
+    // Reads WGPU_BACKEND, WGPU_POWER_PREF and WGPU_ADAPTER_NAME
+    pub fn with_env(mut self) -> Self {
+        self.backends = self.backends.with_env();
+        if let Some(power_preference) = PowerPreference::from_env() {
+            self.power_preference = power_preference;
+        }
+        if let Ok(name) = std::env::var("WGPU_ADAPTER_NAME") {
+            self.adapter_name = Some(name);
+        }
+        self
+    }
+
+    // Reads --backend=, --power-preference=, --adapter=, --present-mode= and --frame-latency=
+    pub fn with_args(mut self) -> Self {
+        for arg in std::env::args().skip(1) {
+            let Some((key, value)) = arg.split_once('=') else {
+                continue;
+            };
+            match key {
+                "--backend" => self.backends = Backends::from_comma_list(value),
+                "--power-preference" => match value {
+                    "low" => self.power_preference = PowerPreference::LowPower,
+                    "high" => self.power_preference = PowerPreference::HighPerformance,
+                    "none" => self.power_preference = PowerPreference::None,
+                    _ => warn!("Unknown power preference {value:?}"),
+                },
+                "--adapter" => self.adapter_name = Some(value.to_owned()),
+                "--present-mode" => match parse_present_mode(value) {
+                    Some(present_mode) => self.present_mode = present_mode,
+                    None => warn!("Unknown present mode {value:?}"),
+                },
+                "--frame-latency" => match value.parse() {
+                    Ok(frames) => self.desired_maximum_frame_latency = frames,
+                    Err(_) => warn!("Invalid frame latency {value:?}"),
+                },
+                _ => {}
+            }
+        }
+        self
+    }
+
+    pub fn build(self, window: Arc<Window>) -> GpuContext {
+        self.try_build(window).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_build(self, window: Arc<Window>) -> Result<GpuContext> {
         let mut physical_size = window.inner_size();
         physical_size.width = physical_size.width.max(1);
         physical_size.height = physical_size.height.max(1);
 
-        let instance = Instance::new(InstanceDescriptor {
-            backends: Backends::PRIMARY,
-            ..InstanceDescriptor::new_without_display_handle()
-        });
+        let instance = self.create_instance();
 
-        let surface = instance
-            .create_surface(window)
-            .expect("Failed to create surface");
-
-        let adapter = pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
-            power_preference: PowerPreference::default(),
-            force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
-            ..Default::default()
-        }))
-        .expect("Failed to request adapter");
+        let surface = instance.create_surface(window.clone())?;
 
-        let (device, queue) = pollster::block_on(adapter.request_device(&DeviceDescriptor {
-            label: Some("Main device"),
-            required_features: adapter.features()
-                - Features::all_experimental_mask()
-                - Features::MAPPABLE_PRIMARY_BUFFERS,
-            required_limits: Limits::default().using_resolution(adapter.limits()),
-            memory_hints: MemoryHints::Performance,
-            trace: Default::default(),
-            experimental_features: ExperimentalFeatures::disabled(),
-        }))
-        .expect("Failed to request device");
+        let adapter = self.request_adapter(&instance, Some(&surface))?;
+        let (device, queue, lost) = self.request_device(&adapter)?;
 
         let surface_capabilities = surface.get_capabilities(&adapter);
 
-        let surface_format = surface_capabilities
-            .formats
-            .iter()
-            .copied()
-            .find(TextureFormat::is_srgb)
+        let surface_format = self
+            .surface_format
+            .filter(|format| surface_capabilities.formats.contains(format))
+            .or_else(|| {
+                surface_capabilities
+                    .formats
+                    .iter()
+                    .copied()
+                    .find(TextureFormat::is_srgb)
+            })
             .or_else(|| surface_capabilities.formats.first().copied())
-            .expect("Failed to get surface format");
+            .ok_or(Error::NoSurfaceFormat)?;
+
+        let present_mode = match self.present_mode {
+            PresentMode::AutoVsync | PresentMode::AutoNoVsync => self.present_mode,
+            mode if surface_capabilities.present_modes.contains(&mode) => mode,
+            mode => {
+                warn!("Present mode {mode:?} is not supported, falling back to AutoVsync");
+                PresentMode::AutoVsync
+            }
+        };
 
         let surface_config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            // COPY_SRC is needed to capture presented frames, but not every surface supports it
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | (surface_capabilities.usages & TextureUsages::COPY_SRC),
             format: surface_format,
             width: physical_size.width,
             height: physical_size.height,
-            present_mode: PresentMode::AutoVsync,
-            desired_maximum_frame_latency: 2,
+            present_mode,
+            desired_maximum_frame_latency: self.desired_maximum_frame_latency,
             alpha_mode: CompositeAlphaMode::Auto,
             view_formats: vec![],
             color_space: wgpu::SurfaceColorSpace::Auto,
@@ -75,49 +291,179 @@ impl GpuContext {
 
         surface.configure(&device, &surface_config);
 
-        Self {
+        let profiler = GpuProfiler::new(&device, &queue);
+        let overlay = Overlay::new(&device, &queue, surface_format);
+
+        Ok(GpuContext {
+            profiler,
+            overlay,
+            mipmap_pipelines: MipmapPipelines::default(),
             device,
             queue,
-            surface,
+            surface: Some(surface),
+            offscreen: None,
             surface_config,
             surface_format,
-        }
+            builder: self,
+            window: Some(window),
+            lost,
+        })
     }
 
-    pub fn resize(&mut self, size: PhysicalSize<u32>) {
-        self.surface_config.width = size.width.max(1);
-        self.surface_config.height = size.height.max(1);
-        self.surface.configure(&self.device, &self.surface_config);
+    pub fn build_headless(self, size: PhysicalSize<u32>, format: TextureFormat) -> GpuContext {
+        self.try_build_headless(size, format)
+            .unwrap_or_else(|err| panic!("{err}"))
     }
 
-    pub fn acquire_frame(&mut self) -> Option<(SurfaceTexture, TextureView, CommandEncoder)> {
-        let frame = match self.surface.get_current_texture() {
-            Success(frame) => frame,
-            Suboptimal(frame) => {
-                warn!("Surface suboptimal, reconfiguring");
-                self.surface.configure(&self.device, &self.surface_config);
-                frame
-            }
-            Outdated | Lost => {
-                warn!("Surface lost or outdated, reconfiguring");
-                self.surface.configure(&self.device, &self.surface_config);
-                return None;
-            }
-            Timeout | Occluded => return None,
-            Validation => {
-                warn!("Surface texture validation error");
-                return None;
+    pub fn try_build_headless(
+        self,
+        size: PhysicalSize<u32>,
+        format: TextureFormat,
+    ) -> Result<GpuContext> {
+        let instance = self.create_instance();
+        let adapter = self.request_adapter(&instance, None)?;
+        let (device, queue, lost) = self.request_device(&adapter)?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: self.present_mode,
+            desired_maximum_frame_latency: self.desired_maximum_frame_latency,
+            alpha_mode: CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            color_space: wgpu::SurfaceColorSpace::Auto,
+        };
+
+        let offscreen = create_offscreen_texture(&device, &surface_config);
+
+        let profiler = GpuProfiler::new(&device, &queue);
+        let overlay = Overlay::new(&device, &queue, format);
+
+        Ok(GpuContext {
+            profiler,
+            overlay,
+            mipmap_pipelines: MipmapPipelines::default(),
+            device,
+            queue,
+            surface: None,
+            offscreen: Some(offscreen),
+            surface_config,
+            surface_format: format,
+            builder: self,
+            window: None,
+            lost,
+        })
+    }
+
+    fn create_instance(&self) -> Instance {
+        Instance::new(InstanceDescriptor {
+            backends: self.backends,
+            ..InstanceDescriptor::new_without_display_handle()
+        })
+    }
+
+    fn request_adapter(
+        &self,
+        instance: &Instance,
+        surface: Option<&Surface<'static>>,
+    ) -> Result<Adapter> {
+        let adapter = match &self.adapter_name {
+            Some(name) => {
+                let name = name.to_lowercase();
+                pollster::block_on(instance.enumerate_adapters(self.backends))
+                    .into_iter()
+                    .filter(|adapter| surface.is_none_or(|s| adapter.is_surface_supported(s)))
+                    .find(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
             }
+            None => pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
+                power_preference: self.power_preference,
+                force_fallback_adapter: false,
+                compatible_surface: surface,
+                ..Default::default()
+            }))
+            .ok(),
         };
 
-        let view = frame.texture.create_view(&TextureViewDescriptor::default());
+        adapter.ok_or_else(|| Error::NoAdapter {
+            backends: self.backends,
+            name: self.adapter_name.clone(),
+            available: available_adapters(instance, self.backends),
+        })
+    }
 
-        let encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Main command encoder"),
-            });
+    fn request_device(&self, adapter: &Adapter) -> Result<(Device, Queue, Arc<AtomicBool>)> {
+        let info = adapter.get_info();
+        info!("Using adapter {} ({:?})", info.name, info.backend);
 
-        Some((frame, view, encoder))
+        let required_features = self.features.unwrap_or_else(|| {
+            adapter.features()
+                - Features::all_experimental_mask()
+                - Features::MAPPABLE_PRIMARY_BUFFERS
+        });
+        let required_limits = self
+            .limits
+            .clone()
+            .unwrap_or_else(|| Limits::default().using_resolution(adapter.limits()));
+
+        let (device, queue) = pollster::block_on(adapter.request_device(&DeviceDescriptor {
+            label: Some("Main device"),
+            required_features,
+            required_limits,
+            memory_hints: MemoryHints::Performance,
+            trace: Default::default(),
+            experimental_features: ExperimentalFeatures::disabled(),
+        }))?;
+
+        device.on_uncaptured_error(Arc::new(|err| error!("Uncaptured wgpu error: {err}")));
+
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            error!("Device lost ({reason:?}): {message}");
+            flag.store(true, Ordering::Relaxed);
+        });
+
+        Ok((device, queue, lost))
+    }
+}
+
+fn available_adapters(instance: &Instance, backends: Backends) -> Vec<String> {
+    pollster::block_on(instance.enumerate_adapters(backends))
+        .iter()
+        .map(|adapter| {
+            let info = adapter.get_info();
+            format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+        })
+        .collect()
+}
+
+fn parse_present_mode(value: &str) -> Option<PresentMode> {
+    match value.to_lowercase().as_str() {
+        "auto-vsync" | "vsync" => Some(PresentMode::AutoVsync),
+        "auto-no-vsync" | "no-vsync" => Some(PresentMode::AutoNoVsync),
+        "fifo" => Some(PresentMode::Fifo),
+        "fifo-relaxed" => Some(PresentMode::FifoRelaxed),
+        "immediate" => Some(PresentMode::Immediate),
+        "mailbox" => Some(PresentMode::Mailbox),
+        _ => None,
     }
 }
+
+fn create_offscreen_texture(device: &Device, config: &SurfaceConfiguration) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("Offscreen Color Target"),
+        size: Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: config.format,
+        usage: config.usage | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
@@ -1,12 +1,72 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use winit::keyboard::{KeyCode, PhysicalKey};
+use serde::{Deserialize, Serialize};
+use winit::dpi::PhysicalSize;
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
 
-#[derive(Default)]
+mod bindings;
+mod gamepad;
+
+pub(crate) use bindings::BindingsFile;
+pub use bindings::{Binding, Bindings};
+pub use gamepad::GamepadEvent;
+pub(crate) use gamepad::Gamepads;
+
+// Stick values closer to the center than this read as 0, worn sticks rarely rest at exactly 0
+pub const STICK_DEAD_ZONE: f32 = 0.15;
+
+// Named after the position on an Xbox-style pad, so South is A on Xbox and Cross on PlayStation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+// Sticks go from -1 to 1 with positive Y pointing up, triggers from 0 to 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+// State of one frame. The "just" queries and `text` only see what happened since the previous
+// frame, `clear_delta` starts a new one
+#[derive(Clone, Default)]
 pub struct Input {
     pressed_keys: HashSet<KeyCode>,
+    just_pressed_keys: HashSet<KeyCode>,
+    just_released_keys: HashSet<KeyCode>,
     mouse_delta: (f64, f64),
+    scroll_delta: f64,
     mouse_buttons: HashSet<u64>,
+    just_pressed_mouse: HashSet<u64>,
+    just_released_mouse: HashSet<u64>,
+    cursor_position: Option<(f64, f64)>,
+    window_size: PhysicalSize<u32>,
+    modifiers: ModifiersState,
+    text: String,
+    gamepad_buttons: HashSet<GamepadButton>,
+    just_pressed_gamepad: HashSet<GamepadButton>,
+    just_released_gamepad: HashSet<GamepadButton>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    previous_gamepad_axes: HashMap<GamepadAxis, f32>,
+    bindings: Bindings,
 }
 
 impl Input {
@@ -14,37 +74,229 @@ impl Input {
         self.pressed_keys.contains(&key)
     }
 
+    // Only on the frame the key goes down, key repeat doesn't count
+    pub fn key_just_pressed(&self, key: KeyCode) -> bool {
+        self.just_pressed_keys.contains(&key)
+    }
+
+    pub fn key_just_released(&self, key: KeyCode) -> bool {
+        self.just_released_keys.contains(&key)
+    }
+
     pub fn mouse_delta(&self) -> (f64, f64) {
         self.mouse_delta
     }
 
+    // Wheel movement this frame in lines, positive when scrolling up (away from the user)
+    pub fn scroll_delta(&self) -> f64 {
+        self.scroll_delta
+    }
+
     pub fn mouse_button_pressed(&self, button: u64) -> bool {
         self.mouse_buttons.contains(&button)
     }
 
+    pub fn mouse_button_just_pressed(&self, button: u64) -> bool {
+        self.just_pressed_mouse.contains(&button)
+    }
+
+    pub fn mouse_button_just_released(&self, button: u64) -> bool {
+        self.just_released_mouse.contains(&button)
+    }
+
+    // In physical pixels, `None` while the cursor is outside the window
+    pub fn cursor_position(&self) -> Option<(f64, f64)> {
+        self.cursor_position
+    }
+
+    // From (0, 0) in the top left corner of the window to (1, 1) in the bottom right
+    pub fn cursor_normalized(&self) -> Option<(f64, f64)> {
+        let (x, y) = self.cursor_position?;
+        let PhysicalSize { width, height } = self.window_size;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some((x / width as f64, y / height as f64))
+    }
+
+    pub fn window_size(&self) -> PhysicalSize<u32> {
+        self.window_size
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    // Characters typed this frame with the keyboard layout and dead keys applied, without control
+    // characters like Backspace or Enter
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons.contains(&button)
+    }
+
+    pub fn gamepad_button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.just_pressed_gamepad.contains(&button)
+    }
+
+    pub fn gamepad_button_just_released(&self, button: GamepadButton) -> bool {
+        self.just_released_gamepad.contains(&button)
+    }
+
+    // 0 inside `STICK_DEAD_ZONE`, the rest of the range is stretched so values still reach 1
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        dead_zone(self.gamepad_axes.get(&axis).copied().unwrap_or(0.0))
+    }
+
+    fn previous_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        dead_zone(
+            self.previous_gamepad_axes
+                .get(&axis)
+                .copied()
+                .unwrap_or(0.0),
+        )
+    }
+
+    // Actions go through `bindings`, so they follow the bindings file and any rebinding
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.bindings.pressed(self, action)
+    }
+
+    pub fn action_just_pressed(&self, action: &str) -> bool {
+        self.bindings.just_pressed(self, action)
+    }
+
+    pub fn action_just_released(&self, action: &str) -> bool {
+        self.bindings.just_released(self, action)
+    }
+
+    pub fn action_value(&self, action: &str) -> f32 {
+        self.bindings.value(self, action)
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.pressed_keys.iter().copied()
+    }
+
+    pub fn pressed_mouse_buttons(&self) -> impl Iterator<Item = u64> + '_ {
+        self.mouse_buttons.iter().copied()
+    }
+
+    pub fn pressed_gamepad_buttons(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.gamepad_buttons.iter().copied()
+    }
+
+    // Raw values, before the dead zone
+    pub fn gamepad_axes(&self) -> impl Iterator<Item = (GamepadAxis, f32)> + '_ {
+        self.gamepad_axes
+            .iter()
+            .map(|(&axis, &value)| (axis, value))
+    }
+
     pub fn press_key(&mut self, key: KeyCode) {
-        self.pressed_keys.insert(key);
+        if self.pressed_keys.insert(key) {
+            self.just_pressed_keys.insert(key);
+        }
     }
 
     pub fn release_key(&mut self, key: KeyCode) {
-        self.pressed_keys.remove(&key);
+        if self.pressed_keys.remove(&key) {
+            self.just_released_keys.insert(key);
+        }
     }
 
     pub fn set_mouse_delta(&mut self, dx: f64, dy: f64) {
         self.mouse_delta = (dx, dy);
     }
 
+    pub fn add_scroll_delta(&mut self, lines: f64) {
+        self.scroll_delta += lines;
+    }
+
+    pub fn set_cursor_position(&mut self, position: Option<(f64, f64)>) {
+        self.cursor_position = position;
+    }
+
+    pub fn set_window_size(&mut self, size: PhysicalSize<u32>) {
+        self.window_size = size;
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn push_text(&mut self, text: &str) {
+        self.text.extend(text.chars().filter(|c| !c.is_control()));
+    }
+
     pub fn press_mouse(&mut self, button: u64) {
-        self.mouse_buttons.insert(button);
+        if self.mouse_buttons.insert(button) {
+            self.just_pressed_mouse.insert(button);
+        }
     }
 
     pub fn release_mouse(&mut self, button: u64) {
-        self.mouse_buttons.remove(&button);
+        if self.mouse_buttons.remove(&button) {
+            self.just_released_mouse.insert(button);
+        }
+    }
+
+    // `App` feeds gamepads in through `apply_gamepad_event`, replays and tests can set the state
+    // directly with these
+    pub fn press_gamepad_button(&mut self, button: GamepadButton) {
+        if self.gamepad_buttons.insert(button) {
+            self.just_pressed_gamepad.insert(button);
+        }
+    }
+
+    pub fn release_gamepad_button(&mut self, button: GamepadButton) {
+        if self.gamepad_buttons.remove(&button) {
+            self.just_released_gamepad.insert(button);
+        }
+    }
+
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes.insert(axis, value.clamp(-1.0, 1.0));
+    }
+
+    // Every axis back at rest
+    pub fn clear_gamepad_axes(&mut self) {
+        self.gamepad_axes.clear();
     }
 
     pub fn clear_delta(&mut self) {
         self.mouse_delta = (0.0, 0.0);
+        self.scroll_delta = 0.0;
+        self.just_pressed_keys.clear();
+        self.just_released_keys.clear();
+        self.just_pressed_mouse.clear();
+        self.just_released_mouse.clear();
+        self.just_pressed_gamepad.clear();
+        self.just_released_gamepad.clear();
+        self.text.clear();
+        self.previous_gamepad_axes.clone_from(&self.gamepad_axes);
+    }
+}
+
+fn dead_zone(value: f32) -> f32 {
+    if value.abs() < STICK_DEAD_ZONE {
+        return 0.0;
     }
+    value.signum() * (value.abs() - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)
 }
 
 fn key_code(physical_key: PhysicalKey) -> Option<KeyCode> {
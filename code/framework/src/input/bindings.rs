@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
+use tracing::{info, warn};
+use winit::keyboard::KeyCode;
+
+use super::{GamepadAxis, GamepadButton, Input};
+use crate::error::{Error, Result};
+
+const DEFAULT_PATH: &str = "bindings.toml";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// An axis bound as a button counts as pressed past half way
+const AXIS_PRESS: f32 = 0.5;
+
+// Same names as in the bindings file. Physical keys are named after the US layout position, so
+// KeyW is Z on AZERTY and comma on Dvorak
+const DEFAULT_BINDINGS: &[(&str, &[&str])] = &[
+    ("move_forward", &["KeyW", "GamepadLeftStickY+"]),
+    ("move_back", &["KeyS", "GamepadLeftStickY-"]),
+    ("move_left", &["KeyA", "GamepadLeftStickX-"]),
+    ("move_right", &["KeyD", "GamepadLeftStickX+"]),
+    ("move_up", &["Space", "GamepadRightTrigger+"]),
+    ("move_down", &["ShiftLeft", "GamepadLeftTrigger+"]),
+    ("look", &["MouseRight"]),
+    ("pan", &["MouseMiddle"]),
+    ("look_left", &["GamepadRightStickX-"]),
+    ("look_right", &["GamepadRightStickX+"]),
+    ("look_up", &["GamepadRightStickY+"]),
+    ("look_down", &["GamepadRightStickY-"]),
+    ("exit", &["Escape"]),
+    ("toggle_ui", &["F1"]),
+    ("toggle_stats", &["F3"]),
+    ("capture", &["F12"]),
+];
+
+const MOUSE_BUTTONS: [&str; 5] = ["Left", "Right", "Middle", "Back", "Forward"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(u64),
+    GamepadButton(GamepadButton),
+    // One half of an axis, read from 0 to 1
+    GamepadAxis { axis: GamepadAxis, positive: bool },
+}
+
+fn parse_enum<'de, T: Deserialize<'de>>(name: &'de str) -> Option<T> {
+    let deserializer: StrDeserializer<'de, ValueError> = name.into_deserializer();
+    T::deserialize(deserializer).ok()
+}
+
+// "KeyW", "MouseRight" or "Mouse5", "GamepadSouth", "GamepadLeftStickY+"
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, String> {
+        let binding = if let Some(button) = name.strip_prefix("Mouse") {
+            MOUSE_BUTTONS
+                .iter()
+                .position(|&known| known == button)
+                .map(|index| index as u64)
+                .or_else(|| button.parse().ok())
+                .map(Binding::Mouse)
+        } else if let Some(gamepad) = name.strip_prefix("Gamepad") {
+            if let Some(axis) = gamepad.strip_suffix('+') {
+                parse_enum(axis).map(|axis| Binding::GamepadAxis {
+                    axis,
+                    positive: true,
+                })
+            } else if let Some(axis) = gamepad.strip_suffix('-') {
+                parse_enum(axis).map(|axis| Binding::GamepadAxis {
+                    axis,
+                    positive: false,
+                })
+            } else {
+                parse_enum(gamepad).map(Binding::GamepadButton)
+            }
+        } else {
+            parse_enum(name).map(Binding::Key)
+        };
+        binding.ok_or_else(|| format!("unknown key or button {name:?}"))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Mouse(button) => match MOUSE_BUTTONS.get(*button as usize) {
+                Some(name) => write!(f, "Mouse{name}"),
+                None => write!(f, "Mouse{button}"),
+            },
+            Binding::GamepadButton(button) => write!(f, "Gamepad{button:?}"),
+            Binding::GamepadAxis { axis, positive } => {
+                write!(f, "Gamepad{axis:?}{}", if *positive { '+' } else { '-' })
+            }
+        }
+    }
+}
+
+impl Binding {
+    fn value(self, input: &Input) -> f32 {
+        match self {
+            Binding::GamepadAxis { axis, positive } => {
+                let value = input.gamepad_axis(axis);
+                (if positive { value } else { -value }).max(0.0)
+            }
+            _ if self.down(input) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn down(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.key_pressed(key),
+            Binding::Mouse(button) => input.mouse_button_pressed(button),
+            Binding::GamepadButton(button) => input.gamepad_button_pressed(button),
+            Binding::GamepadAxis { .. } => self.value(input) > AXIS_PRESS,
+        }
+    }
+
+    // Whether it was down in the previous frame, undoing this frame's edges
+    fn was_down(self, input: &Input) -> bool {
+        let (down, pressed, released) = match self {
+            Binding::Key(key) => (
+                input.key_pressed(key),
+                input.key_just_pressed(key),
+                input.key_just_released(key),
+            ),
+            Binding::Mouse(button) => (
+                input.mouse_button_pressed(button),
+                input.mouse_button_just_pressed(button),
+                input.mouse_button_just_released(button),
+            ),
+            Binding::GamepadButton(button) => (
+                input.gamepad_button_pressed(button),
+                input.gamepad_button_just_pressed(button),
+                input.gamepad_button_just_released(button),
+            ),
+            Binding::GamepadAxis { axis, positive } => {
+                let value = input.previous_gamepad_axis(axis);
+                return (if positive { value } else { -value }) > AXIS_PRESS;
+            }
+        };
+        (down || released) && !pressed
+    }
+}
+
+// Named actions and the keys, buttons and axes that trigger them. An action with several bindings
+// is pressed while any of them is
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    actions: BTreeMap<String, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Self::empty();
+        for (action, names) in DEFAULT_BINDINGS {
+            bindings.define(action, names);
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    pub fn empty() -> Self {
+        Self {
+            actions: BTreeMap::new(),
+        }
+    }
+
+    // The defaults with the actions from an `[actions]` table replaced:
+    //
+    //   [actions]
+    //   move_forward = ["KeyW", "ArrowUp"]
+    //   exit = "KeyQ"
+    pub fn from_toml(text: &str) -> std::result::Result<Self, String> {
+        let document: DocumentMut = text.parse().map_err(|err| format!("{err}"))?;
+        let mut bindings = Self::default();
+        let Some(actions) = document.get("actions") else {
+            return Ok(bindings);
+        };
+        let actions = actions.as_table_like().ok_or("`actions` is not a table")?;
+        for (action, item) in actions.iter() {
+            let names: Vec<&str> = match item.as_value() {
+                Some(Value::String(name)) => vec![name.value().as_str()],
+                Some(Value::Array(array)) => array
+                    .iter()
+                    .map(|value| value.as_str().ok_or(format!("{action}: expected strings")))
+                    .collect::<std::result::Result<_, _>>()?,
+                _ => {
+                    return Err(format!(
+                        "{action}: expected a string or an array of strings"
+                    ));
+                }
+            };
+            let list = names
+                .into_iter()
+                .map(|name| name.parse().map_err(|err| format!("{action}: {err}")))
+                .collect::<std::result::Result<_, _>>()?;
+            bindings.set(action, list);
+        }
+        Ok(bindings)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml(&text).map_err(|message| Error::InputBindings {
+            path: path.display().to_string(),
+            message,
+        })
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut actions = Table::new();
+        for (action, list) in &self.actions {
+            let names: Array = list.iter().map(|binding| binding.to_string()).collect();
+            actions.insert(action, Item::Value(Value::Array(names)));
+        }
+        let mut document = DocumentMut::new();
+        document.insert("actions", Item::Table(actions));
+        document.to_string()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    // Binds `action` unless something already did, e.g. the bindings file. Panics on unknown names
+    pub fn define(&mut self, action: &str, names: &[&str]) {
+        if self.actions.contains_key(action) {
+            return;
+        }
+        let list = names
+            .iter()
+            .map(|name| name.parse().expect("Failed to parse default binding"))
+            .collect();
+        self.set(action, list);
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.actions.keys().map(String::as_str)
+    }
+
+    pub fn get(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set(&mut self, action: &str, list: Vec<Binding>) {
+        self.actions.insert(action.to_owned(), list);
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let list = self.actions.entry(action.to_owned()).or_default();
+        if !list.contains(&binding) {
+            list.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(list) = self.actions.get_mut(action) {
+            list.retain(|&bound| bound != binding);
+        }
+    }
+
+    pub fn pressed(&self, input: &Input, action: &str) -> bool {
+        self.get(action).iter().any(|binding| binding.down(input))
+    }
+
+    pub fn just_pressed(&self, input: &Input, action: &str) -> bool {
+        let list = self.get(action);
+        list.iter().any(|binding| binding.down(input))
+            && !list.iter().any(|binding| binding.was_down(input))
+    }
+
+    pub fn just_released(&self, input: &Input, action: &str) -> bool {
+        let list = self.get(action);
+        !list.iter().any(|binding| binding.down(input))
+            && list.iter().any(|binding| binding.was_down(input))
+    }
+
+    // From 0 to 1, the strongest of the bindings. Keys and buttons are 0 or 1
+    pub fn value(&self, input: &Input, action: &str) -> f32 {
+        self.get(action)
+            .iter()
+            .map(|binding| binding.value(input))
+            .fold(0.0, f32::max)
+    }
+}
+
+// The user's bindings file, `--bindings=<file>` or `bindings.toml` in the working directory. It is
+// polled like shaders, so editing it rebinds the running example
+pub(crate) struct BindingsFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl BindingsFile {
+    pub(crate) fn from_args() -> Self {
+        let path = std::env::args()
+            .skip(1)
+            .find_map(|arg| arg.strip_prefix("--bindings=").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PATH));
+        Self {
+            modified: modified(&path),
+            path,
+            last_poll: Instant::now(),
+        }
+    }
+
+    // The defaults when there is no file, or it is broken
+    pub(crate) fn load(&self) -> Bindings {
+        if !self.path.exists() {
+            return Bindings::default();
+        }
+        match Bindings::load(&self.path) {
+            Ok(bindings) => {
+                info!("Loaded input bindings from {}", self.path.display());
+                bindings
+            }
+            Err(err) => {
+                warn!("{err}");
+                Bindings::default()
+            }
+        }
+    }
+
+    // The new bindings after the file changed
+    pub(crate) fn poll(&mut self) -> Option<Bindings> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(self.load())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,132 @@
+use gilrs::{Axis, Button, Event, EventType, Gilrs};
+use tracing::{info, warn};
+
+use super::{GamepadAxis, GamepadButton, Input};
+
+// A gilrs event without its platform-specific code, which only gilrs itself can create
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEvent {
+    ButtonPressed(Button),
+    ButtonReleased(Button),
+    // Analog buttons, the triggers report how far they are pulled this way
+    ButtonChanged(Button, f32),
+    AxisChanged(Axis, f32),
+    Connected,
+    Disconnected,
+}
+
+impl GamepadEvent {
+    pub fn from_gilrs(event: &EventType) -> Option<Self> {
+        Some(match *event {
+            EventType::ButtonPressed(button, _) => Self::ButtonPressed(button),
+            EventType::ButtonReleased(button, _) => Self::ButtonReleased(button),
+            EventType::ButtonChanged(button, value, _) => Self::ButtonChanged(button, value),
+            EventType::AxisChanged(axis, value, _) => Self::AxisChanged(axis, value),
+            EventType::Connected => Self::Connected,
+            EventType::Disconnected => Self::Disconnected,
+            _ => return None,
+        })
+    }
+}
+
+// gilrs calls the bumpers LeftTrigger/RightTrigger and the analog triggers LeftTrigger2/RightTrigger2
+fn button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn axis(axis: Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
+
+impl Input {
+    // Every connected gamepad feeds the same state, so a pad plugged in or pulled out mid-press
+    // would otherwise leave its buttons held and sticks tilted
+    pub fn apply_gamepad_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::ButtonPressed(b) => {
+                if let Some(b) = button(b) {
+                    self.press_gamepad_button(b);
+                }
+            }
+            GamepadEvent::ButtonReleased(b) => {
+                if let Some(b) = button(b) {
+                    self.release_gamepad_button(b);
+                }
+            }
+            GamepadEvent::ButtonChanged(Button::LeftTrigger2, value) => {
+                self.set_gamepad_axis(GamepadAxis::LeftTrigger, value);
+            }
+            GamepadEvent::ButtonChanged(Button::RightTrigger2, value) => {
+                self.set_gamepad_axis(GamepadAxis::RightTrigger, value);
+            }
+            GamepadEvent::ButtonChanged(..) => {}
+            GamepadEvent::AxisChanged(a, value) => {
+                if let Some(a) = axis(a) {
+                    self.set_gamepad_axis(a, value);
+                }
+            }
+            GamepadEvent::Connected | GamepadEvent::Disconnected => self.release_gamepad(),
+        }
+    }
+
+    fn release_gamepad(&mut self) {
+        let held: Vec<_> = self.gamepad_buttons.iter().copied().collect();
+        for button in held {
+            self.release_gamepad_button(button);
+        }
+        self.clear_gamepad_axes();
+    }
+}
+
+// Polled by `App` once per frame. Without gilrs support on the platform the example still runs,
+// just without gamepads
+pub(crate) struct Gamepads {
+    gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+    pub(crate) fn new() -> Self {
+        let gilrs = Gilrs::new()
+            .inspect_err(|err| warn!("Gamepads are unavailable: {err}"))
+            .ok();
+        Self { gilrs }
+    }
+
+    pub(crate) fn poll(&mut self, input: &mut Input) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        while let Some(Event { id, event, .. }) = gilrs.next_event() {
+            match event {
+                EventType::Connected => info!("Gamepad {} connected", gilrs.gamepad(id).name()),
+                EventType::Disconnected => info!("Gamepad {id} disconnected"),
+                _ => {}
+            }
+            if let Some(event) = GamepadEvent::from_gilrs(&event) {
+                input.apply_gamepad_event(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use syn::{Expr, Fields, Item, ItemStruct, Lit, Type};
+use wgpu::naga::proc::Layouter;
+use wgpu::naga::{Module, TypeInner};
+
+use crate::error::{Error, Result};
+use crate::shader::{parse, preprocess};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Rules {
+    // `#[derive(ShaderType)]`: encase writes fields with WGSL size and alignment
+    Wgsl,
+    // `#[derive(Pod)]` with `#[repr(C)]`: bytes are copied as they are laid out in Rust
+    ReprC,
+}
+
+struct Field {
+    name: String,
+    offset: u32,
+    size: u32,
+}
+
+struct StructLayout {
+    fields: Vec<Field>,
+    size: u32,
+    align: u32,
+}
+
+// Panics with a field-by-field diff when a Rust struct and its WGSL counterpart disagree.
+// `pairs` are `(rust struct, wgsl file, wgsl struct)`, files are relative to the chapter's `src/`
+pub fn assert_struct_layouts(chapter_dir: &str, pairs: &[(&str, &str, &str)]) {
+    check_struct_layouts(chapter_dir, pairs).unwrap_or_else(|err| panic!("{err}"));
+}
+
+pub fn check_struct_layouts(chapter_dir: &str, pairs: &[(&str, &str, &str)]) -> Result<()> {
+    let src = Path::new(chapter_dir).join("src");
+    let rust_path = src.join("main.rs");
+    let rust_source = std::fs::read_to_string(&rust_path)?;
+    let file = syn::parse_file(&rust_source).map_err(|err| Error::Layout {
+        name: rust_path.display().to_string(),
+        diff: err.to_string(),
+    })?;
+    let structs: HashMap<String, &ItemStruct> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(item) => Some((item.ident.to_string(), item)),
+            _ => None,
+        })
+        .collect();
+
+    let mut report = String::new();
+    for &(rust_name, wgsl_file, wgsl_name) in pairs {
+        let name = format!("{rust_name} (main.rs) vs {wgsl_name} ({wgsl_file})");
+        let error = |diff: String| Error::Layout {
+            name: name.clone(),
+            diff,
+        };
+
+        let item = structs
+            .get(rust_name)
+            .ok_or_else(|| error("no such struct in main.rs".to_owned()))?;
+        let rules = if derives(item, "ShaderType") {
+            Rules::Wgsl
+        } else if derives(item, "Pod") {
+            Rules::ReprC
+        } else {
+            return Err(error(
+                "struct derives neither ShaderType nor Pod".to_owned(),
+            ));
+        };
+        let rust = rust_layout(&structs, item, rules).map_err(error)?;
+
+        let path = src.join(wgsl_file);
+        let path = path.to_string_lossy();
+        let source = std::fs::read_to_string(path.as_ref())?;
+        let (module, _) = parse(&path, &preprocess(&path, &source, &[])?)?;
+        let wgsl = wgsl_layout(&module, wgsl_name).map_err(error)?;
+
+        if let Some(diff) = diff(&rust, &wgsl) {
+            writeln!(report, "{name}:\n{diff}").unwrap();
+        }
+    }
+
+    if report.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Layout {
+            name: chapter_dir.to_owned(),
+            diff: report,
+        })
+    }
+}
+
+fn derives(item: &ItemStruct, name: &str) -> bool {
+    item.attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .any(|attr| {
+            let mut found = false;
+            let _ = attr.parse_nested_meta(|meta| {
+                found |= meta.path.segments.last().is_some_and(|s| s.ident == name);
+                Ok(())
+            });
+            found
+        })
+}
+
+fn repr_c(item: &ItemStruct) -> bool {
+    item.attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+        .any(|attr| {
+            let mut found = false;
+            let _ = attr.parse_nested_meta(|meta| {
+                found |= meta.path.is_ident("C");
+                Ok(())
+            });
+            found
+        })
+}
+
+fn rust_layout(
+    structs: &HashMap<String, &ItemStruct>,
+    item: &ItemStruct,
+    rules: Rules,
+) -> std::result::Result<StructLayout, String> {
+    let Fields::Named(named) = &item.fields else {
+        return Err(format!("{} has no named fields", item.ident));
+    };
+    // Without `#[repr(C)]` Rust may reorder fields, so the computed offsets would mean nothing
+    if rules == Rules::ReprC && !repr_c(item) {
+        return Err(format!("{} derives Pod without #[repr(C)]", item.ident));
+    }
+
+    let mut fields = Vec::new();
+    let mut offset = 0;
+    let mut align = 1;
+    for field in &named.named {
+        let name = field.ident.as_ref().unwrap().to_string();
+        let (size, field_align) = rust_type_layout(structs, &field.ty, rules)
+            .map_err(|err| format!("field {name}: {err}"))?;
+        offset = round_up(offset, field_align);
+        fields.push(Field { name, offset, size });
+        offset += size;
+        align = align.max(field_align);
+    }
+
+    Ok(StructLayout {
+        fields,
+        size: round_up(offset, align),
+        align,
+    })
+}
+
+// Size and alignment of a field type under the given rules
+fn rust_type_layout(
+    structs: &HashMap<String, &ItemStruct>,
+    ty: &Type,
+    rules: Rules,
+) -> std::result::Result<(u32, u32), String> {
+    match ty {
+        Type::Array(array) => {
+            let (size, align) = rust_type_layout(structs, &array.elem, rules)?;
+            let Expr::Lit(lit) = &array.len else {
+                return Err("array length must be a literal".to_owned());
+            };
+            let Lit::Int(len) = &lit.lit else {
+                return Err("array length must be an integer".to_owned());
+            };
+            let len: u32 = len.base10_parse().map_err(|err| err.to_string())?;
+            Ok((round_up(size, align) * len, align))
+        }
+        Type::Path(path) => {
+            let name = path.path.segments.last().unwrap().ident.to_string();
+            if let Some(item) = structs.get(&name) {
+                let layout = rust_layout(structs, item, rules)?;
+                return Ok((layout.size, layout.align));
+            }
+            let wgsl = rules == Rules::Wgsl;
+            Ok(match name.as_str() {
+                "f32" | "u32" | "i32" => (4, 4),
+                "Vec2" | "UVec2" | "IVec2" => (8, if wgsl { 8 } else { 4 }),
+                "Vec3" | "UVec3" | "IVec3" => (12, if wgsl { 16 } else { 4 }),
+                "Vec4" | "UVec4" | "IVec4" => (16, 16),
+                "Mat2" => (16, if wgsl { 8 } else { 16 }),
+                "Mat3" => (if wgsl { 48 } else { 36 }, if wgsl { 16 } else { 4 }),
+                "Mat4" => (64, 16),
+                _ => return Err(format!("unsupported type {name}")),
+            })
+        }
+        _ => Err("unsupported type".to_owned()),
+    }
+}
+
+fn wgsl_layout(module: &Module, name: &str) -> std::result::Result<StructLayout, String> {
+    let mut layouter = Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .map_err(|err| err.to_string())?;
+
+    let (handle, members) = module
+        .types
+        .iter()
+        .find_map(|(handle, ty)| match &ty.inner {
+            TypeInner::Struct { members, .. } if ty.name.as_deref() == Some(name) => {
+                Some((handle, members))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| "no such struct in the shader".to_owned())?;
+
+    let fields = members
+        .iter()
+        .map(|member| Field {
+            name: member.name.clone().unwrap_or_default(),
+            offset: member.offset,
+            size: layouter[member.ty].size,
+        })
+        .collect();
+
+    Ok(StructLayout {
+        fields,
+        size: layouter[handle].size,
+        align: layouter[handle].alignment.round_up(1),
+    })
+}
+
+// A table of both layouts with mismatching rows marked, or `None` when they agree
+fn diff(rust: &StructLayout, wgsl: &StructLayout) -> Option<String> {
+    let mut table = String::new();
+    let mut matches = true;
+
+    let describe = |field: Option<&Field>| {
+        field.map_or_else(
+            || "-".to_owned(),
+            |f| format!("{} @{} size {}", f.name, f.offset, f.size),
+        )
+    };
+    let rows = rust.fields.len().max(wgsl.fields.len());
+    writeln!(table, "    {:<32} wgsl", "rust").unwrap();
+    for row in 0..rows {
+        let (r, w) = (rust.fields.get(row), wgsl.fields.get(row));
+        let same = matches!((r, w), (Some(r), Some(w))
+            if r.name == w.name && r.offset == w.offset && r.size == w.size);
+        matches &= same;
+        let marker = if same { ' ' } else { '>' };
+        writeln!(table, "  {marker} {:<32} {}", describe(r), describe(w)).unwrap();
+    }
+
+    let same = rust.size == wgsl.size && rust.align == wgsl.align;
+    matches &= same;
+    let marker = if same { ' ' } else { '>' };
+    let rust_size = format!("size {} align {}", rust.size, rust.align);
+    let wgsl_size = format!("size {} align {}", wgsl.size, wgsl.align);
+    writeln!(table, "  {marker} {rust_size:<32} {wgsl_size}").unwrap();
+
+    (!matches).then_some(table)
+}
+
+fn round_up(value: u32, align: u32) -> u32 {
+    value.div_ceil(align) * align
+}
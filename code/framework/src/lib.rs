@@ -1,15 +1,45 @@
+extern crate self as framework;
+
 pub mod app;
 pub mod camera;
+pub mod capture;
+pub mod error;
 pub mod example;
 pub mod geometry;
+pub mod golden;
 pub mod gpu;
 pub mod input;
+#[cfg(feature = "layout")]
+pub mod layout;
+pub mod mesh;
+pub mod overlay;
+pub mod profiler;
+pub mod reflect;
+pub mod replay;
+pub mod shader;
 pub mod texture;
+pub mod ui;
+pub mod vertex;
 
-pub use app::run;
-pub use camera::Camera;
-pub use example::Example;
+pub use app::{
+    recover_device, run, run_headless, run_headless_with, run_with, try_run, try_run_headless_with,
+    try_run_with,
+};
+pub use camera::{
+    ArcballCamera, Camera, CameraController, CameraSwitcher, Frustum, OrbitCamera, Projection, Ray,
+    TurntableCamera,
+};
+pub use error::{Error, Result};
+pub use example::{Example, TryExample};
 pub use geometry::{CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS};
-pub use gpu::GpuContext;
-pub use input::Input;
-pub use texture::{create_depth_texture, generate_checkerboard};
+pub use gpu::{GpuContext, GpuContextBuilder};
+pub use input::{Binding, Bindings, GamepadAxis, GamepadButton, GamepadEvent, Input};
+pub use mesh::{Indices, MeshVertex, Model, load_gltf, load_obj};
+pub use overlay::Overlay;
+pub use profiler::GpuProfiler;
+pub use reflect::ShaderLayout;
+pub use replay::rng;
+pub use shader::{ShaderFile, load_shader, load_shader_with_defs, load_shader_with_layout};
+pub use texture::{DepthMode, create_depth_texture, generate_checkerboard, read_texture};
+pub use ui::Ui;
+pub use vertex::VertexLayout;
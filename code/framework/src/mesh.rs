@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+use wgpu::IndexFormat;
+
+use crate::error::Error;
+use crate::golden::Image;
+use crate::vertex::VertexLayout;
+
+mod gltf;
+mod obj;
+
+pub use gltf::load_gltf;
+pub use obj::load_obj;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable, VertexLayout)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    // xyz is the tangent, w is the bitangent sign: bitangent = cross(normal, tangent) * w
+    pub tangent: [f32; 4],
+}
+
+// Index data in the smallest format that can address every vertex
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        if vertex_count <= u16::MAX as usize {
+            Self::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Self::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U16(indices) => indices.len(),
+            Self::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            Self::U16(_) => IndexFormat::Uint16,
+            Self::U32(_) => IndexFormat::Uint32,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::U16(indices) => bytemuck::cast_slice(indices),
+            Self::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        let (short, long): (&[u16], &[u32]) = match self {
+            Self::U16(indices) => (indices, &[]),
+            Self::U32(indices) => (&[], indices),
+        };
+        short.iter().map(|&i| i as u32).chain(long.iter().copied())
+    }
+}
+
+// One glTF primitive placed by its node, one OBJ group and material or a `geometry` shape
+pub struct Mesh {
+    pub name: String,
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Indices,
+    // Node transform with all parents applied, identity for OBJ and generated shapes
+    pub transform: Mat4,
+    pub material: Option<usize>,
+}
+
+impl Mesh {
+    // Moves the vertices themselves, for static geometry that is drawn without a model matrix
+    pub fn transformed(mut self, matrix: Mat4) -> Self {
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        for vertex in &mut self.vertices {
+            vertex.position = matrix.transform_point3(vertex.position.into()).to_array();
+            vertex.normal = (normal_matrix * Vec3::from(vertex.normal))
+                .normalize_or_zero()
+                .to_array();
+            let tangent = matrix.transform_vector3(Vec3::from_slice(&vertex.tangent));
+            vertex.tangent[..3].copy_from_slice(&tangent.normalize_or_zero().to_array());
+        }
+        self
+    }
+
+    // Repeats the texture `scale` times across the mesh
+    pub fn with_uv_scale(mut self, scale: Vec2) -> Self {
+        for vertex in &mut self.vertices {
+            vertex.uv = (Vec2::from(vertex.uv) * scale).to_array();
+        }
+        self
+    }
+}
+
+pub struct Material {
+    pub name: String,
+    pub base_color: Vec4,
+    // Indices into `Model::images`
+    pub base_color_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub normal_scale: f32,
+}
+
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    // RGBA8, base color images are sRGB and normal maps are linear
+    pub images: Vec<Image>,
+}
+
+fn model_error(path: &Path, message: String) -> Error {
+    Error::Model {
+        path: path.display().to_string(),
+        message,
+    }
+}
+
+// glTF asks for flat normals when a primitive has none, so every triangle gets its own vertices
+fn flat_normals(vertices: &[MeshVertex], indices: &[u32]) -> (Vec<MeshVertex>, Vec<u32>) {
+    let mut flat = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+        let normal = (Vec3::from(b.position) - Vec3::from(a.position))
+            .cross(Vec3::from(c.position) - Vec3::from(a.position))
+            .normalize_or_zero()
+            .to_array();
+        flat.extend([a, b, c].map(|v| MeshVertex { normal, ..v }));
+    }
+    (flat, (0..indices.len() as u32).collect())
+}
+
+// MikkTSpace tangents, the ones baked normal maps expect. glTF UVs grow downwards while normal
+// maps are +Y up, so MikkTSpace sees v flipped. Vertices no triangle uses get any tangent
+// perpendicular to the normal
+pub(crate) fn generate_tangents(vertices: &mut [MeshVertex], indices: &[u32]) {
+    for vertex in vertices.iter_mut() {
+        let normal = Vec3::from(vertex.normal);
+        let tangent = if normal.is_normalized() {
+            normal.any_orthonormal_vector()
+        } else {
+            Vec3::X
+        };
+        vertex.tangent = tangent.extend(1.0).to_array();
+    }
+
+    // Fails only when every triangle is degenerate, the fallback stays then
+    bevy_mikktspace::generate_tangents(&mut TangentGeometry { vertices, indices });
+}
+
+struct TangentGeometry<'a> {
+    vertices: &'a mut [MeshVertex],
+    indices: &'a [u32],
+}
+
+impl TangentGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &MeshVertex {
+        &self.vertices[self.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl bevy_mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let [u, v] = self.vertex(face, vert).uv;
+        [u, 1.0 - v]
+    }
+
+    // Corners sharing a vertex get the same tangent unless the mesh already split them. Without
+    // a UV gradient MikkTSpace hands out +X as is, so it's made perpendicular to the normal here
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let vertex = &mut self.vertices[self.indices[face * 3 + vert] as usize];
+        let normal = Vec3::from(vertex.normal);
+        let tangent = Vec4::from(tangent);
+        let projected = tangent.truncate() - normal * normal.dot(tangent.truncate());
+        let projected = projected.normalize_or_zero();
+        if projected != Vec3::ZERO {
+            vertex.tangent = projected.extend(tangent.w).to_array();
+        }
+    }
+}
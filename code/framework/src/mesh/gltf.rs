@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use glam::{Mat4, Quat, Vec3, Vec4};
+use serde::Deserialize;
+
+use super::{
+    Indices, Material, Mesh, MeshVertex, Model, flat_normals, generate_tangents, model_error,
+};
+use crate::error::{Error, Result};
+use crate::golden::Image;
+use crate::texture::decode_image;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+const MODE_TRIANGLES: u32 = 4;
+
+// Loads a glTF 2.0 scene, either `.gltf` with external or embedded buffers or a binary `.glb`.
+// Missing normals are generated flat, missing tangents are derived from UVs
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<Model> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)?;
+    Loader::new(path, &bytes)?.load()
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    meshes: Vec<GltfMesh>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    materials: Vec<GltfMaterial>,
+    textures: Vec<Texture>,
+    images: Vec<GltfImage>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Scene {
+    nodes: Vec<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    name: Option<String>,
+    mesh: Option<usize>,
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct GltfMesh {
+    name: Option<String>,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    byte_offset: usize,
+    component_type: u32,
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct GltfMaterial {
+    name: Option<String>,
+    pbr_metallic_roughness: Option<Pbr>,
+    normal_texture: Option<TextureInfo>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Pbr {
+    base_color_factor: Option<[f32; 4]>,
+    base_color_texture: Option<TextureInfo>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct TextureInfo {
+    index: usize,
+    tex_coord: u32,
+    scale: Option<f32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Texture {
+    source: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct GltfImage {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    doc: Document,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Loader<'a> {
+    fn new(path: &'a Path, bytes: &[u8]) -> Result<Self> {
+        let error = |message: String| model_error(path, message);
+
+        let (json, mut bin) = if bytes.starts_with(GLB_MAGIC) {
+            let (json, bin) = split_glb(bytes).map_err(error)?;
+            (json, bin.map(<[u8]>::to_vec))
+        } else {
+            (bytes, None)
+        };
+        let doc: Document = serde_json::from_slice(json).map_err(|err| error(err.to_string()))?;
+
+        let mut buffers = Vec::with_capacity(doc.buffers.len());
+        for (i, buffer) in doc.buffers.iter().enumerate() {
+            let data = match &buffer.uri {
+                Some(uri) => read_uri(path, uri)?,
+                None => bin
+                    .take()
+                    .ok_or_else(|| error(format!("buffer {i} has no uri and no GLB chunk")))?,
+            };
+            if data.len() < buffer.byte_length {
+                return Err(error(format!(
+                    "buffer {i} is {} bytes, expected {}",
+                    data.len(),
+                    buffer.byte_length
+                )));
+            }
+            buffers.push(data);
+        }
+
+        Ok(Self { path, doc, buffers })
+    }
+
+    fn error(&self, message: String) -> Error {
+        model_error(self.path, message)
+    }
+
+    fn load(&self) -> Result<Model> {
+        let images = (0..self.doc.images.len())
+            .map(|i| self.image(i))
+            .collect::<Result<Vec<_>>>()?;
+        let materials = (0..self.doc.materials.len())
+            .map(|i| self.material(i))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Without a scene every node that isn't somebody's child is a root
+        let roots = match self.doc.scenes.get(self.doc.scene.unwrap_or(0)) {
+            Some(scene) => scene.nodes.clone(),
+            None => (0..self.doc.nodes.len())
+                .filter(|&i| !self.doc.nodes.iter().any(|n| n.children.contains(&i)))
+                .collect(),
+        };
+        let mut meshes = Vec::new();
+        for root in roots {
+            self.visit_node(root, Mat4::IDENTITY, 0, &mut meshes)?;
+        }
+
+        Ok(Model {
+            meshes,
+            materials,
+            images,
+        })
+    }
+
+    fn visit_node(
+        &self,
+        index: usize,
+        parent: Mat4,
+        depth: usize,
+        meshes: &mut Vec<Mesh>,
+    ) -> Result<()> {
+        let node = self
+            .doc
+            .nodes
+            .get(index)
+            .ok_or_else(|| self.error(format!("node {index} doesn't exist")))?;
+        if depth > self.doc.nodes.len() {
+            return Err(self.error(format!("node {index} is its own ancestor")));
+        }
+
+        let local = match node.matrix {
+            Some(matrix) => Mat4::from_cols_array(&matrix),
+            None => Mat4::from_scale_rotation_translation(
+                Vec3::from(node.scale.unwrap_or([1.0; 3])),
+                Quat::from_array(node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0])).normalize(),
+                Vec3::from(node.translation.unwrap_or([0.0; 3])),
+            ),
+        };
+        let transform = parent * local;
+
+        if let Some(mesh_index) = node.mesh {
+            let mesh = self
+                .doc
+                .meshes
+                .get(mesh_index)
+                .ok_or_else(|| self.error(format!("mesh {mesh_index} doesn't exist")))?;
+            let name = node
+                .name
+                .clone()
+                .or_else(|| mesh.name.clone())
+                .unwrap_or_else(|| format!("Mesh {mesh_index}"));
+            for primitive in &mesh.primitives {
+                let (vertices, indices) = self.primitive(primitive).map_err(|err| match err {
+                    Error::Model { path, message } => Error::Model {
+                        path,
+                        message: format!("{name}: {message}"),
+                    },
+                    err => err,
+                })?;
+                meshes.push(Mesh {
+                    name: name.clone(),
+                    indices: Indices::new(indices, vertices.len()),
+                    vertices,
+                    transform,
+                    material: primitive.material,
+                });
+            }
+        }
+
+        for &child in &node.children {
+            self.visit_node(child, transform, depth + 1, meshes)?;
+        }
+        Ok(())
+    }
+
+    fn primitive(&self, primitive: &Primitive) -> Result<(Vec<MeshVertex>, Vec<u32>)> {
+        let mode = primitive.mode.unwrap_or(MODE_TRIANGLES);
+        if mode != MODE_TRIANGLES {
+            return Err(self.error(format!(
+                "primitive mode {mode} is not supported, only triangle lists are"
+            )));
+        }
+
+        let attribute = |name: &str, kind: &str| {
+            primitive
+                .attributes
+                .get(name)
+                .map(|&accessor| self.read_floats(accessor, kind))
+                .transpose()
+        };
+        let positions = attribute("POSITION", "VEC3")?
+            .ok_or_else(|| self.error("primitive has no POSITION".to_owned()))?;
+        let normals = attribute("NORMAL", "VEC3")?;
+        let uvs = attribute("TEXCOORD_0", "VEC2")?;
+        let tangents = attribute("TANGENT", "VEC4")?;
+
+        // Every attribute has one element per vertex, and indices point into them; checked up front
+        // so a malformed file is an error rather than an out-of-bounds panic below
+        let count = positions.len() / 3;
+        for (name, values, components) in [
+            ("NORMAL", &normals, 3),
+            ("TEXCOORD_0", &uvs, 2),
+            ("TANGENT", &tangents, 4),
+        ] {
+            if let Some(values) = values
+                && values.len() / components != count
+            {
+                return Err(self.error(format!(
+                    "{name} has {} elements, POSITION has {count}",
+                    values.len() / components
+                )));
+            }
+        }
+
+        let mut indices = match primitive.indices {
+            Some(accessor) => self.read_indices(accessor)?,
+            None => (0..count as u32).collect(),
+        };
+        if indices.len() % 3 != 0 {
+            return Err(self.error(format!(
+                "{} indices don't make whole triangles",
+                indices.len()
+            )));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= count) {
+            return Err(self.error(format!(
+                "index {index} is out of range for {count} vertices"
+            )));
+        }
+
+        let mut vertices: Vec<MeshVertex> = (0..count)
+            .map(|i| MeshVertex {
+                position: [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]],
+                normal: normals
+                    .as_ref()
+                    .map_or([0.0; 3], |n| [n[i * 3], n[i * 3 + 1], n[i * 3 + 2]]),
+                uv: uvs
+                    .as_ref()
+                    .map_or([0.0; 2], |uv| [uv[i * 2], uv[i * 2 + 1]]),
+                tangent: tangents.as_ref().map_or([0.0; 4], |t| {
+                    [t[i * 4], t[i * 4 + 1], t[i * 4 + 2], t[i * 4 + 3]]
+                }),
+            })
+            .collect();
+
+        if normals.is_none() {
+            (vertices, indices) = flat_normals(&vertices, &indices);
+        }
+        if tangents.is_none() {
+            generate_tangents(&mut vertices, &indices);
+        }
+        Ok((vertices, indices))
+    }
+
+    fn material(&self, index: usize) -> Result<Material> {
+        let material = &self.doc.materials[index];
+        let pbr = material.pbr_metallic_roughness.as_ref();
+        let texture = |info: Option<&TextureInfo>| -> Result<Option<usize>> {
+            let Some(info) = info else {
+                return Ok(None);
+            };
+            if info.tex_coord != 0 {
+                return Err(self.error(format!(
+                    "material {index} uses TEXCOORD_{}, only TEXCOORD_0 is supported",
+                    info.tex_coord
+                )));
+            }
+            let texture = self
+                .doc
+                .textures
+                .get(info.index)
+                .ok_or_else(|| self.error(format!("texture {} doesn't exist", info.index)))?;
+            Ok(texture.source)
+        };
+
+        Ok(Material {
+            name: material
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Material {index}")),
+            base_color: Vec4::from(pbr.and_then(|p| p.base_color_factor).unwrap_or([1.0; 4])),
+            base_color_texture: texture(pbr.and_then(|p| p.base_color_texture.as_ref()))?,
+            normal_texture: texture(material.normal_texture.as_ref())?,
+            normal_scale: material
+                .normal_texture
+                .as_ref()
+                .and_then(|t| t.scale)
+                .unwrap_or(1.0),
+        })
+    }
+
+    fn image(&self, index: usize) -> Result<Image> {
+        let image = &self.doc.images[index];
+        let bytes = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) => read_uri(self.path, uri)?,
+            (None, Some(view)) => self.view_bytes(view)?.to_vec(),
+            (None, None) => {
+                return Err(self.error(format!("image {index} has no uri or buffer view")));
+            }
+        };
+
+        decode_image(&bytes).map_err(|message| self.error(format!("image {index}: {message}")))
+    }
+
+    fn view_bytes(&self, index: usize) -> Result<&[u8]> {
+        let view = self
+            .doc
+            .buffer_views
+            .get(index)
+            .ok_or_else(|| self.error(format!("buffer view {index} doesn't exist")))?;
+        self.buffers
+            .get(view.buffer)
+            .and_then(|b| b.get(view.byte_offset..view.byte_offset + view.byte_length))
+            .ok_or_else(|| self.error(format!("buffer view {index} is out of bounds")))
+    }
+
+    // Calls `f` with the component type and bytes of every component of the accessor
+    fn visit_accessor(
+        &self,
+        index: usize,
+        kind: &str,
+        mut f: impl FnMut(u32, &[u8]),
+    ) -> Result<()> {
+        let accessor = self
+            .doc
+            .accessors
+            .get(index)
+            .ok_or_else(|| self.error(format!("accessor {index} doesn't exist")))?;
+        if accessor.kind != kind {
+            return Err(self.error(format!(
+                "accessor {index} is {}, expected {kind}",
+                accessor.kind
+            )));
+        }
+        if accessor.sparse.is_some() {
+            return Err(self.error(format!(
+                "accessor {index} is sparse, which is not supported"
+            )));
+        }
+        let component_size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => {
+                return Err(self.error(format!(
+                    "accessor {index} has unknown component type {other}"
+                )));
+            }
+        };
+        let components = match kind {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            _ => 4,
+        };
+        let element_size = component_size * components;
+
+        // An accessor without a buffer view reads as zeros
+        let Some(view_index) = accessor.buffer_view else {
+            let zero = [0; 4];
+            for _ in 0..accessor.count * components {
+                f(accessor.component_type, &zero[..component_size]);
+            }
+            return Ok(());
+        };
+        let bytes = self.view_bytes(view_index)?;
+        let stride = self.doc.buffer_views[view_index]
+            .byte_stride
+            .unwrap_or(element_size);
+        let end = match accessor.count {
+            0 => accessor.byte_offset,
+            n => accessor.byte_offset + stride * (n - 1) + element_size,
+        };
+        if end > bytes.len() {
+            return Err(self.error(format!("accessor {index} is out of bounds")));
+        }
+
+        for element in 0..accessor.count {
+            let start = accessor.byte_offset + element * stride;
+            for component in bytes[start..start + element_size].chunks_exact(component_size) {
+                f(accessor.component_type, component);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_floats(&self, index: usize, kind: &str) -> Result<Vec<f32>> {
+        let normalized = self.doc.accessors.get(index).is_some_and(|a| a.normalized);
+        let mut values = Vec::new();
+        let mut integer = false;
+        self.visit_accessor(index, kind, |ty, bytes| {
+            integer |= ty != 5126;
+            values.push(match ty {
+                5120 => normalize(bytes[0] as i8 as f32, 127.0, normalized),
+                5121 => normalize(bytes[0] as f32, 255.0, normalized),
+                5122 => normalize(
+                    i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+                    32767.0,
+                    normalized,
+                ),
+                5123 => normalize(
+                    u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+                    65535.0,
+                    normalized,
+                ),
+                5125 => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+                _ => f32::from_le_bytes(bytes.try_into().unwrap()),
+            });
+        })?;
+        if integer && !normalized {
+            return Err(self.error(format!(
+                "accessor {index} holds integers, expected floats or normalized integers"
+            )));
+        }
+        Ok(values)
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>> {
+        let mut indices = Vec::new();
+        let mut float = false;
+        self.visit_accessor(index, "SCALAR", |ty, bytes| {
+            float |= ty == 5126;
+            indices.push(match bytes.len() {
+                1 => bytes[0] as u32,
+                2 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+                _ => u32::from_le_bytes(bytes.try_into().unwrap()),
+            });
+        })?;
+        if float {
+            return Err(self.error(format!("index accessor {index} holds floats")));
+        }
+        Ok(indices)
+    }
+}
+
+fn normalize(value: f32, max: f32, normalized: bool) -> f32 {
+    if normalized {
+        (value / max).max(-1.0)
+    } else {
+        value
+    }
+}
+
+// JSON and optional BIN chunk of a `.glb`
+fn split_glb(bytes: &[u8]) -> std::result::Result<(&[u8], Option<&[u8]>), String> {
+    let word = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    };
+    let version = word(4).ok_or("truncated GLB header")?;
+    if version != 2 {
+        return Err(format!("GLB version {version} is not supported"));
+    }
+    let length = word(8).ok_or("truncated GLB header")? as usize;
+    let bytes = bytes
+        .get(..length)
+        .ok_or("GLB is shorter than its header says")?;
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset < bytes.len() {
+        let chunk_length = word(offset).ok_or("truncated GLB chunk")? as usize;
+        let chunk_type = word(offset + 4).ok_or("truncated GLB chunk")?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or("GLB chunk is out of bounds")?;
+        match chunk_type {
+            CHUNK_JSON if json.is_none() => json = Some(data),
+            CHUNK_BIN if bin.is_none() => bin = Some(data),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+
+    Ok((json.ok_or("GLB has no JSON chunk")?, bin))
+}
+
+// Buffer and image URIs are either base64 `data:` URIs or paths relative to the model
+fn read_uri(model: &Path, uri: &str) -> Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .ok_or_else(|| model_error(model, "data URI is not base64".to_owned()))?;
+        return decode_base64(encoded)
+            .ok_or_else(|| model_error(model, "data URI has invalid base64".to_owned()));
+    }
+    let path = model.parent().unwrap_or(Path::new("")).join(uri);
+    std::fs::read(&path).map_err(|err| model_error(model, format!("{}: {err}", path.display())))
+}
+
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.bytes().filter(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use super::{Indices, Material, Mesh, MeshVertex, Model, generate_tangents, model_error};
+use crate::error::Result;
+use crate::golden::Image;
+use crate::texture::decode_image;
+
+// Position, UV and normal indices of one face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// Loads a Wavefront OBJ with the materials of its MTL libraries. Polygons are fan-triangulated,
+// every distinct `v/vt/vn` triple becomes one vertex and missing normals are smoothed over faces
+// sharing a position. OBJ UVs start at the bottom left, they are flipped to wgpu's top-left origin
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Model> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)?;
+    let mut parser = Parser {
+        name: path
+            .file_stem()
+            .map_or_else(String::new, |s| s.to_string_lossy().into_owned()),
+        ..Parser::default()
+    };
+    for (i, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        if keyword == "mtllib" {
+            for file in &args {
+                parser.load_mtl(path, file)?;
+            }
+            continue;
+        }
+        parser
+            .statement(keyword, &args)
+            .map_err(|message| model_error(path, format!("line {}: {message}", i + 1)))?;
+    }
+    Ok(parser.finish())
+}
+
+#[derive(Default)]
+struct Group {
+    name: String,
+    material: Option<usize>,
+    triangles: Vec<[Corner; 3]>,
+}
+
+#[derive(Default)]
+struct Parser {
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    name: String,
+    material: Option<usize>,
+    groups: Vec<Group>,
+    materials: Vec<Material>,
+    material_names: HashMap<String, usize>,
+    images: Vec<Image>,
+    image_paths: HashMap<PathBuf, usize>,
+}
+
+impl Parser {
+    fn statement(&mut self, keyword: &str, args: &[&str]) -> std::result::Result<(), String> {
+        match keyword {
+            "v" => self.positions.push(Vec3::from_slice(&floats(args, 3)?)),
+            "vt" => {
+                let uv = floats(args, 1)?;
+                self.uvs
+                    .push(Vec2::new(uv[0], 1.0 - uv.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => self.normals.push(Vec3::from_slice(&floats(args, 3)?)),
+            "f" => self.face(args)?,
+            "o" | "g" => self.name = args.join(" "),
+            "usemtl" => {
+                let name = args.join(" ");
+                self.material = Some(
+                    *self
+                        .material_names
+                        .get(&name)
+                        .ok_or_else(|| format!("unknown material {name:?}"))?,
+                );
+            }
+            // Smoothing groups, lines, points, curves and the rest don't affect triangle meshes
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn face(&mut self, args: &[&str]) -> std::result::Result<(), String> {
+        if args.len() < 3 {
+            return Err(format!(
+                "a face needs 3 or more vertices, got {}",
+                args.len()
+            ));
+        }
+        let corners = args
+            .iter()
+            .map(|corner| self.corner(corner))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let key = (self.name.as_str(), self.material);
+        let group = match self
+            .groups
+            .iter()
+            .position(|g| (g.name.as_str(), g.material) == key)
+        {
+            Some(i) => &mut self.groups[i],
+            None => {
+                self.groups.push(Group {
+                    name: self.name.clone(),
+                    material: self.material,
+                    triangles: Vec::new(),
+                });
+                self.groups.last_mut().unwrap()
+            }
+        };
+        for i in 1..corners.len() - 1 {
+            group
+                .triangles
+                .push([corners[0], corners[i], corners[i + 1]]);
+        }
+        Ok(())
+    }
+
+    // `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indices count back from the latest element
+    fn corner(&self, corner: &str) -> std::result::Result<Corner, String> {
+        let mut parts = corner.split('/');
+        let position = resolve(parts.next(), self.positions.len(), "position")?
+            .ok_or_else(|| format!("face corner {corner:?} has no position"))?;
+        let uv = resolve(parts.next(), self.uvs.len(), "texture coordinate")?;
+        let normal = resolve(parts.next(), self.normals.len(), "normal")?;
+        Ok((position, uv, normal))
+    }
+
+    fn load_mtl(&mut self, obj: &Path, file: &str) -> Result<()> {
+        let path = obj.parent().unwrap_or(Path::new("")).join(file);
+        let source = std::fs::read_to_string(&path)
+            .map_err(|err| model_error(obj, format!("{}: {err}", path.display())))?;
+
+        let mut current = None;
+        for (i, line) in source.lines().enumerate() {
+            let error = |message: String| model_error(&path, format!("line {}: {message}", i + 1));
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            let args: Vec<&str> = words.collect();
+
+            if keyword == "newmtl" {
+                let name = args.join(" ");
+                self.material_names
+                    .insert(name.clone(), self.materials.len());
+                current = Some(self.materials.len());
+                self.materials.push(Material {
+                    name,
+                    base_color: Vec4::ONE,
+                    base_color_texture: None,
+                    normal_texture: None,
+                    normal_scale: 1.0,
+                });
+                continue;
+            }
+            let Some(index) = current else {
+                return Err(error(format!("{keyword} before newmtl")));
+            };
+
+            match keyword {
+                "Kd" => {
+                    let color = floats(&args, 3).map_err(error)?;
+                    let alpha = self.materials[index].base_color.w;
+                    self.materials[index].base_color = Vec3::from_slice(&color).extend(alpha);
+                }
+                "d" => self.materials[index].base_color.w = floats(&args, 1).map_err(error)?[0],
+                "Tr" => {
+                    self.materials[index].base_color.w = 1.0 - floats(&args, 1).map_err(error)?[0];
+                }
+                "map_Kd" => {
+                    let image = self.image(&path, &args).map_err(error)?;
+                    self.materials[index].base_color_texture = Some(image);
+                }
+                "map_Bump" | "map_bump" | "bump" | "norm" => {
+                    let image = self.image(&path, &args).map_err(error)?;
+                    self.materials[index].normal_texture = Some(image);
+                    if let Some(at) = args.iter().position(|&a| a == "-bm") {
+                        let scale = args.get(at + 1).and_then(|s| s.parse().ok());
+                        self.materials[index].normal_scale =
+                            scale.ok_or_else(|| error("-bm needs a number".to_owned()))?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Texture maps end with the file name, options like `-bm 0.5` come before it
+    fn image(&mut self, mtl: &Path, args: &[&str]) -> std::result::Result<usize, String> {
+        let file = args.last().ok_or("texture map needs a file name")?;
+        let path = mtl.parent().unwrap_or(Path::new("")).join(file);
+        if let Some(&index) = self.image_paths.get(&path) {
+            return Ok(index);
+        }
+
+        let bytes = std::fs::read(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let image = decode_image(&bytes).map_err(|err| format!("{file}: {err}"))?;
+        self.images.push(image);
+        self.image_paths.insert(path, self.images.len() - 1);
+        Ok(self.images.len() - 1)
+    }
+
+    fn finish(self) -> Model {
+        let meshes = self
+            .groups
+            .iter()
+            .filter(|group| !group.triangles.is_empty())
+            .map(|group| self.mesh(group))
+            .collect();
+
+        Model {
+            meshes,
+            materials: self.materials,
+            images: self.images,
+        }
+    }
+
+    fn mesh(&self, group: &Group) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(group.triangles.len() * 3);
+        let mut lookup: HashMap<Corner, u32> = HashMap::new();
+        let mut smooth = vec![Vec3::ZERO; self.positions.len()];
+        let mut missing_normals = false;
+
+        for triangle in &group.triangles {
+            let positions = triangle.map(|(p, _, _)| self.positions[p]);
+            let [a, b, c] = positions;
+            let face_normal = (b - a).cross(c - a).normalize_or_zero();
+            for (i, &corner) in triangle.iter().enumerate() {
+                let (position, uv, normal) = corner;
+                // Weighting by the corner angle keeps the result independent of how polygons
+                // were split into triangles
+                let here = positions[i];
+                let angle =
+                    (positions[(i + 1) % 3] - here).angle_between(positions[(i + 2) % 3] - here);
+                if angle.is_finite() {
+                    smooth[position] += face_normal * angle;
+                }
+                missing_normals |= normal.is_none();
+                let index = *lookup.entry(corner).or_insert_with(|| {
+                    vertices.push(MeshVertex {
+                        position: self.positions[position].to_array(),
+                        normal: normal.map_or([0.0; 3], |n| self.normals[n].to_array()),
+                        uv: uv.map_or([0.0; 2], |t| self.uvs[t].to_array()),
+                        tangent: [0.0; 4],
+                    });
+                    vertices.len() as u32 - 1
+                });
+                indices.push(index);
+            }
+        }
+
+        if missing_normals {
+            for (&(position, _, normal), &index) in &lookup {
+                if normal.is_none() {
+                    vertices[index as usize].normal =
+                        smooth[position].normalize_or_zero().to_array();
+                }
+            }
+        }
+        generate_tangents(&mut vertices, &indices);
+
+        Mesh {
+            name: group.name.clone(),
+            indices: Indices::new(indices, vertices.len()),
+            vertices,
+            transform: Mat4::IDENTITY,
+            material: group.material,
+        }
+    }
+}
+
+fn floats(args: &[&str], min: usize) -> std::result::Result<Vec<f32>, String> {
+    if args.len() < min {
+        return Err(format!("expected {min} numbers, got {}", args.len()));
+    }
+    args.iter()
+        .map(|arg| arg.parse().map_err(|_| format!("{arg:?} is not a number")))
+        .collect()
+}
+
+fn resolve(
+    index: Option<&str>,
+    len: usize,
+    kind: &str,
+) -> std::result::Result<Option<usize>, String> {
+    let Some(index) = index.filter(|i| !i.is_empty()) else {
+        return Ok(None);
+    };
+    let value: i64 = index
+        .parse()
+        .map_err(|_| format!("{index:?} is not a {kind} index"))?;
+    let resolved = match value {
+        0 => None,
+        1.. => Some(value as usize - 1),
+        _ => (len as i64 + value).try_into().ok(),
+    };
+    match resolved {
+        Some(i) if i < len => Ok(Some(i)),
+        _ => Err(format!("{kind} index {value} is out of range for {len}")),
+    }
+}
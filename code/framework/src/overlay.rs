@@ -0,0 +1,343 @@
+use std::sync::Mutex;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferBindingType,
+    BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    Extent3d, FragmentState, LoadOp, MultisampleState, Operations, Origin3d,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderStages, StoreOp, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexAttribute, VertexBufferLayout,
+    VertexFormat, VertexState, VertexStepMode, include_wgsl,
+};
+
+use crate::GpuContext;
+
+const GLYPH_SIZE: u32 = 8;
+const ATLAS_COLUMNS: u32 = 16;
+// Printable ASCII, from ' ' to '~', followed by a solid cell used for rectangles
+const FIRST_GLYPH: u8 = b' ';
+const GLYPH_COUNT: u32 = 95;
+const SOLID_GLYPH: u32 = GLYPH_COUNT;
+const SCALE: f32 = 2.0;
+const MARGIN: f32 = 8.0;
+
+pub const GLYPH_WIDTH: f32 = GLYPH_SIZE as f32 * SCALE;
+pub const LINE_HEIGHT: f32 = GLYPH_WIDTH + 4.0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GlyphInstance {
+    origin: [f32; 2],
+    size: [f32; 2],
+    glyph: u32,
+    color: u32,
+}
+
+#[derive(Default)]
+struct OverlayState {
+    lines: Vec<String>,
+    instances: Vec<GlyphInstance>,
+}
+
+pub struct Overlay {
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    screen_buffer: Buffer,
+    state: Mutex<OverlayState>,
+}
+
+impl Overlay {
+    pub fn new(device: &Device, queue: &Queue, format: TextureFormat) -> Self {
+        let shader_module = device.create_shader_module(include_wgsl!("overlay.wgsl"));
+
+        let atlas_view = create_atlas(device, queue);
+
+        let screen_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Overlay Screen Buffer"),
+            size: 16,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Overlay Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Overlay Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: screen_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&atlas_view),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &[Some(VertexBufferLayout {
+                    array_stride: size_of::<GlyphInstance>() as u64,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: &[
+                        VertexAttribute {
+                            format: VertexFormat::Float32x2,
+                            offset: 0,
+                            shader_location: 0,
+                        },
+                        VertexAttribute {
+                            format: VertexFormat::Float32x2,
+                            offset: 8,
+                            shader_location: 1,
+                        },
+                        VertexAttribute {
+                            format: VertexFormat::Uint32,
+                            offset: 16,
+                            shader_location: 2,
+                        },
+                        VertexAttribute {
+                            format: VertexFormat::Uint32,
+                            offset: 20,
+                            shader_location: 3,
+                        },
+                    ],
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            screen_buffer,
+            state: Mutex::new(OverlayState::default()),
+        }
+    }
+
+    // Everything pushed is drawn once, on top of the next frame, and then cleared
+    pub fn push_line(&self, line: impl Into<String>) {
+        self.state.lock().unwrap().lines.push(line.into());
+    }
+
+    // Positions are in physical pixels from the top-left corner, colors are linear RGBA
+    pub fn text(&self, position: Vec2, text: &str, color: [f32; 4]) {
+        let mut state = self.state.lock().unwrap();
+        push_text(&mut state.instances, position, text, color);
+    }
+
+    pub fn rect(&self, position: Vec2, size: Vec2, color: [f32; 4]) {
+        self.state.lock().unwrap().instances.push(GlyphInstance {
+            origin: position.to_array(),
+            size: size.to_array(),
+            glyph: SOLID_GLYPH,
+            color: pack_color(color),
+        });
+    }
+
+    pub fn text_width(text: &str) -> f32 {
+        text.len() as f32 * GLYPH_WIDTH
+    }
+
+    // Drops everything pushed this frame without drawing it
+    pub fn clear(&self) {
+        *self.state.lock().unwrap() = OverlayState::default();
+    }
+
+    pub fn render(&self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
+        let OverlayState { lines, instances } = std::mem::take(&mut *self.state.lock().unwrap());
+
+        let mut lines_instances = Vec::new();
+        for (row, line) in lines.iter().enumerate() {
+            let position = Vec2::new(MARGIN, MARGIN + row as f32 * LINE_HEIGHT);
+            push_text(&mut lines_instances, position, line, [1.0; 4]);
+        }
+        let instances = [lines_instances, instances].concat();
+        if instances.is_empty() {
+            return;
+        }
+
+        let screen = [
+            ctx.surface_config.width as f32,
+            ctx.surface_config.height as f32,
+            0.0,
+            0.0,
+        ];
+        ctx.queue
+            .write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&screen));
+
+        let instance_buffer = ctx.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Overlay Instance Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, instance_buffer.slice(..));
+        rpass.draw(0..4, 0..instances.len() as u32);
+    }
+}
+
+// 16 glyphs per row from the public domain font8x8 set, one byte per texel
+fn create_atlas(device: &Device, queue: &Queue) -> TextureView {
+    let width = ATLAS_COLUMNS * GLYPH_SIZE;
+    let height = (GLYPH_COUNT + 1).div_ceil(ATLAS_COLUMNS) * GLYPH_SIZE;
+    let mut texels = vec![0u8; (width * height) as usize];
+
+    let solid_x = (SOLID_GLYPH % ATLAS_COLUMNS) * GLYPH_SIZE;
+    let solid_y = (SOLID_GLYPH / ATLAS_COLUMNS) * GLYPH_SIZE;
+    for y in solid_y..solid_y + GLYPH_SIZE {
+        let row = (y * width + solid_x) as usize;
+        texels[row..row + GLYPH_SIZE as usize].fill(255);
+    }
+
+    for glyph in 0..GLYPH_COUNT {
+        let bitmap = font8x8::legacy::BASIC_LEGACY[(glyph + FIRST_GLYPH as u32) as usize];
+        let cell_x = (glyph % ATLAS_COLUMNS) * GLYPH_SIZE;
+        let cell_y = (glyph / ATLAS_COLUMNS) * GLYPH_SIZE;
+        for (y, row) in bitmap.iter().enumerate() {
+            for x in 0..GLYPH_SIZE {
+                // Bit 0 is the leftmost pixel
+                if row & (1 << x) != 0 {
+                    let index = (cell_y + y as u32) * width + cell_x + x;
+                    texels[index as usize] = 255;
+                }
+            }
+        }
+    }
+
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("Overlay Font Atlas"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::R8Unorm,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        &texels,
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(width),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+
+    texture.create_view(&TextureViewDescriptor::default())
+}
+
+fn push_text(instances: &mut Vec<GlyphInstance>, position: Vec2, text: &str, color: [f32; 4]) {
+    for (column, byte) in text.bytes().enumerate() {
+        let glyph = match byte {
+            b' ' => continue,
+            b'!'..=b'~' => (byte - FIRST_GLYPH) as u32,
+            _ => (b'?' - FIRST_GLYPH) as u32,
+        };
+        let origin = position + Vec2::new(column as f32 * GLYPH_WIDTH, 0.0);
+        // A dark copy one scaled pixel down-right keeps the text readable on any background
+        for (offset, color) in [(SCALE, [0.0, 0.0, 0.0, color[3]]), (0.0, color)] {
+            instances.push(GlyphInstance {
+                origin: (origin + offset).to_array(),
+                size: [GLYPH_WIDTH; 2],
+                glyph,
+                color: pack_color(color),
+            });
+        }
+    }
+}
+
+fn pack_color(color: [f32; 4]) -> u32 {
+    u32::from_le_bytes(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
@@ -0,0 +1,49 @@
+struct Screen {
+    size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> screen: Screen;
+
+@group(0) @binding(1)
+var atlas: texture_2d<f32>;
+
+struct GlyphInput {
+    @location(0) origin: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) glyph: u32,
+    @location(3) color: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) texel: vec2<f32>,
+    @location(1) @interpolate(flat) glyph: u32,
+    @location(2) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, glyph: GlyphInput) -> VertexOutput {
+    // Triangle strip corners: (0, 0), (1, 0), (0, 1), (1, 1)
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u));
+    let pixel = glyph.origin + corner * glyph.size;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(pixel / screen.size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.texel = corner * 8.0;
+    out.glyph = glyph.glyph;
+    out.color = unpack4x8unorm(glyph.color);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = vec2<u32>(in.glyph % 16u, in.glyph / 16u) * 8u;
+    let texel = vec2<u32>(min(in.texel, vec2<f32>(7.0)));
+    let coverage = textureLoad(atlas, cell + texel, 0).r;
+    if (coverage < 0.5) {
+        discard;
+    }
+    return in.color;
+}
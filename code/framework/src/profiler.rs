@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePassTimestampWrites, Device,
+    Features, MapMode, PollType, QUERY_SIZE, QuerySet, QuerySetDescriptor, QueryType, Queue,
+    RenderPassTimestampWrites,
+};
+
+const MAX_SCOPES: u32 = 32;
+const READBACK_FRAMES: usize = 3;
+const HISTORY: usize = 60;
+
+pub struct GpuProfiler {
+    timer: Option<GpuTimer>,
+    state: Mutex<ProfilerState>,
+}
+
+struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readbacks: Vec<Buffer>,
+    // Nanoseconds per timestamp tick
+    period: f32,
+}
+
+#[derive(Default)]
+struct ProfilerState {
+    scopes: Vec<&'static str>,
+    pending: VecDeque<PendingFrame>,
+    free: Vec<usize>,
+    gpu: Vec<(&'static str, VecDeque<Duration>)>,
+    cpu: VecDeque<Duration>,
+    last_frame: Option<Instant>,
+}
+
+struct PendingFrame {
+    readback: usize,
+    scopes: Vec<&'static str>,
+    // `None` until the frame has been submitted and `map_async` requested, then set to whether the
+    // mapping succeeded once it finished
+    mapped: Option<Arc<OnceLock<bool>>>,
+}
+
+impl GpuProfiler {
+    // Without TIMESTAMP_QUERY only CPU frame times are collected
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let timer = device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(device, queue));
+
+        Self {
+            state: Mutex::new(ProfilerState {
+                free: (0..timer.as_ref().map_or(0, |t| t.readbacks.len())).collect(),
+                ..Default::default()
+            }),
+            timer,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    pub fn render_pass(&self, name: &'static str) -> Option<RenderPassTimestampWrites<'_>> {
+        let (query_set, begin) = self.begin_scope(name)?;
+        Some(RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(begin),
+            end_of_pass_write_index: Some(begin + 1),
+        })
+    }
+
+    pub fn compute_pass(&self, name: &'static str) -> Option<ComputePassTimestampWrites<'_>> {
+        let (query_set, begin) = self.begin_scope(name)?;
+        Some(ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(begin),
+            end_of_pass_write_index: Some(begin + 1),
+        })
+    }
+
+    fn begin_scope(&self, name: &'static str) -> Option<(&QuerySet, u32)> {
+        let timer = self.timer.as_ref()?;
+        let mut state = self.state.lock().unwrap();
+        if state.scopes.len() as u32 >= MAX_SCOPES {
+            return None;
+        }
+        state.scopes.push(name);
+        Some((&timer.query_set, (state.scopes.len() as u32 - 1) * 2))
+    }
+
+    // Records the copy of this frame's timestamps; call once per frame before submitting
+    pub fn resolve(&self, encoder: &mut CommandEncoder) {
+        let Some(timer) = &self.timer else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let scopes = std::mem::take(&mut state.scopes);
+        if scopes.is_empty() {
+            return;
+        }
+        // All readback buffers are still in flight, so this frame's timings are dropped
+        let Some(readback) = state.free.pop() else {
+            return;
+        };
+
+        let count = scopes.len() as u32 * 2;
+        let size = count as u64 * QUERY_SIZE as u64;
+        encoder.resolve_query_set(&timer.query_set, 0..count, &timer.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &timer.resolve_buffer,
+            0,
+            &timer.readbacks[readback],
+            0,
+            size,
+        );
+
+        state.pending.push_back(PendingFrame {
+            readback,
+            scopes,
+            mapped: None,
+        });
+    }
+
+    // Call after submitting; never blocks on the GPU
+    pub fn end_frame(&self, device: &Device) {
+        let mut state = self.state.lock().unwrap();
+
+        let now = Instant::now();
+        if let Some(last) = state.last_frame.replace(now) {
+            push_sample(&mut state.cpu, now - last);
+        }
+
+        let Some(timer) = &self.timer else {
+            return;
+        };
+
+        for frame in state.pending.iter_mut().filter(|f| f.mapped.is_none()) {
+            let mapped = Arc::new(OnceLock::new());
+            let result_slot = mapped.clone();
+            let size = frame.scopes.len() as u64 * 2 * QUERY_SIZE as u64;
+            timer.readbacks[frame.readback]
+                .slice(..size)
+                .map_async(MapMode::Read, move |result| {
+                    let _ = result_slot.set(result.is_ok());
+                });
+            frame.mapped = Some(mapped);
+        }
+
+        let _ = device.poll(PollType::Poll);
+
+        while let Some(frame) = state.pending.front() {
+            let Some(&ok) = frame.mapped.as_ref().and_then(|mapped| mapped.get()) else {
+                break;
+            };
+            let frame = state.pending.pop_front().unwrap();
+            // A failed mapping leaves the buffer unmapped, it only loses this frame's timings
+            state.free.push(frame.readback);
+            if !ok {
+                continue;
+            }
+            let buffer = &timer.readbacks[frame.readback];
+
+            let timestamps: Vec<u64> = {
+                let size = frame.scopes.len() as u64 * 2 * QUERY_SIZE as u64;
+                let data = buffer
+                    .slice(..size)
+                    .get_mapped_range()
+                    .expect("Failed to access timestamp buffer");
+                data.chunks_exact(8)
+                    .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect()
+            };
+            buffer.unmap();
+
+            for (name, pair) in frame.scopes.iter().zip(timestamps.chunks_exact(2)) {
+                let ticks = pair[1].saturating_sub(pair[0]);
+                let sample = Duration::from_nanos((ticks as f64 * timer.period as f64) as u64);
+                let index = match state.gpu.iter().position(|(n, _)| n == name) {
+                    Some(index) => index,
+                    None => {
+                        state.gpu.push((name, VecDeque::new()));
+                        state.gpu.len() - 1
+                    }
+                };
+                push_sample(&mut state.gpu[index].1, sample);
+            }
+        }
+    }
+
+    // Rolling averages in the order passes were first seen
+    pub fn timings(&self) -> Vec<(&'static str, Duration)> {
+        let state = self.state.lock().unwrap();
+        state
+            .gpu
+            .iter()
+            .map(|(name, samples)| (*name, average(samples)))
+            .collect()
+    }
+
+    pub fn cpu_frame_time(&self) -> Duration {
+        average(&self.state.lock().unwrap().cpu)
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let cpu = self.cpu_frame_time();
+        let fps = if cpu.is_zero() {
+            0.0
+        } else {
+            1.0 / cpu.as_secs_f64()
+        };
+
+        let mut lines = vec![format!("CPU {:.2} ms ({fps:.0} FPS)", as_millis(cpu))];
+        for (name, time) in self.timings() {
+            lines.push(format!("{name} {:.2} ms", as_millis(time)));
+        }
+        lines
+    }
+}
+
+impl GpuTimer {
+    fn new(device: &Device, queue: &Queue) -> Self {
+        let size = (MAX_SCOPES * 2) as u64 * QUERY_SIZE as u64;
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("Profiler Query Set"),
+            ty: QueryType::Timestamp,
+            count: MAX_SCOPES * 2,
+        });
+
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Profiler Resolve Buffer"),
+            size,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readbacks = (0..READBACK_FRAMES)
+            .map(|_| {
+                device.create_buffer(&BufferDescriptor {
+                    label: Some("Profiler Readback Buffer"),
+                    size,
+                    usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+
+        Self {
+            query_set,
+            resolve_buffer,
+            readbacks,
+            period: queue.get_timestamp_period(),
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<Duration>, sample: Duration) {
+    if samples.len() == HISTORY {
+        samples.pop_front();
+    }
+    samples.push_back(sample);
+}
+
+fn average(samples: &VecDeque<Duration>) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    samples.iter().sum::<Duration>() / samples.len() as u32
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
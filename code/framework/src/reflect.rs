@@ -0,0 +1,369 @@
+use std::num::NonZeroU64;
+
+use wgpu::naga::proc::Layouter;
+use wgpu::naga::valid::ModuleInfo;
+use wgpu::naga::{
+    AddressSpace, ImageClass, ImageDimension, Module, ScalarKind, ShaderStage, StorageAccess,
+    TypeInner,
+};
+use wgpu::util::TextureFormatExt;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, BufferUsages,
+    Device, PipelineLayout, PipelineLayoutDescriptor, Sampler, SamplerBindingType, ShaderStages,
+    StorageTextureAccess, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDimension,
+};
+
+use crate::error::{Error, Result};
+use crate::shader::parse;
+
+// Bind group and pipeline layouts derived from a shader's `@group`/`@binding` declarations
+pub struct ShaderLayout {
+    label: String,
+    groups: Vec<Vec<BindGroupLayoutEntry>>,
+    pub bind_group_layouts: Vec<BindGroupLayout>,
+    pub pipeline_layout: PipelineLayout,
+}
+
+impl ShaderLayout {
+    // Bindings no entry point uses are left out, like in wgpu's automatic layouts.
+    // Float textures are filterable only if the shader samples them, samplers are always filtering
+    pub fn new(device: &Device, label: &str, module: &Module, info: &ModuleInfo) -> Result<Self> {
+        let error = |message: String| Error::Binding {
+            label: label.to_owned(),
+            message,
+        };
+
+        let mut layouter = Layouter::default();
+        layouter
+            .update(module.to_ctx())
+            .map_err(|err| error(err.to_string()))?;
+
+        let mut groups: Vec<Vec<BindGroupLayoutEntry>> = Vec::new();
+        for (handle, global) in module.global_variables.iter() {
+            let Some(binding) = &global.binding else {
+                continue;
+            };
+
+            let mut visibility = ShaderStages::empty();
+            let mut sampled = false;
+            for (index, entry_point) in module.entry_points.iter().enumerate() {
+                let function = info.get_entry_point(index);
+                if function[handle].is_empty() {
+                    continue;
+                }
+                visibility |= match entry_point.stage {
+                    ShaderStage::Vertex => ShaderStages::VERTEX,
+                    ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                    ShaderStage::Compute => ShaderStages::COMPUTE,
+                    stage => return Err(error(format!("{stage:?} shaders are not supported"))),
+                };
+                sampled |= function.sampling_set.iter().any(|key| key.image == handle);
+            }
+            if visibility.is_empty() {
+                continue;
+            }
+
+            let name = global.name.as_deref().unwrap_or("?");
+            let ty = match (global.space, &module.types[global.ty].inner) {
+                (AddressSpace::Uniform, _) => BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: NonZeroU64::new(layouter[global.ty].size as u64),
+                },
+                (AddressSpace::Storage { access }, _) => BindingType::Buffer {
+                    ty: BufferBindingType::Storage {
+                        read_only: !access.contains(StorageAccess::STORE),
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: NonZeroU64::new(layouter[global.ty].size as u64),
+                },
+                (AddressSpace::Handle, TypeInner::Sampler { comparison }) => {
+                    BindingType::Sampler(if *comparison {
+                        SamplerBindingType::Comparison
+                    } else {
+                        SamplerBindingType::Filtering
+                    })
+                }
+                (
+                    AddressSpace::Handle,
+                    TypeInner::Image {
+                        dim,
+                        arrayed,
+                        class,
+                    },
+                ) => {
+                    let view_dimension = view_dimension(*dim, *arrayed);
+                    match class {
+                        ImageClass::Sampled { kind, multi } => BindingType::Texture {
+                            sample_type: match kind {
+                                ScalarKind::Sint => TextureSampleType::Sint,
+                                ScalarKind::Uint => TextureSampleType::Uint,
+                                _ => TextureSampleType::Float {
+                                    filterable: sampled && !multi,
+                                },
+                            },
+                            view_dimension,
+                            multisampled: *multi,
+                        },
+                        ImageClass::Depth { multi } => BindingType::Texture {
+                            sample_type: TextureSampleType::Depth,
+                            view_dimension,
+                            multisampled: *multi,
+                        },
+                        ImageClass::Storage { format, access } => BindingType::StorageTexture {
+                            access: match (
+                                access.contains(StorageAccess::LOAD),
+                                access.contains(StorageAccess::STORE),
+                            ) {
+                                (true, true) => StorageTextureAccess::ReadWrite,
+                                (true, false) => StorageTextureAccess::ReadOnly,
+                                _ => StorageTextureAccess::WriteOnly,
+                            },
+                            format: TextureFormat::from_storage_format(*format),
+                            view_dimension,
+                        },
+                        ImageClass::External => {
+                            return Err(error(format!("external texture {name} is not supported")));
+                        }
+                    }
+                }
+                _ => return Err(error(format!("binding {name} has an unsupported type"))),
+            };
+
+            let group = binding.group as usize;
+            if groups.len() <= group {
+                groups.resize_with(group + 1, Vec::new);
+            }
+            groups[group].push(BindGroupLayoutEntry {
+                binding: binding.binding,
+                visibility,
+                ty,
+                count: None,
+            });
+        }
+
+        let bind_group_layouts: Vec<BindGroupLayout> = groups
+            .iter()
+            .enumerate()
+            .map(|(group, entries)| {
+                device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some(&format!("{label} Bind Group Layout {group}")),
+                    entries,
+                })
+            })
+            .collect();
+
+        let layouts: Vec<_> = bind_group_layouts.iter().map(Some).collect();
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(&format!("{label} Pipeline Layout")),
+            bind_group_layouts: &layouts,
+            immediate_size: 0,
+        });
+
+        Ok(Self {
+            label: label.to_owned(),
+            groups,
+            bind_group_layouts,
+            pipeline_layout,
+        })
+    }
+
+    pub fn from_wgsl(device: &Device, label: &str, source: &str) -> Result<Self> {
+        let (module, info) = parse(label, source)?;
+        Self::new(device, label, &module, &info)
+    }
+
+    pub fn entries(&self, group: u32) -> &[BindGroupLayoutEntry] {
+        self.groups.get(group as usize).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn bind_group(&self, group: u32) -> BindGroupBuilder<'_> {
+        BindGroupBuilder {
+            layout: self,
+            group,
+            label: None,
+            resources: Vec::new(),
+        }
+    }
+}
+
+enum Resource<'a> {
+    Buffer(&'a Buffer),
+    Texture(&'a TextureView),
+    Sampler(&'a Sampler),
+}
+
+// Checks every resource against the reflected binding before creating the bind group
+pub struct BindGroupBuilder<'a> {
+    layout: &'a ShaderLayout,
+    group: u32,
+    label: Option<&'a str>,
+    resources: Vec<(u32, Resource<'a>)>,
+}
+
+impl<'a> BindGroupBuilder<'a> {
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn buffer(mut self, binding: u32, buffer: &'a Buffer) -> Self {
+        self.resources.push((binding, Resource::Buffer(buffer)));
+        self
+    }
+
+    pub fn texture(mut self, binding: u32, view: &'a TextureView) -> Self {
+        self.resources.push((binding, Resource::Texture(view)));
+        self
+    }
+
+    pub fn sampler(mut self, binding: u32, sampler: &'a Sampler) -> Self {
+        self.resources.push((binding, Resource::Sampler(sampler)));
+        self
+    }
+
+    pub fn build(self, device: &Device) -> Result<BindGroup> {
+        let group = self.group;
+        let error = |message: String| Error::Binding {
+            label: format!("{} @group({group})", self.layout.label),
+            message,
+        };
+        let Some(layout) = self.layout.bind_group_layouts.get(group as usize) else {
+            return Err(error("the shader doesn't use this group".to_owned()));
+        };
+        let entries = self.layout.entries(group);
+
+        for entry in entries {
+            if !self.resources.iter().any(|(b, _)| *b == entry.binding) {
+                return Err(error(format!("@binding({}) is missing", entry.binding)));
+            }
+        }
+
+        let mut bind_group_entries = Vec::new();
+        for (binding, resource) in &self.resources {
+            let Some(entry) = entries.iter().find(|entry| entry.binding == *binding) else {
+                return Err(error(format!("the shader doesn't use @binding({binding})")));
+            };
+            check_resource(&entry.ty, resource)
+                .map_err(|message| error(format!("@binding({binding}): {message}")))?;
+
+            bind_group_entries.push(BindGroupEntry {
+                binding: *binding,
+                resource: match resource {
+                    Resource::Buffer(buffer) => buffer.as_entire_binding(),
+                    Resource::Texture(view) => BindingResource::TextureView(view),
+                    Resource::Sampler(sampler) => BindingResource::Sampler(sampler),
+                },
+            });
+        }
+
+        Ok(device.create_bind_group(&BindGroupDescriptor {
+            label: self.label,
+            layout,
+            entries: &bind_group_entries,
+        }))
+    }
+}
+
+fn check_resource(ty: &BindingType, resource: &Resource) -> std::result::Result<(), String> {
+    match (ty, resource) {
+        (
+            BindingType::Buffer {
+                ty,
+                min_binding_size,
+                ..
+            },
+            Resource::Buffer(buffer),
+        ) => {
+            let usage = match ty {
+                BufferBindingType::Uniform => BufferUsages::UNIFORM,
+                BufferBindingType::Storage { .. } => BufferUsages::STORAGE,
+            };
+            if !buffer.usage().contains(usage) {
+                return Err(format!("buffer needs {usage:?} usage"));
+            }
+            if let Some(size) = min_binding_size
+                && buffer.size() < size.get()
+            {
+                return Err(format!(
+                    "buffer is {} bytes, the shader expects at least {size}",
+                    buffer.size()
+                ));
+            }
+            Ok(())
+        }
+        (
+            BindingType::Texture {
+                sample_type,
+                multisampled,
+                ..
+            },
+            Resource::Texture(view),
+        ) => {
+            let texture = view.texture();
+            if !texture.usage().contains(TextureUsages::TEXTURE_BINDING) {
+                return Err("texture needs TEXTURE_BINDING usage".to_owned());
+            }
+            if *multisampled != (texture.sample_count() > 1) {
+                return Err(format!(
+                    "texture has {} samples, the shader expects {}",
+                    texture.sample_count(),
+                    if *multisampled { "several" } else { "one" }
+                ));
+            }
+            let format = texture.format();
+            let compatible = match (sample_type, format.sample_type(None, None)) {
+                (
+                    TextureSampleType::Float { filterable },
+                    Some(TextureSampleType::Float { filterable: f }),
+                ) => !filterable || f,
+                // Depth textures can also be read as unfilterable floats
+                (
+                    TextureSampleType::Float { filterable: false },
+                    Some(TextureSampleType::Depth),
+                ) => true,
+                (expected, actual) => Some(*expected) == actual,
+            };
+            if !compatible {
+                return Err(format!("{format:?} can't be bound as {sample_type:?}"));
+            }
+            Ok(())
+        }
+        (BindingType::StorageTexture { format, .. }, Resource::Texture(view)) => {
+            let texture = view.texture();
+            if !texture.usage().contains(TextureUsages::STORAGE_BINDING) {
+                return Err("texture needs STORAGE_BINDING usage".to_owned());
+            }
+            if texture.format() != *format {
+                return Err(format!(
+                    "texture is {:?}, the shader expects {format:?}",
+                    texture.format()
+                ));
+            }
+            Ok(())
+        }
+        (BindingType::Sampler(_), Resource::Sampler(_)) => Ok(()),
+        (ty, _) => Err(format!("expected {}", describe(ty))),
+    }
+}
+
+fn describe(ty: &BindingType) -> &'static str {
+    match ty {
+        BindingType::Buffer { .. } => "a buffer",
+        BindingType::Sampler(_) => "a sampler",
+        _ => "a texture view",
+    }
+}
+
+fn view_dimension(dim: ImageDimension, arrayed: bool) -> TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+    }
+}
@@ -0,0 +1,244 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use winit::dpi::PhysicalSize;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+use crate::Input;
+use crate::error::Result;
+use crate::input::{GamepadAxis, GamepadButton};
+
+static SEED: AtomicU64 = AtomicU64::new(0);
+static STREAM: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_seed(seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+    STREAM.store(0, Ordering::Relaxed);
+}
+
+pub fn seed() -> u64 {
+    SEED.load(Ordering::Relaxed)
+}
+
+// Each call returns a new generator, so the sequence of calls has to be deterministic too
+pub fn rng() -> StdRng {
+    let stream = STREAM.fetch_add(1, Ordering::Relaxed);
+    StdRng::seed_from_u64(seed() ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub dt: Duration,
+    pub keys: Vec<KeyCode>,
+    pub mouse_buttons: Vec<u64>,
+    pub mouse_delta: (f64, f64),
+    #[serde(default)]
+    pub cursor_position: Option<(f64, f64)>,
+    #[serde(default)]
+    pub scroll_delta: f64,
+    #[serde(default)]
+    pub modifiers: ModifiersState,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub gamepad_buttons: Vec<GamepadButton>,
+    #[serde(default)]
+    pub gamepad_axes: Vec<(GamepadAxis, f32)>,
+    // `None` in recordings made before the size was stored
+    #[serde(default)]
+    pub window_size: Option<PhysicalSize<u32>>,
+}
+
+impl FrameRecord {
+    pub fn capture(dt: Duration, input: &Input) -> Self {
+        Self {
+            dt,
+            keys: input.pressed_keys().collect(),
+            mouse_buttons: input.pressed_mouse_buttons().collect(),
+            mouse_delta: input.mouse_delta(),
+            cursor_position: input.cursor_position(),
+            scroll_delta: input.scroll_delta(),
+            modifiers: input.modifiers(),
+            text: input.text().to_owned(),
+            gamepad_buttons: input.pressed_gamepad_buttons().collect(),
+            gamepad_axes: input.gamepad_axes().collect(),
+            window_size: Some(input.window_size()),
+        }
+    }
+
+    pub fn to_input(&self) -> Input {
+        let mut input = Input::default();
+        self.apply(&mut input);
+        input
+    }
+
+    // Moves `input` from the previous frame to this one, so presses and releases show up as
+    // "just" pressed or released the same way they did while recording
+    pub fn apply(&self, input: &mut Input) {
+        input.clear_delta();
+        for key in input.pressed_keys().collect::<Vec<_>>() {
+            if !self.keys.contains(&key) {
+                input.release_key(key);
+            }
+        }
+        for &key in &self.keys {
+            input.press_key(key);
+        }
+        for button in input.pressed_mouse_buttons().collect::<Vec<_>>() {
+            if !self.mouse_buttons.contains(&button) {
+                input.release_mouse(button);
+            }
+        }
+        for &button in &self.mouse_buttons {
+            input.press_mouse(button);
+        }
+        for button in input.pressed_gamepad_buttons().collect::<Vec<_>>() {
+            if !self.gamepad_buttons.contains(&button) {
+                input.release_gamepad_button(button);
+            }
+        }
+        for &button in &self.gamepad_buttons {
+            input.press_gamepad_button(button);
+        }
+        // An axis missing from the frame is back at rest
+        input.clear_gamepad_axes();
+        for &(axis, value) in &self.gamepad_axes {
+            input.set_gamepad_axis(axis, value);
+        }
+        input.set_mouse_delta(self.mouse_delta.0, self.mouse_delta.1);
+        input.set_cursor_position(self.cursor_position);
+        input.add_scroll_delta(self.scroll_delta);
+        input.set_modifiers(self.modifiers);
+        input.push_text(&self.text);
+        if let Some(size) = self.window_size {
+            input.set_window_size(size);
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub frames: Vec<FrameRecord>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub enum ReplayMode {
+    #[default]
+    Live,
+    Record {
+        path: PathBuf,
+        recording: Recording,
+    },
+    Replay {
+        recording: Recording,
+        next: usize,
+        input: Box<Input>,
+    },
+}
+
+#[derive(Default)]
+pub struct Replay {
+    pub mode: ReplayMode,
+    pub fixed_dt: Option<Duration>,
+}
+
+impl Replay {
+    // Understands `--record=<file>`, `--replay=<file>` and `--fixed-timestep=<hz>`
+    pub fn from_args() -> Result<Self> {
+        let mut mode = ReplayMode::Live;
+        let mut fixed_dt = None;
+
+        for arg in std::env::args().skip(1) {
+            if let Some(path) = arg.strip_prefix("--record=") {
+                mode = ReplayMode::Record {
+                    path: path.into(),
+                    recording: Recording::default(),
+                };
+            } else if let Some(path) = arg.strip_prefix("--replay=") {
+                mode = ReplayMode::Replay {
+                    recording: Recording::load(Path::new(path))?,
+                    next: 0,
+                    input: Box::default(),
+                };
+            } else if let Some(hz) = arg.strip_prefix("--fixed-timestep=") {
+                match hz.parse::<f64>() {
+                    Ok(hz) if hz > 0.0 => fixed_dt = Some(Duration::from_secs_f64(1.0 / hz)),
+                    _ => warn!("Ignoring invalid fixed timestep {hz:?}"),
+                }
+            }
+        }
+
+        let seed = match &mut mode {
+            ReplayMode::Replay { recording, .. } => recording.seed,
+            ReplayMode::Record { recording, .. } => {
+                recording.seed = rand::random();
+                recording.seed
+            }
+            ReplayMode::Live => rand::random(),
+        };
+        set_seed(seed);
+
+        Ok(Self { mode, fixed_dt })
+    }
+
+    // Returns the dt and input to feed into `Example::update`, or `None` once a replay is over
+    pub fn next_frame(&mut self, dt: Duration, live: &Input) -> Option<(Duration, Option<Input>)> {
+        let dt = self.fixed_dt.unwrap_or(dt);
+
+        match &mut self.mode {
+            ReplayMode::Live => Some((dt, None)),
+            ReplayMode::Record { recording, .. } => {
+                recording.frames.push(FrameRecord::capture(dt, live));
+                Some((dt, None))
+            }
+            ReplayMode::Replay {
+                recording,
+                next,
+                input,
+            } => {
+                let frame = recording.frames.get(*next)?;
+                *next += 1;
+                // Old recordings have no size, they play back at the live one. Recordings store
+                // keys, not actions, so they play back through the current bindings
+                input.set_window_size(live.window_size());
+                frame.apply(input);
+                input.set_bindings(live.bindings().clone());
+                Some((frame.dt, Some(Input::clone(input))))
+            }
+        }
+    }
+
+    pub fn finish(&self) {
+        if let ReplayMode::Record { path, recording } = &self.mode {
+            match recording.save(path) {
+                Ok(()) => info!(
+                    "Saved {} recorded frames to {}",
+                    recording.frames.len(),
+                    path.display()
+                ),
+                Err(err) => error!("Failed to save recording to {}: {err}", path.display()),
+            }
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use tracing::{info, warn};
+use wgpu::naga::Module;
+use wgpu::naga::front::wgsl;
+use wgpu::naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use wgpu::{ShaderModule, ShaderModuleDescriptor, ShaderSource};
+
+use crate::GpuContext;
+use crate::error::{Error, Result};
+use crate::reflect::ShaderLayout;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Snippets any shader can pull in with `#include <name>`
+const LIBRARY: &[(&str, &str)] = &[
+    ("color.wgsl", include_str!("shaders/color.wgsl")),
+    ("fullscreen.wgsl", include_str!("shaders/fullscreen.wgsl")),
+    ("shadow.wgsl", include_str!("shaders/shadow.wgsl")),
+    ("tonemap.wgsl", include_str!("shaders/tonemap.wgsl")),
+];
+
+#[derive(Default)]
+struct Watched {
+    // Every file read from disk and its modification time when last seen
+    files: Vec<(String, Option<SystemTime>)>,
+    // Shaders and the defines they were loaded with, validated again after any change
+    shaders: Vec<(String, Vec<(String, String)>)>,
+}
+
+static WATCHED: Mutex<Watched> = Mutex::new(Watched {
+    files: Vec::new(),
+    shaders: Vec::new(),
+});
+
+pub struct ShaderFile {
+    pub label: &'static str,
+    pub path: &'static str,
+    pub embedded: &'static str,
+    // Files pulled in with `#include "file"`, by path, so release builds don't read them from disk
+    pub includes: &'static [(&'static str, &'static str)],
+}
+
+// `shader!("scene.wgsl")` refers to a file next to the chapter's `main.rs`, like `include_wgsl!`.
+// Files the shader includes with quotes are listed after it: `shader!("scene.wgsl", "common.wgsl")`
+#[macro_export]
+macro_rules! shader {
+    ($file:literal $(, $include:literal)* $(,)?) => {
+        $crate::shader::ShaderFile {
+            label: $file,
+            path: concat!(env!("CARGO_MANIFEST_DIR"), "/src/", $file),
+            embedded: include_str!($file),
+            includes: &[$((
+                concat!(env!("CARGO_MANIFEST_DIR"), "/src/", $include),
+                include_str!($include),
+            )),*],
+        }
+    };
+}
+
+pub fn load_shader(ctx: &GpuContext, file: &ShaderFile) -> Result<ShaderModule> {
+    load_shader_with_defs(ctx, file, &[])
+}
+
+// Debug builds read the file from disk and watch it for changes, release builds use the embedded copy.
+// `defs` are added as `#define NAME value` before the first line. Preprocessor and naga errors are
+// returned; during hot reload they keep the old example running
+pub fn load_shader_with_defs(
+    ctx: &GpuContext,
+    file: &ShaderFile,
+    defs: &[(&str, &str)],
+) -> Result<ShaderModule> {
+    let (module, _, _) = load(ctx, file, defs)?;
+    Ok(module)
+}
+
+// Also derives bind group and pipeline layouts from the shader's `@group`/`@binding` declarations
+pub fn load_shader_with_layout(
+    ctx: &GpuContext,
+    file: &ShaderFile,
+    defs: &[(&str, &str)],
+) -> Result<(ShaderModule, ShaderLayout)> {
+    let (module, naga_module, info) = load(ctx, file, defs)?;
+    let layout = ShaderLayout::new(&ctx.device, file.label, &naga_module, &info)?;
+    Ok((module, layout))
+}
+
+fn load(
+    ctx: &GpuContext,
+    file: &ShaderFile,
+    defs: &[(&str, &str)],
+) -> Result<(ShaderModule, Module, ModuleInfo)> {
+    let (source, from_disk) = if cfg!(debug_assertions) {
+        match std::fs::read_to_string(file.path) {
+            Ok(source) => (source, true),
+            Err(err) => {
+                warn!("Failed to read {}, using embedded copy: {err}", file.path);
+                (file.embedded.to_owned(), false)
+            }
+        }
+    } else {
+        (file.embedded.to_owned(), false)
+    };
+
+    let embedded = (!from_disk).then_some(file.includes);
+    let (source, includes) = expand(file.path, &source, defs, embedded)?;
+    // Watched before validation, so fixing a broken file is picked up on the next poll
+    if from_disk {
+        watch(file.path, &includes, defs);
+    }
+    // Caught in debug builds, where the include is still found on disk
+    if let Some(include) = includes
+        .iter()
+        .find(|include| !file.includes.iter().any(|(path, _)| path == include))
+    {
+        return Err(Error::Shader {
+            path: file.path.to_owned(),
+            message: format!("{include} is not embedded, list it in shader!"),
+        });
+    }
+    let (naga_module, info) = parse(file.path, &source)?;
+
+    let module = ctx.device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(file.label),
+        source: ShaderSource::Wgsl(source.into()),
+    });
+    Ok((module, naga_module, info))
+}
+
+// Resolves `#include`, `#define`, `#ifdef`/`#ifndef`/`#else`/`#endif` into plain WGSL.
+// `#include <name>` refers to the framework library, `#include "file"` to a file next to `path`
+pub fn preprocess(path: &str, source: &str, defs: &[(&str, &str)]) -> Result<String> {
+    expand(path, source, defs, None).map(|(source, _)| source)
+}
+
+// Preprocesses and validates the file on disk
+pub fn validate(path: &str, defs: &[(&str, &str)]) -> Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    let (source, _) = expand(path, &source, defs, None)?;
+    parse(path, &source).map(|_| ())
+}
+
+pub(crate) fn parse(path: &str, source: &str) -> Result<(Module, ModuleInfo)> {
+    let shader_error = |message: String| Error::Shader {
+        path: path.to_owned(),
+        message,
+    };
+
+    let module = wgsl::parse_str(source).map_err(|err| shader_error(err.emit_to_string(source)))?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| shader_error(err.emit_to_string(source)))?;
+    Ok((module, info))
+}
+
+// Returns the expanded source and the local files it included. Local files come from `embedded`
+// when it's given, from disk otherwise
+fn expand(
+    path: &str,
+    source: &str,
+    defs: &[(&str, &str)],
+    embedded: Option<&'static [(&'static str, &'static str)]>,
+) -> Result<(String, Vec<String>)> {
+    let mut preprocessor = Preprocessor {
+        defines: defs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        embedded,
+        ..Default::default()
+    };
+    preprocessor.process(path, source)?;
+    Ok((preprocessor.output, preprocessor.includes))
+}
+
+#[derive(Default)]
+struct Preprocessor {
+    defines: HashMap<String, String>,
+    embedded: Option<&'static [(&'static str, &'static str)]>,
+    // Each file is included once, however many snippets ask for it
+    included: HashSet<String>,
+    includes: Vec<String>,
+    output: String,
+}
+
+struct Condition {
+    active: bool,
+    has_else: bool,
+}
+
+impl Preprocessor {
+    fn process(&mut self, path: &str, source: &str) -> Result<()> {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| Error::Shader {
+                path: path.to_owned(),
+                message: format!("line {}: {message}", index + 1),
+            };
+            let active = conditions.iter().all(|condition| condition.active);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    self.substitute(line);
+                }
+                continue;
+            };
+            let (name, argument) = directive
+                .split_once(char::is_whitespace)
+                .map_or((directive, ""), |(name, argument)| (name, argument.trim()));
+
+            match name {
+                "ifdef" | "ifndef" => conditions.push(Condition {
+                    active: self.defines.contains_key(argument) == (name == "ifdef"),
+                    has_else: false,
+                }),
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .filter(|condition| !condition.has_else)
+                        .ok_or_else(|| error("unexpected #else".to_owned()))?;
+                    condition.active = !condition.active;
+                    condition.has_else = true;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| error("unexpected #endif".to_owned()))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = argument
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((argument, ""));
+                    if name.is_empty() {
+                        return Err(error("#define without a name".to_owned()));
+                    }
+                    self.defines
+                        .insert(name.to_owned(), value.trim().to_owned());
+                }
+                "include" => self.include(path, argument).map_err(|err| match err {
+                    Error::Shader { .. } => err,
+                    err => error(err.to_string()),
+                })?,
+                _ => return Err(error(format!("unknown directive #{name}"))),
+            }
+        }
+
+        if !conditions.is_empty() {
+            return Err(Error::Shader {
+                path: path.to_owned(),
+                message: "missing #endif".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, path: &str, argument: &str) -> Result<()> {
+        if let Some(name) = argument.strip_prefix('<').and_then(|a| a.strip_suffix('>')) {
+            let (_, source) = LIBRARY
+                .iter()
+                .find(|(file, _)| *file == name)
+                .ok_or_else(|| Error::Shader {
+                    path: path.to_owned(),
+                    message: format!("no library snippet <{name}>"),
+                })?;
+            if self.included.insert(format!("<{name}>")) {
+                self.process(name, source)?;
+            }
+        } else if let Some(name) = argument.strip_prefix('"').and_then(|a| a.strip_suffix('"')) {
+            let include_path = Path::new(path).with_file_name(name);
+            let include_path = include_path.to_string_lossy().into_owned();
+            if self.included.insert(include_path.clone()) {
+                let source = match self.embedded {
+                    Some(embedded) => embedded
+                        .iter()
+                        .find(|(file, _)| *file == include_path)
+                        .map(|(_, source)| source.to_string())
+                        .ok_or_else(|| Error::Shader {
+                            path: path.to_owned(),
+                            message: format!("\"{name}\" is not embedded, list it in shader!"),
+                        })?,
+                    None => std::fs::read_to_string(&include_path)?,
+                };
+                self.includes.push(include_path.clone());
+                self.process(&include_path, &source)?;
+            }
+        } else {
+            return Err(Error::Shader {
+                path: path.to_owned(),
+                message: format!("expected #include <name> or #include \"file\", got {argument}"),
+            });
+        }
+        Ok(())
+    }
+
+    // Copies the line, replacing identifiers defined with a value
+    fn substitute(&mut self, line: &str) {
+        let mut rest = line;
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let (before, word) = rest.split_at(start);
+            let end = word
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(word.len());
+            let (word, after) = word.split_at(end);
+
+            self.output.push_str(before);
+            match self.defines.get(word) {
+                Some(value) if !value.is_empty() => self.output.push_str(value),
+                _ => self.output.push_str(word),
+            }
+            rest = after;
+        }
+        self.output.push_str(rest);
+        self.output.push('\n');
+    }
+}
+
+fn watch(path: &str, includes: &[String], defs: &[(&str, &str)]) {
+    let mut watched = WATCHED.lock().unwrap();
+    for file in std::iter::once(path).chain(includes.iter().map(String::as_str)) {
+        if !watched.files.iter().any(|(p, _)| p == file) {
+            let modified = modified(file);
+            watched.files.push((file.to_owned(), modified));
+        }
+    }
+
+    let defs: Vec<_> = defs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    if !watched.shaders.iter().any(|(p, d)| p == path && *d == defs) {
+        watched.shaders.push((path.to_owned(), defs));
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(Path::new(path))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[derive(Default)]
+pub struct ShaderWatcher {
+    last_poll: Option<Instant>,
+    error: Option<String>,
+}
+
+impl ShaderWatcher {
+    // Returns true when watched files changed and all of them are valid again
+    pub fn poll(&mut self) -> bool {
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.last_poll = Some(Instant::now());
+
+        let mut watched = WATCHED.lock().unwrap();
+        let mut changed = false;
+        for (path, last_modified) in watched.files.iter_mut() {
+            let modified = modified(path);
+            if modified != *last_modified {
+                info!("Shader {path} changed");
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+        if !changed {
+            return false;
+        }
+
+        // Every file is checked, so an error in one shader isn't hidden by a fix in another
+        self.error = watched
+            .shaders
+            .iter()
+            .find_map(|(path, defs)| {
+                let defs: Vec<_> = defs.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+                validate(path, &defs).err()
+            })
+            .map(|err| err.to_string());
+        if let Some(error) = &self.error {
+            warn!("{error}");
+        }
+        self.error.is_none()
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
@@ -0,0 +1,30 @@
+// Shadow map lookups; `light_pos` is the fragment position in the light's NDC.
+// Define SHADOW_PCF to average a 3x3 neighbourhood instead of a single comparison.
+fn shadow_uv(light_pos: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        light_pos.x * 0.5 + 0.5,
+        1.0 - (light_pos.y * 0.5 + 0.5),
+        light_pos.z
+    );
+}
+
+// Returns 1.0 when lit and 0.0 when fully in shadow; points outside the map are lit
+fn sample_shadow(shadow_tex: texture_depth_2d, shadow_sampler: sampler_comparison, light_pos: vec3<f32>) -> f32 {
+    let uv = shadow_uv(light_pos);
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        return 1.0;
+    }
+#ifdef SHADOW_PCF
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_tex));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_tex, shadow_sampler, uv.xy + offset, uv.z);
+        }
+    }
+    return lit / 9.0;
+#else
+    return textureSampleCompareLevel(shadow_tex, shadow_sampler, uv.xy, uv.z);
+#endif
+}
@@ -1,15 +1,45 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use half::f16;
+use image::ImageFormat;
+use tracing::{info, warn};
+use wgpu::util::{DeviceExt, TextureDataOrder};
 use wgpu::{
-    Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    TextureView, TextureViewDescriptor,
+    AddressMode, BindGroupDescriptor, BindGroupEntry, BindingResource, Color, CommandEncoder,
+    CommandEncoderDescriptor, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
+    FilterMode, FragmentState, LoadOp, MipmapFilterMode, MultisampleState, Operations,
+    PipelineCompilationOptions, PrimitiveState, RenderPassColorAttachment,
+    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerDescriptor, ShaderModule, StencilState, StoreOp,
+    TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor, VertexState, include_wgsl,
 };
 
 use crate::GpuContext;
+use crate::capture::TextureReadback;
+use crate::error::{Error, Result};
+use crate::golden::Image;
+
+mod block;
+mod dds;
+mod ktx2;
+
+// Decoded pixels ready for upload, rows are tightly packed texels of `format`
+pub struct TextureImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub data: Vec<u8>,
+}
 
 pub fn generate_checkerboard(size: u32, cell_size: u32, light: [u8; 4], dark: [u8; 4]) -> Vec<u8> {
     let mut pixels = Vec::with_capacity((size * size * 4) as usize);
     for y in 0..size {
         for x in 0..size {
-            if ((x / cell_size) + (y / cell_size)) % 2 == 0 {
+            if ((x / cell_size) + (y / cell_size)).is_multiple_of(2) {
                 pixels.extend_from_slice(&light);
             } else {
                 pixels.extend_from_slice(&dark);
@@ -19,6 +49,56 @@ pub fn generate_checkerboard(size: u32, cell_size: u32, light: [u8; 4], dark: [u
     pixels
 }
 
+// Which end of the depth range is close to the camera. `Reverse` goes with
+// `Projection::InfiniteReverse`: near is 1 and infinity 0, so the float depth buffer keeps its
+// precision far away instead of spending it all next to the near plane
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    #[default]
+    Standard,
+    Reverse,
+}
+
+impl DepthMode {
+    pub fn compare(self) -> CompareFunction {
+        match self {
+            DepthMode::Standard => CompareFunction::Less,
+            DepthMode::Reverse => CompareFunction::Greater,
+        }
+    }
+
+    // The farthest possible depth
+    pub fn clear_value(self) -> f32 {
+        match self {
+            DepthMode::Standard => 1.0,
+            DepthMode::Reverse => 0.0,
+        }
+    }
+
+    // For pipelines that draw into `create_depth_texture`
+    pub fn depth_stencil_state(self) -> DepthStencilState {
+        DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: Some(true),
+            depth_compare: Some(self.compare()),
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }
+    }
+
+    pub fn attachment(self, view: &TextureView) -> RenderPassDepthStencilAttachment<'_> {
+        RenderPassDepthStencilAttachment {
+            view,
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(self.clear_value()),
+                store: StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }
+    }
+}
+
+// Always Depth32Float, reverse-Z needs a float format to pay off
 pub fn create_depth_texture(ctx: &GpuContext, label: &str) -> (Texture, TextureView) {
     let size = &ctx.surface_config;
     let texture = ctx.device.create_texture(&TextureDescriptor {
@@ -38,3 +118,472 @@ pub fn create_depth_texture(ctx: &GpuContext, label: &str) -> (Texture, TextureV
     let view = texture.create_view(&TextureViewDescriptor::default());
     (texture, view)
 }
+
+pub fn read_texture(ctx: &GpuContext, texture: &Texture) -> Result<Vec<u8>> {
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+    let readback = TextureReadback::new(ctx, texture, &mut encoder)?;
+    ctx.queue.submit([encoder.finish()]);
+    readback.read(ctx)
+}
+
+// Halving down to 1x1: a 256x256 texture has 9 levels
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+// PNG and JPEG load as RGBA8: sRGB for colors, linear for data such as normal maps. Radiance HDR
+// is always linear and loads as RGBA16F, which every adapter can filter and render to
+pub fn load_image(path: impl AsRef<Path>, srgb: bool) -> Result<TextureImage> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)?;
+    let error = |message| Error::Image {
+        path: path.display().to_string(),
+        message,
+    };
+
+    if bytes.starts_with(b"#?") {
+        let image = image::load_from_memory_with_format(&bytes, ImageFormat::Hdr)
+            .map_err(|err| error(err.to_string()))?
+            .into_rgba32f();
+        return Ok(TextureImage {
+            width: image.width(),
+            height: image.height(),
+            format: TextureFormat::Rgba16Float,
+            data: image
+                .as_raw()
+                .iter()
+                .flat_map(|&p| f16::from_f32(p).to_le_bytes())
+                .collect(),
+        });
+    }
+
+    let image = decode_image(&bytes).map_err(error)?;
+    Ok(TextureImage {
+        width: image.width,
+        height: image.height,
+        format: if srgb {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        },
+        data: image.pixels,
+    })
+}
+
+pub fn load_texture(ctx: &GpuContext, path: impl AsRef<Path>, srgb: bool) -> Result<Texture> {
+    let path = path.as_ref();
+    let image = load_image(path, srgb)?;
+    let label = path.file_name().unwrap_or_default().to_string_lossy();
+    Ok(create_texture_with_mips(ctx, &label, &image))
+}
+
+// Uploads the image as mip level 0 and fills the rest of the chain on the GPU
+pub fn create_texture_with_mips(ctx: &GpuContext, label: &str, image: &TextureImage) -> Texture {
+    let size = Extent3d {
+        width: image.width,
+        height: image.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = ctx.device.create_texture(&TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: mip_level_count(image.width, image.height),
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: image.format,
+        usage: TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::COPY_SRC
+            | TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let texel_size = image
+        .format
+        .block_copy_size(None)
+        .expect("Failed to get texel size");
+    ctx.queue.write_texture(
+        texture.as_image_copy(),
+        &image.data,
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(image.width * texel_size),
+            rows_per_image: Some(image.height),
+        },
+        size,
+    );
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+    generate_mipmaps(ctx, &mut encoder, &texture);
+    ctx.queue.submit([encoder.finish()]);
+    texture
+}
+
+// Built on first use and kept on the context, one pipeline per texture format
+#[derive(Default)]
+pub(crate) struct MipmapPipelines {
+    shader_module: OnceLock<ShaderModule>,
+    sampler: OnceLock<Sampler>,
+    pipelines: Mutex<HashMap<TextureFormat, RenderPipeline>>,
+}
+
+impl MipmapPipelines {
+    fn get(&self, ctx: &GpuContext, format: TextureFormat) -> (RenderPipeline, Sampler) {
+        let sampler = self.sampler.get_or_init(|| {
+            ctx.device.create_sampler(&SamplerDescriptor {
+                label: Some("Mipmap Sampler"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            })
+        });
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let pipeline = pipelines.entry(format).or_insert_with(|| {
+            let shader_module = self.shader_module.get_or_init(|| {
+                ctx.device
+                    .create_shader_module(include_wgsl!("mipmap.wgsl"))
+            });
+            ctx.device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("Mipmap Pipeline"),
+                    layout: None,
+                    vertex: VertexState {
+                        module: shader_module,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(FragmentState {
+                        module: shader_module,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(format.into())],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    cache: None,
+                    multiview_mask: None,
+                })
+        });
+        (pipeline.clone(), sampler.clone())
+    }
+}
+
+// Renders every mip level after the first from the level above. The texture needs
+// RENDER_ATTACHMENT usage and a format that is both filterable and renderable
+pub fn generate_mipmaps(ctx: &GpuContext, encoder: &mut CommandEncoder, texture: &Texture) {
+    let (pipeline, sampler) = ctx.mipmap_pipelines.get(ctx, texture.format());
+    let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+    let level_view = |level| {
+        texture.create_view(&TextureViewDescriptor {
+            base_mip_level: level,
+            mip_level_count: Some(1),
+            ..Default::default()
+        })
+    };
+    for level in 1..texture.mip_level_count() {
+        let source = level_view(level - 1);
+        let target = level_view(level);
+        let bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Mipmap Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Mipmap Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+// Texels straight from a KTX2 or DDS file, usually block-compressed, with one entry per mip level
+// from the largest down
+pub struct CompressedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    // RGBA8 levels for adapters without the format's feature. Only BC1-BC5 and ETC2/EAC can be
+    // decoded on the CPU; BC6H, BC7, ASTC and the signed formats are unsupported and return `None`
+    pub fn decompress(&self) -> Option<Vec<TextureImage>> {
+        let format = if self.format.is_srgb() {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        };
+        let mut images = Vec::with_capacity(self.levels.len());
+        for (level, data) in self.levels.iter().enumerate() {
+            let width = (self.width >> level).max(1);
+            let height = (self.height >> level).max(1);
+            let data = match self.format {
+                TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => data.clone(),
+                _ => block::decode(self.format, width, height, data)?,
+            };
+            images.push(TextureImage {
+                width,
+                height,
+                format,
+                data,
+            });
+        }
+        Some(images)
+    }
+}
+
+// Bytes of one mip level, whole blocks for compressed formats. Saturates rather than overflows
+// for the sizes a broken file may claim
+fn level_size(format: TextureFormat, width: u32, height: u32) -> usize {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_copy_size(None)
+        .expect("Failed to get block size");
+    (width.max(1).div_ceil(block_width) as usize)
+        .saturating_mul(height.max(1).div_ceil(block_height) as usize)
+        .saturating_mul(block_size as usize)
+}
+
+// `length` bytes at `offset`, both read from the file, or `None` when they don't fit
+fn file_range(bytes: &[u8], offset: usize, length: usize) -> Option<&[u8]> {
+    bytes.get(offset..offset.checked_add(length)?)
+}
+
+pub fn load_compressed_image(path: impl AsRef<Path>) -> Result<CompressedImage> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)?;
+    let image = if bytes.starts_with(ktx2::SIGNATURE) {
+        ktx2::decode(&bytes)
+    } else if bytes.starts_with(dds::SIGNATURE) {
+        dds::decode(&bytes)
+    } else {
+        Err("unknown container, expected KTX2 or DDS".to_owned())
+    };
+    image.map_err(|message| Error::Image {
+        path: path.display().to_string(),
+        message,
+    })
+}
+
+pub struct CompressedTexture {
+    pub texture: Texture,
+    // True when the adapter lacks the format's feature and the blocks were decoded on the CPU
+    pub decompressed: bool,
+    // GPU memory of the whole mip chain, and what the same chain takes as RGBA8
+    pub size: u64,
+    pub rgba8_size: u64,
+}
+
+impl CompressedTexture {
+    pub fn saved_bytes(&self) -> u64 {
+        self.rgba8_size.saturating_sub(self.size)
+    }
+}
+
+// Uploads the blocks as they are when the adapter has the feature for the format (GpuContext
+// requests every feature the adapter offers), otherwise decodes them to RGBA8 first
+pub fn load_compressed_texture(
+    ctx: &GpuContext,
+    path: impl AsRef<Path>,
+) -> Result<CompressedTexture> {
+    let path = path.as_ref();
+    let image = load_compressed_image(path)?;
+    let label = path.file_name().unwrap_or_default().to_string_lossy();
+
+    // Compressed textures must be a whole number of blocks, only the smaller mips may round up
+    let (block_width, block_height) = image.format.block_dimensions();
+    let supported = ctx
+        .device
+        .features()
+        .contains(image.format.required_features())
+        && image.width.is_multiple_of(block_width)
+        && image.height.is_multiple_of(block_height);
+
+    let texture = if supported {
+        create_texture_with_levels(
+            ctx,
+            &label,
+            image.format,
+            image.width,
+            image.height,
+            &image.levels,
+        )
+    } else {
+        let levels = image.decompress().ok_or_else(|| Error::Image {
+            path: path.display().to_string(),
+            message: format!(
+                "{:?} needs {:?}, which the adapter lacks, and has no CPU fallback: only BC1-BC5 \
+                 and ETC2/EAC are decoded on the CPU, BC6H, BC7, ASTC and signed formats are not",
+                image.format,
+                image.format.required_features()
+            ),
+        })?;
+        warn!(
+            "{:?} is not supported by the adapter, decoded {label} to RGBA8 on the CPU",
+            image.format
+        );
+        if levels.len() == 1 {
+            create_texture_with_mips(ctx, &label, &levels[0])
+        } else {
+            let data: Vec<_> = levels.iter().map(|level| level.data.clone()).collect();
+            create_texture_with_levels(
+                ctx,
+                &label,
+                levels[0].format,
+                image.width,
+                image.height,
+                &data,
+            )
+        }
+    };
+
+    let (mut size, mut rgba8_size) = (0, 0);
+    for level in 0..texture.mip_level_count() {
+        let (width, height) = (texture.width() >> level, texture.height() >> level);
+        size += level_size(texture.format(), width, height) as u64;
+        rgba8_size += level_size(TextureFormat::Rgba8Unorm, width, height) as u64;
+    }
+    info!(
+        "Loaded {label} as {:?}: {} KiB instead of {} KiB as RGBA8",
+        texture.format(),
+        size / 1024,
+        rgba8_size / 1024
+    );
+    Ok(CompressedTexture {
+        texture,
+        decompressed: !supported,
+        size,
+        rgba8_size,
+    })
+}
+
+fn create_texture_with_levels(
+    ctx: &GpuContext,
+    label: &str,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    levels: &[Vec<u8>],
+) -> Texture {
+    ctx.device.create_texture_with_data(
+        &ctx.queue,
+        &TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &levels.concat(),
+    )
+}
+
+// Trilinear filtering between mip levels. `anisotropy` above 1 (up to 16) also enables
+// anisotropic filtering, which keeps surfaces seen at grazing angles sharp
+pub fn create_sampler(ctx: &GpuContext, address_mode: AddressMode, anisotropy: u16) -> Sampler {
+    ctx.device.create_sampler(&SamplerDescriptor {
+        label: Some("Trilinear Sampler"),
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        mipmap_filter: MipmapFilterMode::Linear,
+        anisotropy_clamp: anisotropy.clamp(1, 16),
+        ..Default::default()
+    })
+}
+
+// PNG or JPEG, recognized by their signatures, decoded to RGBA8
+pub(crate) fn decode_image(bytes: &[u8]) -> std::result::Result<Image, String> {
+    if bytes.starts_with(b"\x89PNG") {
+        decode_png(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        decode_jpeg(bytes)
+    } else {
+        Err("unknown image format, expected PNG or JPEG".to_owned())
+    }
+}
+
+// Baseline and progressive, any chroma subsampling
+fn decode_jpeg(bytes: &[u8]) -> std::result::Result<Image, String> {
+    let image = image::load_from_memory_with_format(bytes, ImageFormat::Jpeg)
+        .map_err(|err| err.to_string())?
+        .into_rgba8();
+    Ok(Image {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    })
+}
+
+pub(crate) fn decode_png(bytes: &[u8]) -> std::result::Result<Image, String> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(
+        png::Transformations::EXPAND | png::Transformations::ALPHA | png::Transformations::STRIP_16,
+    );
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let size = reader.output_buffer_size().ok_or("PNG is too large")?;
+    let mut pixels = vec![0; size];
+    let info = reader
+        .next_frame(&mut pixels)
+        .map_err(|err| err.to_string())?;
+    pixels.truncate(info.buffer_size());
+
+    // Grayscale images come out as gray + alpha
+    if info.color_type == png::ColorType::GrayscaleAlpha {
+        pixels = pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect();
+    }
+
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
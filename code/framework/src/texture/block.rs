@@ -0,0 +1,348 @@
+use wgpu::TextureFormat;
+
+type Texels = [[u8; 4]; 16];
+
+// CPU decoders for adapters without the compression feature. Every format here uses 4x4 blocks,
+// single channel formats come out as (r, 0, 0, 255) and two channel ones as (r, g, 0, 255), like
+// the GPU would return them
+pub(crate) fn decode(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    let (block_size, decode_block): (usize, fn(&[u8]) -> Texels) = match format {
+        TextureFormat::Bc1RgbaUnorm | TextureFormat::Bc1RgbaUnormSrgb => (8, |b| bc1(b, false)),
+        TextureFormat::Bc2RgbaUnorm | TextureFormat::Bc2RgbaUnormSrgb => (16, bc2),
+        TextureFormat::Bc3RgbaUnorm | TextureFormat::Bc3RgbaUnormSrgb => (16, bc3),
+        TextureFormat::Bc4RUnorm => (8, bc4),
+        TextureFormat::Bc5RgUnorm => (16, bc5),
+        TextureFormat::Etc2Rgb8Unorm | TextureFormat::Etc2Rgb8UnormSrgb => (8, |b| etc2(b, false)),
+        TextureFormat::Etc2Rgb8A1Unorm | TextureFormat::Etc2Rgb8A1UnormSrgb => {
+            (8, |b| etc2(b, true))
+        }
+        TextureFormat::Etc2Rgba8Unorm | TextureFormat::Etc2Rgba8UnormSrgb => (16, etc2_rgba),
+        TextureFormat::EacR11Unorm => (8, eac_r),
+        TextureFormat::EacRg11Unorm => (16, eac_rg),
+        _ => return None,
+    };
+
+    let (blocks_x, blocks_y) = (width.div_ceil(4) as usize, height.div_ceil(4) as usize);
+    if data.len() < blocks_x * blocks_y * block_size {
+        return None;
+    }
+    let (width, height) = (width as usize, height as usize);
+    let mut pixels = vec![0; width * height * 4];
+    for (i, block) in data
+        .chunks_exact(block_size)
+        .take(blocks_x * blocks_y)
+        .enumerate()
+    {
+        let (bx, by) = (i % blocks_x * 4, i / blocks_x * 4);
+        for (j, texel) in decode_block(block).iter().enumerate() {
+            let (x, y) = (bx + j % 4, by + j / 4);
+            if x < width && y < height {
+                let offset = (y * width + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(texel);
+            }
+        }
+    }
+    Some(pixels)
+}
+
+fn rgb565(color: u16) -> [i32; 3] {
+    let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+    [
+        (r << 3 | r >> 2) as i32,
+        (g << 2 | g >> 4) as i32,
+        (b << 3 | b >> 2) as i32,
+    ]
+}
+
+// BC2 and BC3 color blocks always use four colors, only BC1 switches to 1-bit alpha when c0 <= c1
+fn bc1(block: &[u8], four_colors: bool) -> Texels {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let mix = |wa: i32, wb: i32| {
+        let [r, g, bl] = [0, 1, 2].map(|i| ((a[i] * wa + b[i] * wb) / (wa + wb)) as u8);
+        [r, g, bl, 255]
+    };
+    let palette = if four_colors || c0 > c1 {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0; 4]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|i| palette[(indices >> (i * 2) & 3) as usize])
+}
+
+fn bc2(block: &[u8]) -> Texels {
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    let mut texels = bc1(&block[8..], true);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[3] = (alpha >> (i * 4) & 15) as u8 * 17;
+    }
+    texels
+}
+
+fn bc3(block: &[u8]) -> Texels {
+    let alpha = bc4_channel(&block[..8]);
+    let mut texels = bc1(&block[8..], true);
+    for (texel, alpha) in texels.iter_mut().zip(alpha) {
+        texel[3] = alpha;
+    }
+    texels
+}
+
+// Two endpoints and 3-bit indices: six interpolated values between them, or four plus 0 and 255
+fn bc4_channel(block: &[u8]) -> [u8; 16] {
+    let (a, b) = (block[0] as u32, block[1] as u32);
+    let palette: [u32; 8] = if a > b {
+        std::array::from_fn(|i| match i {
+            0 => a,
+            1 => b,
+            _ => (a * (8 - i as u32) + b * (i as u32 - 1)) / 7,
+        })
+    } else {
+        std::array::from_fn(|i| match i {
+            0 => a,
+            1 => b,
+            6 => 0,
+            7 => 255,
+            _ => (a * (6 - i as u32) + b * (i as u32 - 1)) / 5,
+        })
+    };
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    std::array::from_fn(|i| palette[(indices >> (i * 3) & 7) as usize] as u8)
+}
+
+fn bc4(block: &[u8]) -> Texels {
+    bc4_channel(block).map(|r| [r, 0, 0, 255])
+}
+
+fn bc5(block: &[u8]) -> Texels {
+    let (red, green) = (bc4_channel(&block[..8]), bc4_channel(&block[8..]));
+    std::array::from_fn(|i| [red[i], green[i], 0, 255])
+}
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+fn bits(block: u64, high: u32, count: u32) -> i32 {
+    (block >> (high + 1 - count) & ((1 << count) - 1)) as i32
+}
+
+fn extend(value: i32, from: u32) -> i32 {
+    value << (8 - from) | value >> (2 * from - 8)
+}
+
+// ETC2 blocks are big-endian and their pixel indices go down the columns first. With
+// `punchthrough` (RGB8A1) the differential bit becomes the opaque bit, and in non-opaque blocks
+// index 2 is transparent black
+fn etc2(block: &[u8], punchthrough: bool) -> Texels {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let differential = punchthrough || bits(block, 33, 1) == 1;
+    let opaque = !punchthrough || bits(block, 33, 1) == 1;
+
+    let mut base = [[0; 3]; 2];
+    if differential {
+        let first = [63, 55, 47].map(|high| bits(block, high, 5));
+        let delta = [58, 50, 42].map(|high| bits(block, high, 3) << 29 >> 29);
+        let second: [i32; 3] = std::array::from_fn(|i| first[i] + delta[i]);
+        if !(0..32).contains(&second[0]) {
+            return etc2_paint(block, opaque, etc2_t_mode(block));
+        }
+        if !(0..32).contains(&second[1]) {
+            return etc2_paint(block, opaque, etc2_h_mode(block));
+        }
+        if !(0..32).contains(&second[2]) {
+            return etc2_planar(block);
+        }
+        base = [first.map(|c| extend(c, 5)), second.map(|c| extend(c, 5))];
+    } else {
+        for (i, high) in [63, 55, 47].into_iter().enumerate() {
+            base[0][i] = bits(block, high, 4) * 17;
+            base[1][i] = bits(block, high - 4, 4) * 17;
+        }
+    }
+
+    let tables = [bits(block, 39, 3), bits(block, 36, 3)].map(|t| ETC_MODIFIERS[t as usize]);
+    let flip = bits(block, 32, 1) == 1;
+    std::array::from_fn(|i| {
+        let (x, y) = (i % 4, i / 4);
+        let index = etc_index(block, x, y);
+        if !opaque && index == 2 {
+            return [0; 4];
+        }
+        let half = usize::from(if flip { y >= 2 } else { x >= 2 });
+        let [small, large] = tables[half];
+        let modifier = match index {
+            0 if !opaque => 0,
+            0 => small,
+            1 => large,
+            2 => -small,
+            _ => -large,
+        };
+        let [r, g, b] = base[half].map(|c| (c + modifier).clamp(0, 255) as u8);
+        [r, g, b, 255]
+    })
+}
+
+fn etc_index(block: u64, x: usize, y: usize) -> usize {
+    let k = x * 4 + y;
+    ((block >> (k + 16) & 1) << 1 | block >> k & 1) as usize
+}
+
+fn etc2_t_mode(block: u64) -> [[i32; 3]; 4] {
+    let first = [
+        bits(block, 60, 2) << 2 | bits(block, 57, 2),
+        bits(block, 55, 4),
+        bits(block, 51, 4),
+    ]
+    .map(|c| c * 17);
+    let second = [47, 43, 39].map(|high| bits(block, high, 4) * 17);
+    let distance = ETC_DISTANCES[(bits(block, 35, 2) << 1 | bits(block, 32, 1)) as usize];
+    [
+        first,
+        second.map(|c| c + distance),
+        second,
+        second.map(|c| c - distance),
+    ]
+}
+
+fn etc2_h_mode(block: u64) -> [[i32; 3]; 4] {
+    let first = [
+        bits(block, 62, 4),
+        bits(block, 58, 3) << 1 | bits(block, 52, 1),
+        bits(block, 51, 1) << 3 | bits(block, 49, 3),
+    ];
+    let second = [bits(block, 46, 4), bits(block, 42, 4), bits(block, 38, 4)];
+    let packed = |c: [i32; 3]| c[0] << 8 | c[1] << 4 | c[2];
+    let index = bits(block, 34, 1) << 2
+        | bits(block, 32, 1) << 1
+        | i32::from(packed(first) >= packed(second));
+    let distance = ETC_DISTANCES[index as usize];
+    let [first, second] = [first, second].map(|c| c.map(|c| c * 17));
+    [
+        first.map(|c| c + distance),
+        first.map(|c| c - distance),
+        second.map(|c| c + distance),
+        second.map(|c| c - distance),
+    ]
+}
+
+// T and H modes pick one of four paint colors per pixel
+fn etc2_paint(block: u64, opaque: bool, paint: [[i32; 3]; 4]) -> Texels {
+    std::array::from_fn(|i| {
+        let index = etc_index(block, i % 4, i / 4);
+        if !opaque && index == 2 {
+            return [0; 4];
+        }
+        let [r, g, b] = paint[index].map(|c| c.clamp(0, 255) as u8);
+        [r, g, b, 255]
+    })
+}
+
+// Three colors at the corners (origin, horizontal and vertical), interpolated across the block
+fn etc2_planar(block: u64) -> Texels {
+    let origin = [
+        extend(bits(block, 62, 6), 6),
+        extend(bits(block, 56, 1) << 6 | bits(block, 54, 6), 7),
+        extend(
+            bits(block, 48, 1) << 5 | bits(block, 44, 2) << 3 | bits(block, 41, 3),
+            6,
+        ),
+    ];
+    let horizontal = [
+        extend(bits(block, 38, 5) << 1 | bits(block, 32, 1), 6),
+        extend(bits(block, 31, 7), 7),
+        extend(bits(block, 24, 6), 6),
+    ];
+    let vertical = [
+        extend(bits(block, 18, 6), 6),
+        extend(bits(block, 12, 7), 7),
+        extend(bits(block, 5, 6), 6),
+    ];
+    std::array::from_fn(|i| {
+        let (x, y) = ((i % 4) as i32, (i / 4) as i32);
+        let [r, g, b] = [0, 1, 2].map(|c| {
+            let value = x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]);
+            ((value + 4 * origin[c] + 2) >> 2).clamp(0, 255) as u8
+        });
+        [r, g, b, 255]
+    })
+}
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+// EAC stores a base value, a multiplier and a modifier table, then 3-bit indices per pixel
+fn eac_channel(block: &[u8], eleven_bits: bool) -> [u8; 16] {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let base = bits(block, 63, 8);
+    let multiplier = bits(block, 55, 4);
+    let table = EAC_MODIFIERS[bits(block, 51, 4) as usize];
+    std::array::from_fn(|i| {
+        let (x, y) = (i % 4, i / 4);
+        let modifier = table[(block >> (45 - 3 * (x * 4 + y)) & 7) as usize];
+        if eleven_bits {
+            let value = if multiplier == 0 {
+                base * 8 + 4 + modifier
+            } else {
+                base * 8 + 4 + modifier * multiplier * 8
+            };
+            ((value.clamp(0, 2047) * 255 + 1023) / 2047) as u8
+        } else {
+            (base + modifier * multiplier).clamp(0, 255) as u8
+        }
+    })
+}
+
+fn etc2_rgba(block: &[u8]) -> Texels {
+    let alpha = eac_channel(&block[..8], false);
+    let mut texels = etc2(&block[8..], false);
+    for (texel, alpha) in texels.iter_mut().zip(alpha) {
+        texel[3] = alpha;
+    }
+    texels
+}
+
+fn eac_r(block: &[u8]) -> Texels {
+    eac_channel(block, true).map(|r| [r, 0, 0, 255])
+}
+
+fn eac_rg(block: &[u8]) -> Texels {
+    let (red, green) = (
+        eac_channel(&block[..8], true),
+        eac_channel(&block[8..], true),
+    );
+    std::array::from_fn(|i| [red[i], green[i], 0, 255])
+}
@@ -0,0 +1,110 @@
+use wgpu::TextureFormat;
+
+use super::{CompressedImage, file_range, level_size, mip_level_count};
+
+type DecodeResult<T> = std::result::Result<T, String>;
+
+pub(crate) const SIGNATURE: &[u8] = b"DDS ";
+
+const HEADER_SIZE: usize = 4 + 124;
+const DX10_HEADER_SIZE: usize = 20;
+const CAPS2_CUBEMAP: u32 = 0x200;
+const CAPS2_VOLUME: u32 = 0x200000;
+const DIMENSION_TEXTURE2D: u32 = 3;
+const MISC_TEXTURECUBE: u32 = 0x4;
+
+// DirectDraw Surface with a single 2D image and its mip levels, either with a legacy FourCC
+// (DXT1-5, ATI1/2) or a DX10 header with a DXGI format. Legacy files don't say whether they hold
+// sRGB colors, so they load as UNORM
+pub(crate) fn decode(bytes: &[u8]) -> DecodeResult<CompressedImage> {
+    let u32_at = |offset: usize| {
+        file_range(bytes, offset, 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or("unexpected end of header")
+    };
+
+    let (height, width) = (u32_at(12)?, u32_at(16)?);
+    let level_count = u32_at(28)?.max(1);
+    let caps2 = u32_at(112)?;
+    if caps2 & (CAPS2_CUBEMAP | CAPS2_VOLUME) != 0 {
+        return Err("cube maps and volume textures are not supported".to_owned());
+    }
+
+    let four_cc = bytes.get(84..88).ok_or("unexpected end of header")?;
+    let (format, mut offset) = if four_cc == b"DX10" {
+        let dxgi_format = u32_at(HEADER_SIZE)?;
+        let (dimension, flags) = (u32_at(HEADER_SIZE + 4)?, u32_at(HEADER_SIZE + 8)?);
+        if dimension != DIMENSION_TEXTURE2D
+            || flags & MISC_TEXTURECUBE != 0
+            || u32_at(HEADER_SIZE + 12)? > 1
+        {
+            return Err(
+                "only single 2D images are supported, not cube maps, arrays or 3D".to_owned(),
+            );
+        }
+        let format =
+            format(dxgi_format).ok_or(format!("DXGI format {dxgi_format} is not supported"))?;
+        (format, HEADER_SIZE + DX10_HEADER_SIZE)
+    } else {
+        let format = match four_cc {
+            b"DXT1" => TextureFormat::Bc1RgbaUnorm,
+            b"DXT2" | b"DXT3" => TextureFormat::Bc2RgbaUnorm,
+            b"DXT4" | b"DXT5" => TextureFormat::Bc3RgbaUnorm,
+            b"ATI1" | b"BC4U" => TextureFormat::Bc4RUnorm,
+            b"BC4S" => TextureFormat::Bc4RSnorm,
+            b"ATI2" | b"BC5U" => TextureFormat::Bc5RgUnorm,
+            b"BC5S" => TextureFormat::Bc5RgSnorm,
+            _ => {
+                return Err(format!(
+                    "pixel format {:?} is not supported",
+                    String::from_utf8_lossy(four_cc)
+                ));
+            }
+        };
+        (format, HEADER_SIZE)
+    };
+
+    if level_count > mip_level_count(width, height) {
+        return Err(format!(
+            "{level_count} mip levels is more than a {width}x{height} image has"
+        ));
+    }
+
+    // Levels follow each other from the largest down
+    let mut levels = Vec::new();
+    for level in 0..level_count {
+        let length = level_size(format, width >> level, height >> level);
+        let data =
+            file_range(bytes, offset, length).ok_or("level data past the end of the file")?;
+        levels.push(data.to_vec());
+        offset += length;
+    }
+    Ok(CompressedImage {
+        width,
+        height,
+        format,
+        levels,
+    })
+}
+
+fn format(dxgi_format: u32) -> Option<TextureFormat> {
+    Some(match dxgi_format {
+        28 => TextureFormat::Rgba8Unorm,
+        29 => TextureFormat::Rgba8UnormSrgb,
+        71 => TextureFormat::Bc1RgbaUnorm,
+        72 => TextureFormat::Bc1RgbaUnormSrgb,
+        74 => TextureFormat::Bc2RgbaUnorm,
+        75 => TextureFormat::Bc2RgbaUnormSrgb,
+        77 => TextureFormat::Bc3RgbaUnorm,
+        78 => TextureFormat::Bc3RgbaUnormSrgb,
+        80 => TextureFormat::Bc4RUnorm,
+        81 => TextureFormat::Bc4RSnorm,
+        83 => TextureFormat::Bc5RgUnorm,
+        84 => TextureFormat::Bc5RgSnorm,
+        95 => TextureFormat::Bc6hRgbUfloat,
+        96 => TextureFormat::Bc6hRgbFloat,
+        98 => TextureFormat::Bc7RgbaUnorm,
+        99 => TextureFormat::Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
@@ -0,0 +1,122 @@
+use wgpu::{AstcBlock, AstcChannel, TextureFormat};
+
+use super::{CompressedImage, file_range, level_size, mip_level_count};
+
+type DecodeResult<T> = std::result::Result<T, String>;
+
+pub(crate) const SIGNATURE: &[u8] = b"\xABKTX 20\xBB\r\n\x1A\n";
+
+// KTX 2.0 with a single 2D image and its mip levels. Supercompressed (Basis Universal, zstd)
+// files, cube maps and arrays are rejected
+pub(crate) fn decode(bytes: &[u8]) -> DecodeResult<CompressedImage> {
+    let u32_at = |offset: usize| {
+        file_range(bytes, offset, 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or("unexpected end of header")
+    };
+    let u64_at = |offset: usize| {
+        file_range(bytes, offset, 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or("unexpected end of header")
+    };
+
+    let vk_format = u32_at(12)?;
+    // A height of 0 is a 1D texture, which loads as a single row
+    let (width, height, depth) = (u32_at(20)?, u32_at(24)?.max(1), u32_at(28)?);
+    let (layers, faces, level_count) = (u32_at(32)?, u32_at(36)?, u32_at(40)?);
+    let supercompression = u32_at(44)?;
+    if supercompression != 0 {
+        return Err(format!(
+            "supercompression scheme {supercompression} is not supported"
+        ));
+    }
+    if depth > 1 || layers > 1 || faces != 1 {
+        return Err("only single 2D images are supported, not cube maps, arrays or 3D".to_owned());
+    }
+    let format = format(vk_format).ok_or(format!("VkFormat {vk_format} is not supported"))?;
+    if level_count > mip_level_count(width, height) {
+        return Err(format!(
+            "{level_count} mip levels is more than a {width}x{height} image has"
+        ));
+    }
+
+    // Level 0 is the largest. A level count of 0 asks the loader to generate mips, which is
+    // what happens anyway to a single level
+    let mut levels = Vec::new();
+    for level in 0..level_count.max(1) {
+        let index = 80 + level as usize * 24;
+        let (offset, length) = (u64_at(index)? as usize, u64_at(index + 8)? as usize);
+        let expected = level_size(format, width >> level, height >> level);
+        if length != expected {
+            return Err(format!(
+                "level {level} has {length} bytes, expected {expected}"
+            ));
+        }
+        let data =
+            file_range(bytes, offset, length).ok_or("level data past the end of the file")?;
+        levels.push(data.to_vec());
+    }
+    Ok(CompressedImage {
+        width,
+        height,
+        format,
+        levels,
+    })
+}
+
+fn format(vk_format: u32) -> Option<TextureFormat> {
+    use TextureFormat::*;
+    const ASTC_BLOCKS: [AstcBlock; 14] = [
+        AstcBlock::B4x4,
+        AstcBlock::B5x4,
+        AstcBlock::B5x5,
+        AstcBlock::B6x5,
+        AstcBlock::B6x6,
+        AstcBlock::B8x5,
+        AstcBlock::B8x6,
+        AstcBlock::B8x8,
+        AstcBlock::B10x5,
+        AstcBlock::B10x6,
+        AstcBlock::B10x8,
+        AstcBlock::B10x10,
+        AstcBlock::B12x10,
+        AstcBlock::B12x12,
+    ];
+    Some(match vk_format {
+        37 => Rgba8Unorm,
+        43 => Rgba8UnormSrgb,
+        131 | 133 => Bc1RgbaUnorm,
+        132 | 134 => Bc1RgbaUnormSrgb,
+        135 => Bc2RgbaUnorm,
+        136 => Bc2RgbaUnormSrgb,
+        137 => Bc3RgbaUnorm,
+        138 => Bc3RgbaUnormSrgb,
+        139 => Bc4RUnorm,
+        140 => Bc4RSnorm,
+        141 => Bc5RgUnorm,
+        142 => Bc5RgSnorm,
+        143 => Bc6hRgbUfloat,
+        144 => Bc6hRgbFloat,
+        145 => Bc7RgbaUnorm,
+        146 => Bc7RgbaUnormSrgb,
+        147 => Etc2Rgb8Unorm,
+        148 => Etc2Rgb8UnormSrgb,
+        149 => Etc2Rgb8A1Unorm,
+        150 => Etc2Rgb8A1UnormSrgb,
+        151 => Etc2Rgba8Unorm,
+        152 => Etc2Rgba8UnormSrgb,
+        153 => EacR11Unorm,
+        154 => EacR11Snorm,
+        155 => EacRg11Unorm,
+        156 => EacRg11Snorm,
+        157..=184 => Astc {
+            block: ASTC_BLOCKS[(vk_format - 157) as usize / 2],
+            channel: if vk_format % 2 == 1 {
+                AstcChannel::Unorm
+            } else {
+                AstcChannel::UnormSrgb
+            },
+        },
+        _ => return None,
+    })
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::RangeInclusive;
+
+use glam::Vec2;
+
+use crate::GpuContext;
+use crate::Input;
+use crate::overlay::{LINE_HEIGHT, Overlay};
+
+const PANEL_WIDTH: f32 = 320.0;
+const MARGIN: f32 = 8.0;
+const ROW_SPACING: f32 = 4.0;
+
+const TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND: [f32; 4] = [0.1, 0.1, 0.1, 0.75];
+const HOVERED: [f32; 4] = [0.2, 0.2, 0.2, 0.85];
+const ACCENT: [f32; 4] = [0.25, 0.45, 0.85, 0.9];
+
+// Widget state that has to survive between frames
+#[derive(Default)]
+pub struct UiState {
+    mouse_was_down: bool,
+    active: Option<u64>,
+    open_combo: Option<u64>,
+}
+
+// Lays widgets out top to bottom in a panel at the top-right corner of the window
+pub struct Ui<'a> {
+    overlay: &'a Overlay,
+    state: &'a mut UiState,
+    cursor: Option<Vec2>,
+    mouse_down: bool,
+    clicked: bool,
+    position: Vec2,
+    // Widgets are told apart by label, scope and how many times the label came up before, so two
+    // "Reset" buttons don't share state
+    scope: u64,
+    seen: HashMap<u64, u32>,
+}
+
+impl<'a> Ui<'a> {
+    pub fn new(ctx: &'a GpuContext, input: &Input, state: &'a mut UiState) -> Self {
+        let mouse_down = input.mouse_button_pressed(0);
+        let clicked = mouse_down && !state.mouse_was_down;
+        state.mouse_was_down = mouse_down;
+        if !mouse_down {
+            state.active = None;
+        }
+
+        let x = (ctx.surface_config.width as f32 - PANEL_WIDTH - MARGIN).max(MARGIN);
+
+        Self {
+            overlay: &ctx.overlay,
+            state,
+            cursor: input
+                .cursor_position()
+                .map(|(x, y)| Vec2::new(x as f32, y as f32)),
+            mouse_down,
+            clicked,
+            position: Vec2::new(x, MARGIN),
+            scope: 0,
+            seen: HashMap::new(),
+        }
+    }
+
+    // Widgets added by `add` get IDs of their own, e.g. one scope per light with the same sliders
+    pub fn scope(&mut self, id: impl Hash, add: impl FnOnce(&mut Self)) {
+        let outer = self.scope;
+        self.scope = hash((outer, id));
+        add(self);
+        self.scope = outer;
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let (position, _) = self.row();
+        self.overlay
+            .text(position + Vec2::new(4.0, 2.0), text, TEXT);
+    }
+
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let (position, hovered) = self.row();
+        let changed = hovered && self.clicked;
+        if changed {
+            *value = !*value;
+        }
+
+        self.background(position, hovered);
+        let box_size = Vec2::splat(LINE_HEIGHT - 8.0);
+        self.overlay
+            .rect(position + Vec2::splat(4.0), box_size, BACKGROUND);
+        if *value {
+            self.overlay
+                .rect(position + Vec2::splat(7.0), box_size - 6.0, ACCENT);
+        }
+        self.overlay
+            .text(position + Vec2::new(LINE_HEIGHT + 4.0, 2.0), label, TEXT);
+        changed
+    }
+
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let id = self.widget_id(label);
+        let (position, hovered) = self.row();
+        if hovered && self.clicked {
+            self.state.active = Some(id);
+        }
+
+        let (min, max) = (*range.start(), *range.end());
+        let mut changed = false;
+        if self.state.active == Some(id)
+            && self.mouse_down
+            && let Some(cursor) = self.cursor
+        {
+            let t = ((cursor.x - position.x) / PANEL_WIDTH).clamp(0.0, 1.0);
+            let new_value = min + (max - min) * t;
+            changed = new_value != *value;
+            *value = new_value;
+        }
+
+        self.background(position, hovered || self.state.active == Some(id));
+        let t = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+        self.overlay
+            .rect(position, Vec2::new(PANEL_WIDTH * t, LINE_HEIGHT), ACCENT);
+        self.overlay.text(
+            position + Vec2::new(4.0, 2.0),
+            &format!("{label}: {value:.2}"),
+            TEXT,
+        );
+        changed
+    }
+
+    // Shows the selected option; clicking opens the list of options below it
+    pub fn combo(&mut self, label: &str, selected: &mut usize, options: &[&str]) -> bool {
+        let id = self.widget_id(label);
+        let (position, hovered) = self.row();
+        let open = self.state.open_combo == Some(id);
+        if hovered && self.clicked {
+            self.state.open_combo = if open { None } else { Some(id) };
+        }
+
+        self.background(position, hovered);
+        let current = options.get(*selected).copied().unwrap_or("-");
+        let arrow = if open { "^" } else { "v" };
+        self.overlay.text(
+            position + Vec2::new(4.0, 2.0),
+            &format!("{label}: {current} {arrow}"),
+            TEXT,
+        );
+
+        let mut changed = false;
+        if open {
+            for (index, option) in options.iter().enumerate() {
+                let (position, hovered) = self.row();
+                if hovered && self.clicked {
+                    changed = index != *selected;
+                    *selected = index;
+                    self.state.open_combo = None;
+                }
+                if index == *selected {
+                    self.overlay
+                        .rect(position, Vec2::new(PANEL_WIDTH, LINE_HEIGHT), ACCENT);
+                } else {
+                    self.background(position, hovered);
+                }
+                self.overlay
+                    .text(position + Vec2::new(20.0, 2.0), option, TEXT);
+            }
+        }
+        changed
+    }
+
+    fn widget_id(&mut self, label: &str) -> u64 {
+        let base = hash((self.scope, label));
+        let count = self.seen.entry(base).or_default();
+        *count += 1;
+        hash((base, *count))
+    }
+
+    // Allocates the next row and reports whether the cursor is over it
+    fn row(&mut self) -> (Vec2, bool) {
+        let position = self.position;
+        self.position.y += LINE_HEIGHT + ROW_SPACING;
+
+        let hovered = self.cursor.is_some_and(|cursor| {
+            cursor.x >= position.x
+                && cursor.x < position.x + PANEL_WIDTH
+                && cursor.y >= position.y
+                && cursor.y < position.y + LINE_HEIGHT
+        });
+        (position, hovered)
+    }
+
+    fn background(&self, position: Vec2, hovered: bool) {
+        let color = if hovered { HOVERED } else { BACKGROUND };
+        self.overlay
+            .rect(position, Vec2::new(PANEL_WIDTH, LINE_HEIGHT), color);
+    }
+}
+
+fn hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,21 @@
+use std::mem::size_of;
+
+use wgpu::{BufferAddress, VertexBufferLayout};
+
+pub use framework_derive::VertexLayout;
+// Used by the code `#[derive(VertexLayout)]` generates
+pub use wgpu::{VertexAttribute, VertexFormat, VertexStepMode};
+
+// Implemented by `#[derive(VertexLayout)]`: one attribute per field, one per column for matrices
+pub trait VertexLayout: Sized {
+    const STEP_MODE: VertexStepMode;
+    const ATTRIBUTES: &'static [VertexAttribute];
+
+    fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: Self::STEP_MODE,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
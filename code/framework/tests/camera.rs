@@ -0,0 +1,264 @@
+use std::f32::consts::FRAC_PI_2;
+
+use framework::{
+    ArcballCamera, Camera, CameraController, CameraSwitcher, Frustum, Input, OrbitCamera,
+    Projection, TurntableCamera,
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+fn drag(button: u64, dx: f64, dy: f64) -> Input {
+    let mut input = Input::default();
+    input.press_mouse(button);
+    input.set_mouse_delta(dx, dy);
+    input
+}
+
+fn assert_near(actual: Vec3, expected: Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-4), "{actual} != {expected}");
+}
+
+#[test]
+fn controllers_start_at_the_requested_view() {
+    let (eye, target) = (Vec3::new(1.0, 2.0, 5.0), Vec3::new(0.0, 0.5, 0.0));
+    let controllers: [Box<dyn CameraController>; 3] = [
+        Box::new(OrbitCamera::looking_at(eye, target)),
+        Box::new(ArcballCamera::looking_at(eye, target)),
+        Box::new(TurntableCamera::looking_at(eye, target)),
+    ];
+    for camera in controllers {
+        assert_near(camera.position(), eye);
+        assert_near(camera.target(), target);
+        // The target ends up straight ahead, on the view space -Z axis
+        let view_target = camera.view_matrix().transform_point3(target);
+        assert_near(view_target, Vec3::new(0.0, 0.0, -eye.distance(target)));
+    }
+}
+
+#[test]
+fn orbit_rotates_zooms_and_pans_around_the_target() {
+    let mut camera = OrbitCamera::new(Vec3::ZERO, 5.0, 0.0, 0.0);
+    camera.smoothing = 0.0;
+    assert_near(camera.position(), Vec3::new(0.0, 0.0, 5.0));
+
+    // Dragging right swings the camera to the left, dragging down lifts it
+    camera.update(0.016, &drag(1, 100.0, 0.0));
+    assert!(camera.position().x < 0.0);
+    camera.update(0.016, &drag(1, 0.0, 100.0));
+    assert!(camera.position().y > 0.0);
+    assert!((camera.position().length() - 5.0).abs() < 1e-4);
+
+    let mut input = Input::default();
+    input.add_scroll_delta(2.0);
+    camera.update(0.016, &input);
+    assert!((camera.position().length() - 5.0 * 0.9 * 0.9).abs() < 1e-4);
+
+    camera.update(0.016, &drag(2, 50.0, 0.0));
+    assert_ne!(camera.target(), Vec3::ZERO);
+    assert!(((camera.position() - camera.target()).length() - 4.05).abs() < 1e-4);
+}
+
+#[test]
+fn smoothing_eases_towards_the_input() {
+    let mut camera = OrbitCamera::new(Vec3::ZERO, 5.0, 0.0, 0.0);
+    camera.smoothing = 0.1;
+    camera.target = Vec3::new(1.0, 0.0, 0.0);
+    camera.update(0.1, &Input::default());
+    // One time constant covers about 63% of the way
+    assert!(
+        (camera.target().x - 0.632).abs() < 1e-3,
+        "{}",
+        camera.target()
+    );
+    for _ in 0..100 {
+        camera.update(0.1, &Input::default());
+    }
+    assert_near(camera.target(), Vec3::X);
+}
+
+#[test]
+fn arcball_rolls_over_the_pole() {
+    let mut camera = ArcballCamera::looking_at(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO);
+    camera.smoothing = 0.0;
+    camera.sensitivity = 0.01;
+    // 100 pixels turn 1 radian, three drags go past straight overhead
+    for _ in 0..3 {
+        camera.update(0.016, &drag(1, 0.0, 100.0));
+    }
+    assert!(camera.position().z < 0.0, "{}", camera.position());
+    assert!(camera.up().y < 0.0, "{}", camera.up());
+    assert!((camera.position().length() - 5.0).abs() < 1e-4);
+}
+
+#[test]
+fn turntable_spins_at_a_fixed_height() {
+    let mut camera = TurntableCamera::looking_at(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO);
+    camera.orbit.smoothing = 0.0;
+    let height = camera.position().y;
+    camera.update(1.0, &Input::default());
+    assert!(camera.position().x.abs() > 1.0);
+    camera.update(0.016, &drag(1, 30.0, 80.0));
+    assert!((camera.position().y - height).abs() < 1e-4);
+}
+
+#[test]
+fn switching_controllers_keeps_the_view() {
+    let mut cameras = CameraSwitcher::new("Fly", Camera::new(Vec3::new(0.0, 1.0, 4.0), 0.3, -0.2))
+        .with("Orbit", OrbitCamera::new(Vec3::ZERO, 10.0, 0.0, 0.0))
+        .with(
+            "Arcball",
+            ArcballCamera::looking_at(Vec3::Z * 3.0, Vec3::ZERO),
+        );
+    assert_eq!(cameras.names(), ["Fly", "Orbit", "Arcball"]);
+    let view = cameras.view_matrix();
+
+    cameras.select(1);
+    assert_eq!(cameras.active(), 1);
+    assert!(cameras.view_matrix().abs_diff_eq(view, 1e-4));
+    cameras.select(2);
+    assert!(cameras.view_matrix().abs_diff_eq(view, 1e-4));
+    cameras.select(0);
+    assert!(cameras.view_matrix().abs_diff_eq(view, 1e-4));
+}
+
+// 90 degrees vertically, so the side planes are at 45 degrees
+const PERSPECTIVE: Projection = Projection::Perspective {
+    fov_y: FRAC_PI_2,
+    near: 1.0,
+    far: 10.0,
+};
+
+#[test]
+fn projections_match_known_matrices() {
+    let expected = Mat4::from_cols(
+        Vec4::new(0.5, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, 1.0, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, -10.0 / 9.0, -1.0),
+        Vec4::new(0.0, 0.0, -10.0 / 9.0, 0.0),
+    );
+    assert!(PERSPECTIVE.matrix(2.0).abs_diff_eq(expected, 1e-6));
+    assert_eq!(Projection::default().matrix(1.5), {
+        glam::camera::rh::proj::directx::perspective(std::f32::consts::FRAC_PI_4, 1.5, 0.1, 100.0)
+    });
+
+    let reverse = Projection::InfiniteReverse {
+        fov_y: FRAC_PI_2,
+        near: 0.5,
+    };
+    let expected = Mat4::from_cols(
+        Vec4::new(1.0, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, 1.0, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, 0.0, -1.0),
+        Vec4::new(0.0, 0.0, 0.5, 0.0),
+    );
+    assert!(reverse.matrix(1.0).abs_diff_eq(expected, 1e-6));
+
+    let ortho = Projection::Orthographic {
+        height: 4.0,
+        near: 1.0,
+        far: 5.0,
+    };
+    let expected = Mat4::from_cols(
+        Vec4::new(0.25, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, 0.5, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, -0.25, 0.0),
+        Vec4::new(0.0, 0.0, -0.25, 1.0),
+    );
+    assert!(ortho.matrix(2.0).abs_diff_eq(expected, 1e-6));
+}
+
+#[test]
+fn frustum_planes_and_corners() {
+    let frustum = Frustum::from_matrix(PERSPECTIVE.matrix(1.0));
+    let side = std::f32::consts::FRAC_1_SQRT_2;
+    let expected = [
+        Vec4::new(side, 0.0, -side, 0.0),
+        Vec4::new(-side, 0.0, -side, 0.0),
+        Vec4::new(0.0, side, -side, 0.0),
+        Vec4::new(0.0, -side, -side, 0.0),
+        Vec4::new(0.0, 0.0, -1.0, -1.0),
+        Vec4::new(0.0, 0.0, 1.0, 10.0),
+    ];
+    for (plane, expected) in frustum.planes.iter().zip(expected) {
+        assert!(plane.abs_diff_eq(expected, 1e-5), "{plane} != {expected}");
+    }
+    assert_near(frustum.corners[0], Vec3::new(-1.0, -1.0, -1.0));
+    assert_near(frustum.corners[2], Vec3::new(1.0, 1.0, -1.0));
+    assert_near(frustum.corners[4], Vec3::new(-10.0, -10.0, -10.0));
+    assert_near(frustum.corners[5], Vec3::new(10.0, -10.0, -10.0));
+
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -5.0)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -11.0)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.5)));
+    assert!(!frustum.contains_point(Vec3::new(6.0, 0.0, -5.0)));
+    // 0.71 outside the right plane
+    assert!(frustum.intersects_sphere(Vec3::new(6.0, 0.0, -5.0), 1.0));
+    assert!(!frustum.intersects_sphere(Vec3::new(6.0, 0.0, -5.0), 0.5));
+
+    // Moving the camera moves the frustum with it
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 20.0), 0.0, 0.0);
+    camera.projection = PERSPECTIVE;
+    let frustum = camera.frustum(1.0);
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 15.0)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -5.0)));
+    assert_near(frustum.corners[0], Vec3::new(-1.0, -1.0, 19.0));
+}
+
+#[test]
+fn infinite_reverse_frustum_has_no_far_plane() {
+    let reverse = Projection::InfiniteReverse {
+        fov_y: FRAC_PI_2,
+        near: 1.0,
+    };
+    let frustum = Frustum::from_matrix(reverse.matrix(1.0));
+    assert_eq!(frustum.planes[4].truncate(), Vec3::ZERO);
+    assert!(frustum.planes[5].abs_diff_eq(Vec4::new(0.0, 0.0, -1.0, -1.0), 1e-6));
+    assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -1e6)));
+    assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.5)));
+    // Depth 0 is infinitely far away, depth 1 is the near plane
+    assert!(!frustum.corners[0].is_finite());
+    assert_near(frustum.corners[4], Vec3::new(-1.0, -1.0, -1.0));
+}
+
+#[test]
+fn rays_go_through_the_picked_pixel() {
+    let viewport = Vec2::new(800.0, 600.0);
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), 0.0, 0.0);
+    let ray = camera.ray(viewport / 2.0, viewport);
+    assert_near(ray.origin, Vec3::new(0.0, 0.0, 5.0));
+    assert_near(ray.direction, Vec3::NEG_Z);
+
+    camera.projection = PERSPECTIVE;
+    let ray = camera.ray(Vec2::ZERO, viewport);
+    assert_near(ray.direction, Vec3::new(-4.0 / 3.0, 1.0, -1.0).normalize());
+
+    camera.position = Vec3::new(1.0, 2.0, 3.0);
+    camera.yaw = 0.7;
+    camera.pitch = -0.3;
+    let projections = [
+        Projection::default(),
+        Projection::InfiniteReverse {
+            fov_y: 1.0,
+            near: 0.1,
+        },
+        Projection::Orthographic {
+            height: 6.0,
+            near: 0.5,
+            far: 50.0,
+        },
+    ];
+    let pixel = Vec2::new(620.0, 130.0);
+    let expected = pixel / viewport * Vec2::new(2.0, -2.0) + Vec2::new(-1.0, 1.0);
+    for projection in projections {
+        camera.projection = projection;
+        let ray = camera.ray(pixel, viewport);
+        assert!((ray.direction.length() - 1.0).abs() < 1e-5);
+        let view_proj = camera.view_proj(viewport.x / viewport.y);
+        for distance in [1.0, 7.5] {
+            let ndc = view_proj.project_point3(ray.at(distance));
+            assert!(
+                ndc.truncate().abs_diff_eq(expected, 1e-4),
+                "{projection:?} {ndc}"
+            );
+        }
+    }
+}
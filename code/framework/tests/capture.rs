@@ -0,0 +1,53 @@
+use framework::capture::save_texture;
+use framework::{Error, GpuContext};
+use wgpu::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages};
+use winit::dpi::PhysicalSize;
+
+fn texture(ctx: &GpuContext, format: TextureFormat, usage: TextureUsages) -> wgpu::Texture {
+    ctx.device.create_texture(&TextureDescriptor {
+        label: Some("Capture Target"),
+        size: Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    })
+}
+
+#[test]
+fn unreadable_targets_are_errors() {
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    let path = std::env::temp_dir().join(format!("framework-capture-{}.png", std::process::id()));
+
+    let offscreen = ctx.offscreen.as_ref().unwrap();
+    save_texture(&ctx, offscreen, &path).expect("Failed to capture the offscreen target");
+    assert!(path.exists());
+    let _ = std::fs::remove_file(&path);
+
+    // Depth and stencil have no single texel size
+    let depth_stencil = texture(
+        &ctx,
+        TextureFormat::Depth24PlusStencil8,
+        TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+    );
+    let result = save_texture(&ctx, &depth_stencil, &path);
+    assert!(
+        matches!(result, Err(Error::UnsupportedFormat(_))),
+        "{result:?}"
+    );
+
+    let sampled_only = texture(
+        &ctx,
+        TextureFormat::Rgba8Unorm,
+        TextureUsages::TEXTURE_BINDING,
+    );
+    let result = save_texture(&ctx, &sampled_only, &path);
+    assert!(matches!(result, Err(Error::Readback(_))), "{result:?}");
+    assert!(!path.exists());
+}
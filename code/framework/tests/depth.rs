@@ -0,0 +1,200 @@
+use std::f32::consts::FRAC_PI_4;
+
+use framework::{Camera, DepthMode, GpuContext, Projection, create_depth_texture, read_texture};
+use glam::Vec3;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BufferUsages, Color, CommandEncoderDescriptor, Extent3d,
+    FragmentState, LoadOp, MultisampleState, Operations, PrimitiveState, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
+};
+use winit::dpi::PhysicalSize;
+
+const SIZE: u32 = 128;
+// Height of the red decal above the green floor. The floor runs 100 units away from a camera 1 unit
+// above it, with 0.1-100 standard depth the two layers share a depth value over most of it
+const GAP: f32 = 3e-6;
+
+fn quad(y: f32, color: [f32; 3]) -> Vec<[f32; 6]> {
+    let corners = [
+        (-100.0, -100.0),
+        (100.0, -100.0),
+        (100.0, 0.0),
+        (-100.0, 0.0),
+    ];
+    [0, 2, 1, 0, 3, 2]
+        .map(|i| {
+            let (x, z) = corners[i];
+            [x, y, z, color[0], color[1], color[2]]
+        })
+        .to_vec()
+}
+
+// Fraction of the floor pixels where the green floor shows through the red decal above it. The
+// floor goes first in the same draw, so the decal only wins where its depth is strictly closer
+fn z_fighting(projection: Projection) -> f32 {
+    let ctx =
+        GpuContext::new_headless(PhysicalSize::new(SIZE, SIZE), TextureFormat::Rgba8UnormSrgb);
+    let mut camera = Camera::new(Vec3::new(0.0, 1.0, 0.0), 0.0, -0.05);
+    camera.projection = projection;
+    let depth_mode = projection.depth_mode();
+
+    let mut vertices = quad(0.0, [0.0, 1.0, 0.0]);
+    vertices.extend(quad(GAP, [1.0, 0.0, 0.0]));
+    let vertex_buffer = ctx.device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: BufferUsages::VERTEX,
+    });
+    let uniform_buffer = ctx.device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Camera Buffer"),
+        contents: bytemuck::bytes_of(&camera.view_proj(1.0)),
+        usage: BufferUsages::UNIFORM,
+    });
+
+    let module = ctx.device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("Floor Shader"),
+        source: ShaderSource::Wgsl(
+            "@group(0) @binding(0) var<uniform> view_proj: mat4x4f;
+
+            struct VertexOutput {
+                @builtin(position) position: vec4f,
+                @location(0) color: vec3f,
+            }
+
+            @vertex
+            fn vs_main(@location(0) position: vec3f, @location(1) color: vec3f) -> VertexOutput {
+                return VertexOutput(view_proj * vec4f(position, 1.0), color);
+            }
+
+            @fragment
+            fn fs_main(input: VertexOutput) -> @location(0) vec4f {
+                return vec4f(input.color, 1.0);
+            }"
+            .into(),
+        ),
+    });
+    let pipeline = ctx
+        .device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Floor Pipeline"),
+            layout: None,
+            vertex: VertexState {
+                module: &module,
+                entry_point: Some("vs_main"),
+                buffers: &[Some(VertexBufferLayout {
+                    array_stride: 24,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &[
+                        VertexAttribute {
+                            format: VertexFormat::Float32x3,
+                            offset: 0,
+                            shader_location: 0,
+                        },
+                        VertexAttribute {
+                            format: VertexFormat::Float32x3,
+                            offset: 12,
+                            shader_location: 1,
+                        },
+                    ],
+                })],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(TextureFormat::Rgba8Unorm.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(depth_mode.depth_stencil_state()),
+            multisample: MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+    let bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Camera Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        }],
+    });
+
+    let target = ctx.device.create_texture(&TextureDescriptor {
+        label: Some("Floor Target"),
+        size: Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let target_view = target.create_view(&TextureViewDescriptor::default());
+    let (_depth_texture, depth_view) = create_depth_texture(&ctx, "Depth Texture");
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    {
+        let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Floor Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &target_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(depth_mode.attachment(&depth_view)),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        rpass.draw(0..vertices.len() as u32, 0..1);
+    }
+    ctx.queue.submit([encoder.finish()]);
+
+    let pixels = read_texture(&ctx, &target).expect("Failed to read texture");
+    let (mut floor, mut green) = (0, 0);
+    for pixel in pixels.chunks_exact(4) {
+        if pixel[..3] != [0, 0, 0] {
+            floor += 1;
+            if pixel[1] > pixel[0] {
+                green += 1;
+            }
+        }
+    }
+    assert!(floor > (SIZE * SIZE / 4) as usize, "{floor}");
+    green as f32 / floor as f32
+}
+
+#[test]
+fn reverse_z_reduces_z_fighting() {
+    assert_eq!(DepthMode::Standard.clear_value(), 1.0);
+    assert_eq!(DepthMode::Reverse.clear_value(), 0.0);
+    assert_eq!(Projection::default().depth_mode(), DepthMode::Standard);
+
+    let standard = z_fighting(Projection::Perspective {
+        fov_y: FRAC_PI_4,
+        near: 0.1,
+        far: 100.0,
+    });
+    let reverse = z_fighting(Projection::InfiniteReverse {
+        fov_y: FRAC_PI_4,
+        near: 0.1,
+    });
+    assert!(standard > 0.2, "{standard}");
+    assert!(reverse < standard / 10.0, "{reverse} vs {standard}");
+}
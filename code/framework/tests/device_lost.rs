@@ -0,0 +1,58 @@
+use framework::texture::read_texture;
+use framework::{Example, GpuContext, Result, recover_device, run_headless};
+use wgpu::{
+    Color, CommandEncoder, LoadOp, Operations, PollType, RenderPassColorAttachment,
+    RenderPassDescriptor, StoreOp, TextureView,
+};
+use winit::dpi::PhysicalSize;
+
+struct Clear;
+
+impl Example for Clear {
+    fn init(_ctx: &GpuContext) -> Result<Self> {
+        Ok(Self)
+    }
+
+    fn render(&mut self, _ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Clear Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::RED),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+    }
+}
+
+#[test]
+fn recovers_from_destroyed_device() {
+    let (mut ctx, mut example) = run_headless::<Clear>(PhysicalSize::new(16, 16), 1);
+    assert!(!ctx.is_lost());
+
+    ctx.device.destroy();
+    let _ = ctx.device.poll(PollType::wait_indefinitely());
+    assert!(ctx.is_lost());
+
+    recover_device(&mut ctx, &mut example).expect("Failed to recover device");
+    assert!(!ctx.is_lost());
+
+    let (_, view, mut encoder) = ctx.acquire_frame().expect("No frame after recovery");
+    example.render(&ctx, &view, &mut encoder);
+    ctx.queue.submit([encoder.finish()]);
+
+    let texture = ctx
+        .offscreen
+        .as_ref()
+        .expect("Headless context has no offscreen target");
+    let pixels = read_texture(&ctx, texture).expect("Failed to read texture");
+    assert_eq!(&pixels[..4], &[255, 0, 0, 255]);
+}
@@ -0,0 +1,28 @@
+use framework::app::HEADLESS_FRAME_TIME;
+use framework::{Error, Example, GpuContext, Input, Result};
+use wgpu::{CommandEncoder, TextureView};
+use winit::dpi::PhysicalSize;
+
+struct MissingModel;
+
+impl Example for MissingModel {
+    fn init(_ctx: &GpuContext) -> Result<Self> {
+        Err(Error::Model {
+            path: "missing.gltf".into(),
+            message: "not found".into(),
+        })
+    }
+
+    fn render(&mut self, _ctx: &GpuContext, _view: &TextureView, _encoder: &mut CommandEncoder) {}
+}
+
+#[test]
+fn init_errors_are_returned() {
+    let result = framework::try_run_headless_with::<MissingModel>(
+        PhysicalSize::new(4, 4),
+        1,
+        HEADLESS_FRAME_TIME,
+        &Input::default(),
+    );
+    assert!(matches!(result, Err(Error::Model { .. })));
+}
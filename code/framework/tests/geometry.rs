@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use framework::Indices;
+use framework::geometry::{capsule, cone, cylinder, grid, icosphere, plane, torus, uv_sphere};
+use framework::mesh::Mesh;
+use glam::{Vec2, Vec3, Vec4};
+
+fn triangles(mesh: &Mesh) -> Vec<[u32; 3]> {
+    let indices: Vec<u32> = mesh.indices.iter().collect();
+    assert_eq!(indices.len() % 3, 0, "{}", mesh.name);
+    indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        .collect()
+}
+
+// Seams and hard edges duplicate vertices, so the topology is checked on welded positions
+fn welded(mesh: &Mesh) -> Vec<usize> {
+    let mut ids = HashMap::new();
+    mesh.vertices
+        .iter()
+        .map(|v| {
+            let key = v.position.map(|x| (x * 1e4).round() as i64);
+            let next = ids.len();
+            *ids.entry(key).or_insert(next)
+        })
+        .collect()
+}
+
+// Every directed edge appears once, and on a closed surface its reverse does too
+fn assert_manifold(mesh: &Mesh, closed: bool) {
+    let ids = welded(mesh);
+    let mut edges = HashMap::new();
+    for [a, b, c] in triangles(mesh) {
+        let [a, b, c] = [a, b, c].map(|i| ids[i as usize]);
+        assert!(
+            a != b && b != c && c != a,
+            "{} has a degenerate triangle",
+            mesh.name
+        );
+        for edge in [(a, b), (b, c), (c, a)] {
+            *edges.entry(edge).or_insert(0) += 1;
+        }
+    }
+    for (&(a, b), &count) in &edges {
+        assert_eq!(count, 1, "{}: edge {a}-{b} is shared badly", mesh.name);
+        if closed {
+            assert!(
+                edges.contains_key(&(b, a)),
+                "{}: edge {a}-{b} is open",
+                mesh.name
+            );
+        }
+    }
+}
+
+// Faces wind counter-clockwise around the vertex normals, and the normals point away from `inside`
+fn assert_normals_outward(mesh: &Mesh, inside: impl Fn(Vec3) -> Vec3) {
+    for [a, b, c] in triangles(mesh) {
+        let [a, b, c] = [a, b, c].map(|i| mesh.vertices[i as usize]);
+        let [pa, pb, pc] = [a, b, c].map(|v| Vec3::from(v.position));
+        let face = (pb - pa).cross(pc - pa);
+        for vertex in [a, b, c] {
+            let normal = Vec3::from(vertex.normal);
+            assert!(normal.is_normalized(), "{}", mesh.name);
+            assert!(face.dot(normal) > 0.0, "{}: face against normal", mesh.name);
+            let position = Vec3::from(vertex.position);
+            assert!(
+                normal.dot(position - inside(position)) > 0.0,
+                "{}: normal {normal} points inwards at {position}",
+                mesh.name
+            );
+        }
+    }
+    for vertex in &mesh.vertices {
+        let tangent = Vec4::from(vertex.tangent);
+        assert!(tangent.truncate().is_normalized(), "{}", mesh.name);
+        assert!(
+            tangent.truncate().dot(vertex.normal.into()).abs() < 1e-4,
+            "{}",
+            mesh.name
+        );
+        assert_eq!(tangent.w.abs(), 1.0, "{}", mesh.name);
+    }
+}
+
+#[test]
+fn plane_and_grid_face_up() {
+    let quad = plane(10.0, 10.0);
+    assert_eq!(quad.vertices.len(), 4);
+    assert_eq!(quad.indices, Indices::U16(vec![0, 2, 3, 0, 3, 1]));
+
+    let grid = grid(4.0, 2.0, 8, 4);
+    assert_eq!(grid.vertices.len(), 9 * 5);
+    assert_eq!(grid.indices.len(), 8 * 4 * 6);
+    assert_eq!(grid.vertices[0].position, [-2.0, 0.0, -1.0]);
+    assert_eq!(grid.vertices[44].uv, [1.0, 1.0]);
+    for mesh in [&quad, &grid] {
+        assert_manifold(mesh, false);
+        assert_normals_outward(mesh, |p| p - Vec3::Y);
+        // u grows along +X and v along +Z, so the tangent is +X with a positive sign
+        assert!(
+            mesh.vertices
+                .iter()
+                .all(|v| v.tangent == [1.0, 0.0, 0.0, 1.0])
+        );
+    }
+}
+
+#[test]
+fn spheres_are_closed_with_radial_normals() {
+    for mesh in [uv_sphere(2.0, 24, 12), icosphere(2.0, 0), icosphere(2.0, 3)] {
+        assert_manifold(&mesh, true);
+        assert_normals_outward(&mesh, |_| Vec3::ZERO);
+        for vertex in &mesh.vertices {
+            let position = Vec3::from(vertex.position);
+            assert!((position.length() - 2.0).abs() < 1e-5, "{}", mesh.name);
+            assert!(position.normalize().abs_diff_eq(vertex.normal.into(), 1e-5));
+        }
+    }
+
+    // Poles are split per sector, so there are no degenerate triangles
+    let sphere = uv_sphere(1.0, 24, 12);
+    assert_eq!(sphere.vertices.len(), 25 * 13);
+    assert_eq!(sphere.indices.len(), 24 * 11 * 6);
+    // 20 * 4^3 faces, the seam and the poles add vertices on top of 10 * 4^3 + 2
+    let ico = icosphere(1.0, 3);
+    assert_eq!(ico.indices.len(), 1280 * 3);
+    assert!(ico.vertices.len() > 642);
+    let front = ico
+        .vertices
+        .iter()
+        .find(|v| Vec3::from(v.position).abs_diff_eq(Vec3::Z, 1e-6));
+    assert!(Vec2::from(front.unwrap().uv).abs_diff_eq(Vec2::new(0.5, 0.5), 1e-6));
+}
+
+#[test]
+fn solids_of_revolution_are_closed() {
+    let cylinder = cylinder(1.0, 2.0, 16);
+    assert_manifold(&cylinder, true);
+    assert_normals_outward(&cylinder, |p| Vec3::new(0.0, p.y.clamp(-0.5, 0.5), 0.0));
+
+    let cone = cone(1.0, 2.0, 16);
+    assert_manifold(&cone, true);
+    assert_normals_outward(&cone, |_| Vec3::new(0.0, -0.5, 0.0));
+
+    let capsule = capsule(0.5, 1.0, 16, 6);
+    assert_manifold(&capsule, true);
+    assert_normals_outward(&capsule, |p| Vec3::new(0.0, p.y.clamp(-0.5, 0.5), 0.0));
+    let top = capsule
+        .vertices
+        .iter()
+        .map(|v| v.position[1])
+        .fold(0.0, f32::max);
+    assert_eq!(top, 1.0);
+    assert_eq!(capsule.vertices.last().unwrap().uv[1], 1.0);
+
+    let torus = torus(2.0, 0.5, 32, 16);
+    assert_manifold(&torus, true);
+    // The tube's center circle
+    assert_normals_outward(&torus, |p| (p * Vec3::new(1.0, 0.0, 1.0)).normalize() * 2.0);
+}
+
+#[test]
+fn large_meshes_switch_to_u32() {
+    assert!(matches!(grid(1.0, 1.0, 254, 256).indices, Indices::U16(_)));
+    let big = grid(1.0, 1.0, 256, 256);
+    assert_eq!(big.vertices.len(), 257 * 257);
+    assert!(matches!(big.indices, Indices::U32(_)));
+    assert_manifold(&big, false);
+}
+
+#[test]
+fn transformed_moves_vertices_and_normals() {
+    let floor = plane(10.0, 10.0)
+        .transformed(glam::Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)))
+        .with_uv_scale(Vec2::splat(5.0));
+    assert_eq!(floor.vertices[3].position, [5.0, -0.5, 5.0]);
+    assert_eq!(floor.vertices[3].uv, [5.0, 5.0]);
+
+    let tilted = plane(1.0, 1.0).transformed(glam::Mat4::from_scale(Vec3::new(2.0, 1.0, 1.0)));
+    assert_eq!(tilted.vertices[0].normal, [0.0, 1.0, 0.0]);
+    assert_eq!(tilted.vertices[0].position, [-1.0, 0.0, -0.5]);
+}
@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use framework::replay::FrameRecord;
+use framework::{Binding, Bindings, Camera, GamepadAxis, GamepadButton, GamepadEvent, Input};
+use gilrs::{Axis, Button};
+use glam::Vec3;
+use winit::dpi::PhysicalSize;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+#[test]
+fn edges_last_one_frame() {
+    let mut input = Input::default();
+    input.press_key(KeyCode::KeyE);
+    input.press_mouse(0);
+    input.press_gamepad_button(GamepadButton::South);
+    assert!(input.key_just_pressed(KeyCode::KeyE));
+    assert!(input.mouse_button_just_pressed(0));
+    assert!(input.gamepad_button_just_pressed(GamepadButton::South));
+
+    // Key repeat presses an already pressed key again
+    input.clear_delta();
+    input.press_key(KeyCode::KeyE);
+    assert!(input.key_pressed(KeyCode::KeyE));
+    assert!(!input.key_just_pressed(KeyCode::KeyE));
+    assert!(!input.mouse_button_just_pressed(0));
+
+    input.release_key(KeyCode::KeyE);
+    input.release_mouse(0);
+    input.release_gamepad_button(GamepadButton::South);
+    assert!(input.key_just_released(KeyCode::KeyE));
+    assert!(input.mouse_button_just_released(0));
+    assert!(input.gamepad_button_just_released(GamepadButton::South));
+    assert!(!input.key_pressed(KeyCode::KeyE));
+    input.clear_delta();
+    assert!(!input.key_just_released(KeyCode::KeyE));
+}
+
+#[test]
+fn cursor_text_and_modifiers() {
+    let mut input = Input::default();
+    input.set_cursor_position(Some((200.0, 150.0)));
+    assert_eq!(input.cursor_normalized(), None);
+    input.set_window_size(PhysicalSize::new(800, 600));
+    assert_eq!(input.cursor_normalized(), Some((0.25, 0.25)));
+    input.set_cursor_position(None);
+    assert_eq!(input.cursor_normalized(), None);
+
+    input.push_text("é");
+    input.push_text("\u{8}");
+    input.push_text("z\r");
+    assert_eq!(input.text(), "éz");
+    input.set_modifiers(ModifiersState::SHIFT | ModifiersState::CONTROL);
+    assert!(input.modifiers().shift_key());
+    input.clear_delta();
+    assert_eq!(input.text(), "");
+    // Modifiers are state, not an event
+    assert!(input.modifiers().control_key());
+}
+
+#[test]
+fn gamepad_axes_have_a_dead_zone() {
+    let mut input = Input::default();
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, 0.1);
+    input.set_gamepad_axis(GamepadAxis::LeftStickY, -1.5);
+    input.set_gamepad_axis(GamepadAxis::RightTrigger, 0.575);
+    assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickX), 0.0);
+    assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickY), -1.0);
+    assert!((input.gamepad_axis(GamepadAxis::RightTrigger) - 0.5).abs() < 1e-6);
+    assert_eq!(input.gamepad_axis(GamepadAxis::RightStickX), 0.0);
+}
+
+#[test]
+fn gilrs_events_map_onto_the_gamepad_state() {
+    let mut input = Input::default();
+    for event in [
+        GamepadEvent::Connected,
+        GamepadEvent::ButtonPressed(Button::South),
+        // The bumper, gilrs' analog trigger is LeftTrigger2
+        GamepadEvent::ButtonPressed(Button::LeftTrigger),
+        GamepadEvent::ButtonPressed(Button::Mode),
+        GamepadEvent::ButtonChanged(Button::RightTrigger2, 0.8),
+        GamepadEvent::AxisChanged(Axis::LeftStickY, 0.9),
+        GamepadEvent::AxisChanged(Axis::DPadX, 1.0),
+    ] {
+        input.apply_gamepad_event(event);
+    }
+    let mut pressed: Vec<_> = input.pressed_gamepad_buttons().collect();
+    pressed.sort_by_key(|button| format!("{button:?}"));
+    assert_eq!(pressed, [GamepadButton::LeftBumper, GamepadButton::South]);
+    assert!(input.gamepad_button_just_pressed(GamepadButton::South));
+    assert!((input.gamepad_axis(GamepadAxis::RightTrigger) - 0.8).abs() < 0.1);
+    assert!(input.action_pressed("move_forward"));
+    input.clear_delta();
+
+    input.apply_gamepad_event(GamepadEvent::ButtonReleased(Button::LeftTrigger));
+    assert!(input.gamepad_button_just_released(GamepadButton::LeftBumper));
+    input.clear_delta();
+
+    // Pulling the pad out mid-press releases everything it held
+    input.apply_gamepad_event(GamepadEvent::Disconnected);
+    assert!(input.gamepad_button_just_released(GamepadButton::South));
+    assert_eq!(input.pressed_gamepad_buttons().count(), 0);
+    assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickY), 0.0);
+    assert_eq!(input.gamepad_axis(GamepadAxis::RightTrigger), 0.0);
+    assert!(!input.action_pressed("move_forward"));
+}
+
+#[test]
+fn camera_flies_with_the_sticks() {
+    let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0);
+    let mut input = Input::default();
+    // Half way past the dead zone moves at half speed
+    let half = 0.5 + 0.5 * framework::input::STICK_DEAD_ZONE;
+    input.set_gamepad_axis(GamepadAxis::LeftStickY, half);
+    camera.update(1.0, &input);
+    assert!(camera.position.abs_diff_eq(Vec3::new(0.0, 0.0, -2.5), 1e-4));
+
+    // Keys still move at full speed, the stick can't add to that
+    input.press_key(KeyCode::KeyW);
+    camera.update(1.0, &input);
+    assert!(camera.position.abs_diff_eq(Vec3::new(0.0, 0.0, -7.5), 1e-4));
+
+    let mut input = Input::default();
+    input.set_gamepad_axis(GamepadAxis::RightStickX, 1.0);
+    input.set_gamepad_axis(GamepadAxis::RightStickY, 1.0);
+    camera.update(0.1, &input);
+    assert!(camera.yaw < 0.0 && camera.pitch > 0.0);
+    // Turning right moves the view towards +X
+    assert!(camera.direction().x > 0.0);
+}
+
+#[test]
+fn replayed_frames_keep_edges() {
+    let mut input = Input::default();
+    input.set_window_size(PhysicalSize::new(640, 480));
+    input.press_key(KeyCode::Space);
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, 0.8);
+    input.push_text("a");
+    let first = FrameRecord::capture(Duration::from_millis(16), &input);
+    input.clear_delta();
+    input.release_key(KeyCode::Space);
+    let second = FrameRecord::capture(Duration::from_millis(16), &input);
+
+    let mut replayed = first.to_input();
+    assert_eq!(replayed.window_size(), PhysicalSize::new(640, 480));
+    assert!(replayed.key_just_pressed(KeyCode::Space));
+    assert_eq!(replayed.text(), "a");
+    second.apply(&mut replayed);
+    assert!(replayed.key_just_released(KeyCode::Space));
+    assert_eq!(replayed.text(), "");
+    assert_eq!(
+        replayed.gamepad_axis(GamepadAxis::LeftStickX),
+        input.gamepad_axis(GamepadAxis::LeftStickX)
+    );
+}
+
+#[test]
+fn replayed_axes_return_to_rest() {
+    let mut input = Input::default();
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, 0.8);
+    let tilted = FrameRecord::capture(Duration::from_millis(16), &input);
+    input.clear_gamepad_axes();
+    let released = FrameRecord::capture(Duration::from_millis(16), &input);
+
+    let mut replayed = tilted.to_input();
+    assert!(replayed.gamepad_axis(GamepadAxis::LeftStickX) > 0.5);
+    released.apply(&mut replayed);
+    assert_eq!(replayed.gamepad_axis(GamepadAxis::LeftStickX), 0.0);
+}
+
+#[test]
+fn bindings_roundtrip_through_toml() {
+    let bindings = Bindings::default();
+    assert_eq!(
+        Bindings::from_toml(&bindings.to_toml()),
+        Ok(bindings.clone())
+    );
+    assert_eq!(
+        bindings.get("move_forward"),
+        [
+            Binding::Key(KeyCode::KeyW),
+            Binding::GamepadAxis {
+                axis: GamepadAxis::LeftStickY,
+                positive: true
+            }
+        ]
+    );
+    for name in [
+        "Mouse7",
+        "MouseBack",
+        "GamepadDPadUp",
+        "GamepadLeftTrigger-",
+        "Numpad0",
+    ] {
+        assert_eq!(name.parse::<Binding>().unwrap().to_string(), name);
+    }
+    assert!("KeyÜ".parse::<Binding>().is_err());
+    assert!("GamepadLeftStickZ+".parse::<Binding>().is_err());
+
+    let err = Bindings::from_toml("[actions]\nexit = [\"Esc\"]").unwrap_err();
+    assert!(err.contains("exit"), "{err}");
+    assert!(Bindings::from_toml("actions = 1").is_err());
+}
+
+#[test]
+fn azerty_file_moves_the_camera() {
+    let bindings = Bindings::from_toml(
+        "[actions]
+        move_forward = \"KeyZ\"
+        move_left = [\"KeyQ\", \"ArrowLeft\"]",
+    )
+    .unwrap();
+    // Actions missing from the file keep their defaults
+    assert_eq!(
+        bindings.get("move_back"),
+        [
+            Binding::Key(KeyCode::KeyS),
+            Binding::GamepadAxis {
+                axis: GamepadAxis::LeftStickY,
+                positive: false
+            }
+        ]
+    );
+
+    let mut camera = Camera::new(Vec3::ZERO, 0.0, 0.0);
+    let mut input = Input::default();
+    input.set_bindings(bindings);
+    input.press_key(KeyCode::KeyW);
+    camera.update(1.0, &input);
+    assert_eq!(camera.position, Vec3::ZERO);
+
+    input.press_key(KeyCode::KeyZ);
+    camera.update(1.0, &input);
+    assert!(camera.position.abs_diff_eq(Vec3::new(0.0, 0.0, -5.0), 1e-4));
+}
+
+#[test]
+fn actions_combine_their_bindings() {
+    let mut input = Input::default();
+    input
+        .bindings_mut()
+        .bind("jump", Binding::GamepadButton(GamepadButton::South));
+    input.bindings_mut().bind(
+        "jump",
+        Binding::GamepadAxis {
+            axis: GamepadAxis::RightTrigger,
+            positive: true,
+        },
+    );
+    assert!(!input.action_pressed("jump"));
+    assert!(!input.action_pressed("missing"));
+
+    // A trigger half way is not a press yet
+    input.set_gamepad_axis(GamepadAxis::RightTrigger, 0.5);
+    assert!(!input.action_pressed("jump"));
+    assert!(input.action_value("jump") > 0.0 && input.action_value("jump") < 0.5);
+    input.clear_delta();
+    input.set_gamepad_axis(GamepadAxis::RightTrigger, 1.0);
+    assert!(input.action_just_pressed("jump"));
+    assert_eq!(input.action_value("jump"), 1.0);
+
+    // The button going down while the trigger is held is not a new press
+    input.clear_delta();
+    input.press_gamepad_button(GamepadButton::South);
+    assert!(input.action_pressed("jump"));
+    assert!(!input.action_just_pressed("jump"));
+    input.clear_delta();
+    input.set_gamepad_axis(GamepadAxis::RightTrigger, 0.0);
+    assert!(!input.action_just_released("jump"));
+    input.clear_delta();
+    input.release_gamepad_button(GamepadButton::South);
+    assert!(input.action_just_released("jump"));
+
+    input
+        .bindings_mut()
+        .unbind("jump", Binding::GamepadButton(GamepadButton::South));
+    assert_eq!(input.bindings().get("jump").len(), 1);
+}
+
+#[test]
+fn define_keeps_bindings_from_the_file() {
+    let mut bindings = Bindings::from_toml("[actions]\npost_invert = \"KeyI\"").unwrap();
+    bindings.define("post_invert", &["Digit3"]);
+    bindings.define("post_none", &["Digit1"]);
+    assert_eq!(bindings.get("post_invert"), [Binding::Key(KeyCode::KeyI)]);
+    assert_eq!(bindings.get("post_none"), [Binding::Key(KeyCode::Digit1)]);
+
+    let path = std::env::temp_dir().join(format!("bindings-{}.toml", std::process::id()));
+    bindings.save(&path).unwrap();
+    assert_eq!(Bindings::load(&path).unwrap(), bindings);
+    std::fs::write(&path, "[actions]\nexit = \"Nope\"").unwrap();
+    let err = Bindings::load(&path).unwrap_err().to_string();
+    std::fs::remove_file(&path).unwrap();
+    assert!(err.starts_with("Invalid input bindings"), "{err}");
+}
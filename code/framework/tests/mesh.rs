@@ -0,0 +1,380 @@
+use std::path::{Path, PathBuf};
+
+use framework::mesh::Model;
+use framework::{Error, Indices, load_gltf, load_obj};
+use glam::{Vec3, Vec4};
+
+fn asset(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../guide/advanced/multiple-meshes/assets")
+        .join(name)
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("framework-mesh-{}-{name}", std::process::id()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let word = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(word >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn assert_valid_tangents(model: &Model) {
+    for mesh in &model.meshes {
+        for vertex in &mesh.vertices {
+            let normal = Vec3::from(vertex.normal);
+            let tangent = Vec4::from(vertex.tangent);
+            assert!(
+                (tangent.truncate().length() - 1.0).abs() < 1e-4,
+                "{}",
+                mesh.name
+            );
+            assert!(normal.dot(tangent.truncate()).abs() < 1e-4, "{}", mesh.name);
+            assert_eq!(tangent.w.abs(), 1.0, "{}", mesh.name);
+        }
+    }
+}
+
+#[test]
+fn sample_scene_loads_with_node_transforms() {
+    let model = load_gltf(asset("scene.gltf")).unwrap();
+
+    let names: Vec<_> = model.meshes.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["Left Sphere", "Cube", "Right Sphere", "Moon", "Ground"]
+    );
+
+    let origin = |i: usize| model.meshes[i].transform.transform_point3(Vec3::ZERO);
+    assert!(origin(0).abs_diff_eq(Vec3::new(-3.0, 0.0, 0.0), 1e-6));
+    // The moon is a child of the right sphere
+    assert!(origin(3).abs_diff_eq(Vec3::new(3.0, 1.6, 0.0), 1e-6));
+    assert!(origin(4).abs_diff_eq(Vec3::new(0.0, -1.0, 0.0), 1e-6));
+
+    let sphere = &model.meshes[0];
+    assert_eq!(sphere.vertices.len(), 17 * 33);
+    assert_eq!(sphere.indices.len(), 16 * 32 * 6);
+
+    // The ground stores its tangents interleaved, the other meshes get generated ones
+    let ground = &model.meshes[4];
+    assert_eq!(ground.vertices[0].uv, [0.0, 5.0]);
+    assert_eq!(ground.vertices[0].tangent, [1.0, 0.0, 0.0, 1.0]);
+    let cube_front = model.meshes[1].vertices[0];
+    assert_eq!(cube_front.normal, [0.0, 0.0, 1.0]);
+    assert!(Vec4::from(cube_front.tangent).abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-6));
+    assert_valid_tangents(&model);
+
+    let ground_material = &model.materials[ground.material.unwrap()];
+    assert_eq!(ground_material.name, "Ground");
+    assert_eq!(ground_material.normal_texture, Some(4));
+    assert_eq!(model.images.len(), 5);
+    for image in &model.images {
+        assert_eq!((image.width, image.height), (64, 64));
+        assert_eq!(image.pixels.len(), 64 * 64 * 4);
+    }
+}
+
+#[test]
+fn glb_matches_gltf() {
+    let gltf = load_gltf(asset("scene.gltf")).unwrap();
+    let glb = load_gltf(asset("scene.glb")).unwrap();
+
+    assert_eq!(gltf.meshes.len(), glb.meshes.len());
+    for (a, b) in gltf.meshes.iter().zip(&glb.meshes) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.vertices, b.vertices);
+        assert_eq!(a.indices, b.indices);
+        assert_eq!(a.transform, b.transform);
+        assert_eq!(a.material, b.material);
+    }
+    for (a, b) in gltf.images.iter().zip(&glb.images) {
+        assert_eq!(a.pixels, b.pixels);
+    }
+}
+
+#[test]
+fn missing_normals_are_generated_flat() {
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64(bytemuck::cast_slice(&positions))
+    );
+    let json = format!(
+        r#"{{
+            "asset": {{ "version": "2.0" }},
+            "nodes": [{{ "mesh": 0, "translation": [0, 0, -2] }}],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+            "accessors": [{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }}],
+            "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+            "buffers": [{{ "uri": "{uri}", "byteLength": 36 }}]
+        }}"#
+    );
+    let path = temp_path("triangle.gltf");
+    std::fs::write(&path, json).unwrap();
+
+    let model = load_gltf(&path).unwrap();
+    let mesh = &model.meshes[0];
+    assert_eq!(mesh.indices, Indices::U16(vec![0, 1, 2]));
+    assert!(mesh.vertices.iter().all(|v| v.normal == [0.0, 0.0, 1.0]));
+    assert_eq!(mesh.transform.w_axis, Vec4::new(0.0, 0.0, -2.0, 1.0));
+    assert_valid_tangents(&model);
+}
+
+#[test]
+fn malformed_files_are_model_errors() {
+    let path = temp_path("broken.gltf");
+    std::fs::write(
+        &path,
+        r#"{ "asset": { "version": "2.0" }, "nodes": [{ "mesh": 3 }] }"#,
+    )
+    .unwrap();
+    let err = load_gltf(&path).err().unwrap();
+    assert!(matches!(&err, Error::Model { message, .. } if message.contains("mesh 3")));
+
+    let path = temp_path("broken.glb");
+    std::fs::write(&path, b"glTF\x01\x00\x00\x00").unwrap();
+    assert!(matches!(load_gltf(&path), Err(Error::Model { .. })));
+}
+
+#[test]
+fn mismatched_accessors_are_model_errors() {
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let indices: [u16; 4] = [0, 1, 5, 0];
+    let mut bytes = bytemuck::cast_slice::<_, u8>(&positions).to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&indices));
+    let uri = format!("data:application/octet-stream;base64,{}", base64(&bytes));
+    let gltf = |primitive: &str| {
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "nodes": [{{ "mesh": 0 }}],
+                "meshes": [{{ "primitives": [{primitive}] }}],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+                    {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3" }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+                ],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+                ],
+                "buffers": [{{ "uri": "{uri}", "byteLength": 44 }}]
+            }}"#
+        )
+    };
+
+    let path = temp_path("short-normals.gltf");
+    std::fs::write(
+        &path,
+        gltf(r#"{ "attributes": { "POSITION": 0, "NORMAL": 1 } }"#),
+    )
+    .unwrap();
+    let err = load_gltf(&path).err().unwrap();
+    assert!(
+        matches!(&err, Error::Model { message, .. } if message.contains("NORMAL has 2 elements")),
+        "{err}"
+    );
+
+    let path = temp_path("bad-index.gltf");
+    std::fs::write(
+        &path,
+        gltf(r#"{ "attributes": { "POSITION": 0 }, "indices": 2 }"#),
+    )
+    .unwrap();
+    let err = load_gltf(&path).err().unwrap();
+    assert!(
+        matches!(&err, Error::Model { message, .. } if message.contains("index 5 is out of range")),
+        "{err}"
+    );
+}
+
+#[test]
+fn obj_polygons_are_triangulated_and_deduplicated() {
+    let path = temp_path("quad.obj");
+    std::fs::write(
+        &path,
+        "# unit quad split into two groups sharing an edge\n\
+         v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\nv 2 1 0\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+         vn 0 0 1\n\
+         o Quads\n\
+         f 1/1/1 2/2/1 3/3/1 4/4/1\n\
+         s 1\n\
+         f -5/1/-1 5/2/1 6/3/1 -4/4/-1\n",
+    )
+    .unwrap();
+
+    let model = load_obj(&path).unwrap();
+    assert_eq!(model.meshes.len(), 1);
+    let mesh = &model.meshes[0];
+    assert_eq!(mesh.name, "Quads");
+    // Corners 2/1 and 2/2 share a position but not a UV, so both are kept
+    assert_eq!(mesh.vertices.len(), 8);
+    assert_eq!(
+        mesh.indices.iter().collect::<Vec<_>>(),
+        [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]
+    );
+    assert_eq!(mesh.indices.format(), wgpu::IndexFormat::Uint16);
+    // OBJ UVs are flipped to a top-left origin
+    assert_eq!(mesh.vertices[0].uv, [0.0, 1.0]);
+    assert_eq!(mesh.vertices[2].uv, [1.0, 0.0]);
+    for vertex in &mesh.vertices {
+        assert!(Vec4::from(vertex.tangent).abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-6));
+    }
+}
+
+#[test]
+fn obj_tangents_match_mikktspace() {
+    // Three floor quads: plain UVs, u mirrored, and UVs turned a quarter
+    let path = temp_path("floors.obj");
+    std::fs::write(
+        &path,
+        "v -1 0 1\nv 1 0 1\nv 1 0 -1\nv -1 0 -1\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+         vn 0 1 0\n\
+         f 1/1/1 2/2/1 3/3/1 4/4/1\n\
+         v 2 0 1\nv 4 0 1\nv 4 0 -1\nv 2 0 -1\n\
+         f 5/2/1 6/1/1 7/4/1 8/3/1\n\
+         v 5 0 1\nv 7 0 1\nv 7 0 -1\nv 5 0 -1\n\
+         f 9/4/1 10/1/1 11/2/1 12/3/1\n",
+    )
+    .unwrap();
+
+    let model = load_obj(&path).unwrap();
+    let mesh = &model.meshes[0];
+    assert_eq!(mesh.vertices.len(), 12);
+    let expected = [
+        Vec4::new(1.0, 0.0, 0.0, 1.0),
+        // Mirrored UVs flip the bitangent sign
+        Vec4::new(-1.0, 0.0, 0.0, -1.0),
+        Vec4::new(0.0, 0.0, -1.0, 1.0),
+    ];
+    for (quad, expected) in mesh.vertices.chunks_exact(4).zip(expected) {
+        for vertex in quad {
+            let tangent = Vec4::from(vertex.tangent);
+            assert!(tangent.abs_diff_eq(expected, 1e-6), "{tangent}");
+        }
+    }
+}
+
+#[test]
+fn obj_missing_normals_are_smoothed() {
+    // Two faces of a tent meeting at the ridge along the z axis
+    let path = temp_path("tent.obj");
+    std::fs::write(
+        &path,
+        "v -1 0 0\nv 0 1 0\nv 0 1 -1\nv -1 0 -1\nv 1 0 0\nv 1 0 -1\n\
+         f 1 2 3 4\nf 2 5 6 3\n",
+    )
+    .unwrap();
+
+    let model = load_obj(&path).unwrap();
+    let mesh = &model.meshes[0];
+    assert_eq!(
+        mesh.name,
+        format!("framework-mesh-{}-tent", std::process::id())
+    );
+    assert_eq!(mesh.vertices.len(), 6);
+    let ridge = mesh.vertices.iter().find(|v| v.position == [0.0, 1.0, 0.0]);
+    assert!(Vec3::from(ridge.unwrap().normal).abs_diff_eq(Vec3::Y, 1e-6));
+    let side = mesh
+        .vertices
+        .iter()
+        .find(|v| v.position == [-1.0, 0.0, 0.0]);
+    let expected = Vec3::new(-1.0, 1.0, 0.0).normalize();
+    assert!(Vec3::from(side.unwrap().normal).abs_diff_eq(expected, 1e-6));
+    assert_valid_tangents(&model);
+}
+
+#[test]
+fn obj_materials_come_from_mtl() {
+    let dir = temp_path("materials");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(asset("checker_red.png"), dir.join("red.png")).unwrap();
+    std::fs::copy(asset("ground_normal.png"), dir.join("normal.png")).unwrap();
+    std::fs::write(
+        dir.join("scene.mtl"),
+        "newmtl Red\nKd 1 0.5 0.25\nd 0.5\nmap_Kd red.png\n\
+         newmtl Bumpy\nmap_Bump -bm 0.5 normal.png\nmap_Kd red.png\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("scene.obj"),
+        "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+         g First\nusemtl Red\nf 1 2 3\n\
+         g Second\nusemtl Bumpy\nf 1 2 3\n\
+         g First\nusemtl Red\nf 3 2 1\n",
+    )
+    .unwrap();
+
+    let model = load_obj(dir.join("scene.obj")).unwrap();
+    let names: Vec<_> = model.meshes.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["First", "Second"]);
+    assert_eq!(model.meshes[0].indices.len(), 6);
+    assert_eq!(model.meshes[0].material, Some(0));
+    assert_eq!(model.meshes[1].material, Some(1));
+
+    let red = &model.materials[0];
+    assert_eq!(red.base_color, Vec4::new(1.0, 0.5, 0.25, 0.5));
+    assert_eq!(red.base_color_texture, Some(0));
+    let bumpy = &model.materials[1];
+    assert_eq!(bumpy.normal_texture, Some(1));
+    assert_eq!(bumpy.normal_scale, 0.5);
+    // The same file is only decoded once
+    assert_eq!(bumpy.base_color_texture, Some(0));
+    assert_eq!(model.images.len(), 2);
+}
+
+#[test]
+fn obj_switches_to_u32_indices_past_u16() {
+    let columns = 257;
+    let mut source = String::new();
+    for z in 0..256 {
+        for x in 0..columns {
+            source += &format!("v {x} 0 {z}\n");
+        }
+    }
+    for z in 0..255 {
+        for x in 0..columns - 1 {
+            let i = z * columns + x + 1;
+            source += &format!("f {} {} {} {}\n", i, i + columns, i + columns + 1, i + 1);
+        }
+    }
+    let path = temp_path("grid.obj");
+    std::fs::write(&path, source).unwrap();
+
+    let mesh = &load_obj(&path).unwrap().meshes[0];
+    assert_eq!(mesh.vertices.len(), 256 * 257);
+    assert!(matches!(mesh.indices, Indices::U32(_)));
+    assert_eq!(mesh.indices.format(), wgpu::IndexFormat::Uint32);
+    assert_eq!(mesh.indices.iter().max(), Some(256 * 257 - 1));
+    assert!(mesh.vertices.iter().all(|v| v.normal == [0.0, 1.0, 0.0]));
+}
+
+#[test]
+fn malformed_obj_reports_the_line() {
+    let path = temp_path("broken.obj");
+    std::fs::write(&path, "v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap();
+    let err = load_obj(&path).err().unwrap();
+    assert!(
+        matches!(&err, Error::Model { message, .. } if message.starts_with("line 3:")),
+        "{err}"
+    );
+
+    std::fs::write(&path, "usemtl Missing\n").unwrap();
+    assert!(matches!(load_obj(&path), Err(Error::Model { .. })));
+}
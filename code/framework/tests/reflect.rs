@@ -0,0 +1,169 @@
+use framework::{Error, GpuContext, ShaderLayout};
+use wgpu::{
+    BindingType, BufferBindingType, BufferDescriptor, BufferUsages, Extent3d, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, StorageTextureAccess, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor,
+};
+use winit::dpi::PhysicalSize;
+
+const SOURCE: &str = "
+struct Params {
+    scale: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var color_tex: texture_2d<f32>;
+@group(0) @binding(2) var color_sampler: sampler;
+@group(1) @binding(0) var input_tex: texture_2d<f32>;
+@group(1) @binding(1) var output_tex: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(2) var unused_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSample(color_tex, color_sampler, position.xy) * params.scale;
+}
+
+@compute @workgroup_size(1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    textureStore(output_tex, id.xy, textureLoad(input_tex, id.xy, 0));
+}
+";
+
+fn headless() -> GpuContext {
+    GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb)
+}
+
+#[test]
+fn layout_mirrors_shader_bindings() {
+    let ctx = headless();
+    let layout = ShaderLayout::from_wgsl(&ctx.device, "Reflect Test", SOURCE).unwrap();
+
+    assert_eq!(layout.bind_group_layouts.len(), 2);
+
+    let group0 = layout.entries(0);
+    assert_eq!(group0.len(), 3);
+    assert_eq!(group0[0].visibility, ShaderStages::FRAGMENT);
+    assert!(matches!(
+        group0[0].ty,
+        BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            min_binding_size: Some(size),
+            ..
+        } if size.get() == 16
+    ));
+    assert!(matches!(
+        group0[1].ty,
+        BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            ..
+        }
+    ));
+    assert!(matches!(
+        group0[2].ty,
+        BindingType::Sampler(SamplerBindingType::Filtering)
+    ));
+
+    // Textures only read with textureLoad don't need a filterable format
+    let group1 = layout.entries(1);
+    assert_eq!(group1.len(), 2);
+    assert_eq!(group1[0].visibility, ShaderStages::COMPUTE);
+    assert!(matches!(
+        group1[0].ty,
+        BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: false },
+            ..
+        }
+    ));
+    assert!(matches!(
+        group1[1].ty,
+        BindingType::StorageTexture {
+            access: StorageTextureAccess::WriteOnly,
+            format: TextureFormat::Rgba16Float,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn builder_checks_resources() {
+    let ctx = headless();
+    let layout = ShaderLayout::from_wgsl(&ctx.device, "Reflect Test", SOURCE).unwrap();
+
+    let texture = |format, usage| {
+        ctx.device
+            .create_texture(&TextureDescriptor {
+                label: None,
+                size: Extent3d {
+                    width: 4,
+                    height: 4,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default())
+    };
+    let buffer = |size, usage| {
+        ctx.device.create_buffer(&BufferDescriptor {
+            label: None,
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    };
+
+    let uniforms = buffer(16, BufferUsages::UNIFORM);
+    let color = texture(TextureFormat::Rgba8Unorm, TextureUsages::TEXTURE_BINDING);
+    let sampler = ctx.device.create_sampler(&SamplerDescriptor::default());
+    layout
+        .bind_group(0)
+        .buffer(0, &uniforms)
+        .texture(1, &color)
+        .sampler(2, &sampler)
+        .build(&ctx.device)
+        .unwrap();
+
+    let input = texture(TextureFormat::Rgba16Float, TextureUsages::TEXTURE_BINDING);
+    let wrong_format = texture(TextureFormat::Rgba8Unorm, TextureUsages::STORAGE_BINDING);
+    let storage_only = buffer(16, BufferUsages::STORAGE);
+    let too_small = buffer(4, BufferUsages::UNIFORM);
+
+    let failures = [
+        layout.bind_group(0).buffer(0, &uniforms).texture(1, &color),
+        layout
+            .bind_group(0)
+            .buffer(0, &storage_only)
+            .texture(1, &color)
+            .sampler(2, &sampler),
+        layout
+            .bind_group(0)
+            .buffer(0, &too_small)
+            .texture(1, &color)
+            .sampler(2, &sampler),
+        layout
+            .bind_group(0)
+            .buffer(0, &uniforms)
+            .sampler(1, &sampler)
+            .sampler(2, &sampler),
+        layout
+            .bind_group(1)
+            .texture(0, &input)
+            .texture(1, &wrong_format),
+        layout
+            .bind_group(1)
+            .texture(0, &input)
+            .texture(1, &wrong_format)
+            .texture(2, &input),
+        layout.bind_group(2),
+    ];
+    for builder in failures {
+        assert!(matches!(
+            builder.build(&ctx.device),
+            Err(Error::Binding { .. })
+        ));
+    }
+}
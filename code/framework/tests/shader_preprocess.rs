@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use framework::Error;
+use framework::shader::{preprocess, validate};
+
+#[test]
+fn defines_select_branches_and_substitute_values() {
+    let source = "\
+#define SIZE 4
+#ifdef FAST
+const MODE = 1;
+#else
+const MODE = 2;
+#endif
+#ifndef FAST
+const LIMIT = SIZE * SIZE_2;
+#endif
+";
+
+    let fast = preprocess("test.wgsl", source, &[("FAST", "")]).unwrap();
+    assert_eq!(fast, "const MODE = 1;\n");
+
+    let slow = preprocess("test.wgsl", source, &[]).unwrap();
+    assert_eq!(slow, "const MODE = 2;\nconst LIMIT = 4 * SIZE_2;\n");
+}
+
+#[test]
+fn library_snippets_are_included_once() {
+    let source = "#include <tonemap.wgsl>\n#include <tonemap.wgsl>\n";
+    let output = preprocess("test.wgsl", source, &[]).unwrap();
+    assert_eq!(output.matches("fn aces").count(), 1);
+}
+
+#[test]
+fn malformed_directives_are_reported() {
+    for source in [
+        "#ifdef A\n",
+        "#endif\n",
+        "#ifdef A\n#else\n#else\n#endif\n",
+        "#include <missing.wgsl>\n",
+        "#pragma once\n",
+    ] {
+        assert!(
+            matches!(
+                preprocess("test.wgsl", source, &[]),
+                Err(Error::Shader { .. })
+            ),
+            "{source:?} was accepted"
+        );
+    }
+}
+
+// Every chapter shader has to preprocess and pass naga validation
+#[test]
+fn guide_shaders_are_valid() {
+    let guide = Path::new(env!("CARGO_MANIFEST_DIR")).join("../guide");
+    let mut shaders = Vec::new();
+    collect_shaders(&guide, &mut shaders);
+    assert!(!shaders.is_empty());
+
+    for path in shaders {
+        let path = path.to_string_lossy();
+        validate(&path, &[]).unwrap_or_else(|err| panic!("{err}"));
+    }
+}
+
+#[test]
+fn shadow_pcf_variant_is_valid() {
+    let path = std::env::temp_dir().join(format!("framework-pcf-{}.wgsl", std::process::id()));
+    std::fs::write(&path, "#include <shadow.wgsl>\n").expect("Failed to write shader");
+
+    let path_str = path.to_string_lossy();
+    validate(&path_str, &[("SHADOW_PCF", "")]).unwrap_or_else(|err| panic!("{err}"));
+    validate(&path_str, &[]).unwrap_or_else(|err| panic!("{err}"));
+
+    let _ = std::fs::remove_file(path);
+}
+
+fn collect_shaders(dir: &Path, shaders: &mut Vec<std::path::PathBuf>) {
+    for entry in std::fs::read_dir(dir).expect("Failed to read directory") {
+        let path = entry.expect("Failed to read directory entry").path();
+        if path.is_dir() {
+            collect_shaders(&path, shaders);
+        } else if path.extension().is_some_and(|ext| ext == "wgsl") {
+            shaders.push(path);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use framework::shader::{ShaderFile, ShaderWatcher, validate};
+use framework::{Error, Example, GpuContext, Result, TryExample, load_shader};
+use wgpu::{BufferDescriptor, BufferUsages, CommandEncoder, TextureFormat, TextureView};
+use winit::dpi::PhysicalSize;
+
+const VALID: &str = "@compute @workgroup_size(1) fn main() {}";
+const INVALID: &str = "@compute @workgroup_size(1) fn main() { let x: f32 = 1u; }";
+
+fn write_after_tick(path: &PathBuf, source: &str) {
+    // Keeps modification times apart on filesystems with coarse timestamps
+    std::thread::sleep(Duration::from_millis(1100));
+    std::fs::write(path, source).expect("Failed to write shader");
+}
+
+#[test]
+fn watcher_reports_invalid_edits_until_fixed() {
+    let path = std::env::temp_dir().join(format!("framework-reload-{}.wgsl", std::process::id()));
+    std::fs::write(&path, VALID).expect("Failed to write shader");
+    let path_str: &'static str = Box::leak(path.to_string_lossy().into_owned().into_boxed_str());
+
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    load_shader(
+        &ctx,
+        &ShaderFile {
+            label: "Reload Test",
+            path: path_str,
+            embedded: VALID,
+            includes: &[],
+        },
+    )
+    .expect("Failed to load shader");
+
+    let mut watcher = ShaderWatcher::default();
+    assert!(!watcher.poll());
+
+    // Broken WGSL is an error, not a panic
+    let invalid = ShaderFile {
+        label: "Invalid Test",
+        path: "missing.wgsl",
+        embedded: INVALID,
+        includes: &[],
+    };
+    assert!(matches!(
+        load_shader(&ctx, &invalid),
+        Err(Error::Shader { .. })
+    ));
+
+    write_after_tick(&path, INVALID);
+    assert!(matches!(validate(path_str, &[]), Err(Error::Shader { .. })));
+    assert!(!watcher.poll());
+    assert!(watcher.error().is_some_and(|err| err.contains(path_str)));
+
+    write_after_tick(&path, VALID);
+    assert!(watcher.poll());
+    assert!(watcher.error().is_none());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn quoted_includes_come_from_the_embedded_list() {
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    // The file isn't on disk, so the shader and its include are both the embedded copies
+    let embedded = ShaderFile {
+        label: "Embedded Include Test",
+        path: "/missing/scene.wgsl",
+        embedded: "#include \"common.wgsl\"\n@compute @workgroup_size(1) fn main() { helper(); }",
+        includes: &[("/missing/common.wgsl", "fn helper() {}")],
+    };
+    load_shader(&ctx, &embedded).expect("Failed to load shader");
+
+    let unlisted = ShaderFile {
+        includes: &[],
+        ..embedded
+    };
+    assert!(matches!(
+        load_shader(&ctx, &unlisted),
+        Err(Error::Shader { .. })
+    ));
+}
+
+struct Reloading;
+
+impl Example for Reloading {
+    fn init(_ctx: &GpuContext) -> Result<Self> {
+        Ok(Self)
+    }
+
+    fn render(&mut self, _ctx: &GpuContext, _view: &TextureView, _encoder: &mut CommandEncoder) {}
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        // Mapped for both reading and writing, which wgpu rejects
+        let _buffer = ctx.device.create_buffer(&BufferDescriptor {
+            label: Some("Invalid Buffer"),
+            size: 4,
+            usage: BufferUsages::MAP_READ | BufferUsages::MAP_WRITE,
+            mapped_at_creation: false,
+        });
+        Ok(())
+    }
+}
+
+#[test]
+fn reload_validation_errors_are_returned() {
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    let mut example = Reloading::try_init(&ctx).expect("Failed to init example");
+    let result = example.try_reload_shaders(&ctx);
+    assert!(matches!(result, Err(Error::Wgpu(_))), "{result:?}");
+}
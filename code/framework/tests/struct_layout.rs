@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use framework::Error;
+use framework::layout::check_struct_layouts;
+
+const RUST: &str = "
+#[derive(ShaderType)]
+struct LightUniforms {
+    light_dir: Vec3,
+    ambient: f32,
+    color: Vec3,
+}
+
+#[derive(ShaderType)]
+struct Swapped {
+    ambient: f32,
+    light_dir: Vec3,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    model: [[f32; 4]; 4],
+    tint: [f32; 3],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Unordered {
+    model: [[f32; 4]; 4],
+    tint: [f32; 3],
+}
+";
+
+const WGSL: &str = "
+struct LightUniforms {
+    light_dir: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+}
+
+struct Instance {
+    model: mat4x4<f32>,
+    tint: vec3<f32>,
+}
+
+@group(0) @binding(0) var<uniform> light: LightUniforms;
+@group(0) @binding(1) var<storage> instances: array<Instance>;
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(light.color * light.ambient + instances[0].tint, 1.0);
+}
+";
+
+fn chapter(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("framework-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).expect("Failed to create chapter");
+    std::fs::write(dir.join("src/main.rs"), RUST).expect("Failed to write main.rs");
+    std::fs::write(dir.join("src/shader.wgsl"), WGSL).expect("Failed to write shader");
+    dir
+}
+
+#[test]
+fn matching_structs_pass() {
+    let dir = chapter("layout-match");
+    check_struct_layouts(
+        &dir.to_string_lossy(),
+        &[("LightUniforms", "shader.wgsl", "LightUniforms")],
+    )
+    .unwrap_or_else(|err| panic!("{err}"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn mismatches_are_reported_per_field() {
+    let dir = chapter("layout-mismatch");
+    let result = check_struct_layouts(
+        &dir.to_string_lossy(),
+        &[
+            ("Swapped", "shader.wgsl", "LightUniforms"),
+            // Rust packs `[f32; 3]` right after the matrix, WGSL pads the struct to 80 bytes
+            ("Instance", "shader.wgsl", "Instance"),
+        ],
+    );
+    let _ = std::fs::remove_dir_all(dir);
+
+    let Err(Error::Layout { diff, .. }) = result else {
+        panic!("mismatch was not reported");
+    };
+    assert!(diff.contains("> ambient @0 size 4"), "{diff}");
+    assert!(diff.contains("size 76 align 4"), "{diff}");
+    assert!(diff.contains("size 80 align 16"), "{diff}");
+}
+
+#[test]
+fn pod_structs_need_repr_c() {
+    let dir = chapter("layout-repr");
+    let result = check_struct_layouts(
+        &dir.to_string_lossy(),
+        &[("Unordered", "shader.wgsl", "Instance")],
+    );
+    let _ = std::fs::remove_dir_all(dir);
+
+    let Err(Error::Layout { diff, .. }) = result else {
+        panic!("missing #[repr(C)] was not reported");
+    };
+    assert!(diff.contains("without #[repr(C)]"), "{diff}");
+}
@@ -0,0 +1,553 @@
+use std::path::{Path, PathBuf};
+
+use framework::texture::{
+    CompressedImage, TextureImage, create_texture_with_mips, load_compressed_image,
+    load_compressed_texture, load_image, mip_level_count, read_texture,
+};
+use framework::{Error, GpuContext, GpuContextBuilder, generate_checkerboard};
+use half::f16;
+use wgpu::util::{DeviceExt, TextureDataOrder};
+use wgpu::{
+    AstcBlock, AstcChannel, Backends, BindGroupDescriptor, BindGroupEntry, BindingResource, Color,
+    CommandEncoderDescriptor, Extent3d, Features, FragmentState, LoadOp, MultisampleState,
+    Operations, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, StoreOp, TexelCopyTextureInfo,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureViewDescriptor, VertexState,
+};
+use winit::dpi::PhysicalSize;
+
+fn asset(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/assets")
+        .join(name)
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("framework-texture-{}-{name}", std::process::id()))
+}
+
+fn max_difference(image: &TextureImage, expected: impl Fn(u32, u32) -> [u8; 3]) -> u8 {
+    let mut max = 0;
+    for (i, pixel) in image.data.chunks_exact(4).enumerate() {
+        let (x, y) = (i as u32 % image.width, i as u32 / image.width);
+        for (actual, expected) in pixel.iter().zip(expected(x, y)) {
+            max = max.max(actual.abs_diff(expected));
+        }
+        assert_eq!(pixel[3], 255);
+    }
+    max
+}
+
+#[test]
+fn jpeg_decodes_subsampled_color_with_restarts() {
+    // 4:2:0 YCbCr, quality 95, a restart marker every 2 MCUs and a size that isn't a whole
+    // number of MCUs
+    let image = load_image(asset("gradient.jpg"), true).unwrap();
+    assert_eq!((image.width, image.height), (45, 30));
+    assert_eq!(image.format, TextureFormat::Rgba8UnormSrgb);
+    let difference = max_difference(&image, |x, y| {
+        [
+            (40 + x * 4).min(255) as u8,
+            (20 + y * 7) as u8,
+            (200 - x * 2) as u8,
+        ]
+    });
+    assert!(difference <= 12, "{difference}");
+}
+
+#[test]
+fn jpeg_decodes_grayscale() {
+    let image = load_image(asset("gray.jpg"), false).unwrap();
+    assert_eq!((image.width, image.height), (20, 12));
+    assert_eq!(image.format, TextureFormat::Rgba8Unorm);
+    let difference = max_difference(&image, |x, y| [(x * 10 + y * 5) as u8; 3]);
+    assert!(difference <= 2, "{difference}");
+}
+
+#[test]
+fn png_loads_as_srgb_or_linear() {
+    let path = temp_path("checker.png");
+    let pixels = generate_checkerboard(8, 4, [255, 0, 0, 255], [0, 0, 255, 128]);
+    let file = std::fs::File::create(&path).unwrap();
+    let mut encoder = png::Encoder::new(file, 8, 8);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+
+    let srgb = load_image(&path, true).unwrap();
+    assert_eq!(srgb.format, TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(srgb.data, pixels);
+    assert_eq!(
+        load_image(&path, false).unwrap().format,
+        TextureFormat::Rgba8Unorm
+    );
+}
+
+#[test]
+fn hdr_decodes_flat_and_run_length_scanlines() {
+    let mut file = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n-Y 2 +X 8\n".to_vec();
+    // Flat scanline: 1.0, 0.5, 4.0 and black, then [1, 1, 1, 6] repeats the last pixel
+    file.extend([128, 64, 32, 129, 0, 0, 0, 0, 1, 1, 1, 6]);
+    // Run-length scanline: red is a run of 8, green 8 literals, blue a run, exponent a run
+    file.extend([2, 2, 0, 8, 136, 128]);
+    file.push(8);
+    file.extend([0, 16, 32, 48, 64, 80, 96, 112]);
+    file.extend([136, 0, 136, 131]);
+    let path = temp_path("sky.hdr");
+    std::fs::write(&path, file).unwrap();
+
+    let image = load_image(&path, true).unwrap();
+    assert_eq!((image.width, image.height), (8, 2));
+    assert_eq!(image.format, TextureFormat::Rgba16Float);
+    let texels: Vec<f32> = image
+        .data
+        .chunks_exact(2)
+        .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+        .collect();
+    assert_eq!(texels[..8], [1.0, 0.5, 0.25, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(texels[28..32], [0.0, 0.0, 0.0, 1.0]);
+    let second_row = &texels[32..];
+    assert_eq!(second_row[..4], [4.0, 0.0, 0.0, 1.0]);
+    assert_eq!(second_row[28..32], [4.0, 3.5, 0.0, 1.0]);
+}
+
+#[test]
+fn jpeg_decodes_progressive() {
+    // An 8x8 grayscale progressive JPEG with only the DC scan: every coefficient is zero, which
+    // decodes to mid gray
+    let mut file = vec![0xFF, 0xD8, 0xFF, 0xDB, 0x00, 0x43, 0x00];
+    file.extend([1; 64]);
+    file.extend([0xFF, 0xC2, 0x00, 0x0B, 8, 0, 8, 0, 8, 1, 1, 0x11, 0]);
+    file.extend([0xFF, 0xC4, 0x00, 0x14, 0x00, 1]);
+    file.extend([0; 15]);
+    file.push(0);
+    file.extend([0xFF, 0xDA, 0x00, 0x08, 1, 1, 0x00, 0, 0, 0]);
+    file.extend([0x7F, 0xFF, 0xD9]);
+    let path = temp_path("progressive.jpg");
+    std::fs::write(&path, file).unwrap();
+
+    let image = load_image(&path, false).unwrap();
+    assert_eq!((image.width, image.height), (8, 8));
+    assert!(
+        image
+            .data
+            .chunks_exact(4)
+            .all(|p| p == [128, 128, 128, 255]),
+        "{:?}",
+        &image.data[..4]
+    );
+}
+
+#[test]
+fn unsupported_images_are_errors() {
+    let path = temp_path("truncated.jpg");
+    std::fs::write(&path, [0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x02, 0xFF, 0xD9]).unwrap();
+    assert!(matches!(load_image(&path, true), Err(Error::Image { .. })));
+
+    let path = temp_path("image.bmp");
+    std::fs::write(&path, b"BM\x00\x00").unwrap();
+    assert!(matches!(load_image(&path, true), Err(Error::Image { .. })));
+}
+
+#[test]
+fn mip_chain_is_generated_on_gpu() {
+    assert_eq!(mip_level_count(256, 256), 9);
+    assert_eq!(mip_level_count(300, 20), 9);
+    assert_eq!(mip_level_count(1, 1), 1);
+
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    let image = TextureImage {
+        width: 4,
+        height: 4,
+        format: TextureFormat::Rgba8Unorm,
+        data: generate_checkerboard(4, 1, [255, 255, 255, 255], [0, 0, 0, 255]),
+    };
+    let texture = create_texture_with_mips(&ctx, "Checker", &image);
+    assert_eq!(texture.mip_level_count(), 3);
+
+    // The last level is a single texel, the average of the whole checkerboard
+    let last = ctx.device.create_texture(&TextureDescriptor {
+        label: Some("Last Mip"),
+        size: Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_texture(
+        TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 2,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        last.as_image_copy(),
+        last.size(),
+    );
+    ctx.queue.submit([encoder.finish()]);
+
+    let pixel = read_texture(&ctx, &last).expect("Failed to read texture");
+    assert!(
+        pixel[..3].iter().all(|&c| c.abs_diff(128) <= 1),
+        "{pixel:?}"
+    );
+    assert_eq!(pixel[3], 255);
+}
+
+fn ktx2_file(vk_format: u32, width: u32, height: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = b"\xABKTX 20\xBB\r\n\x1A\n".to_vec();
+    for value in [vk_format, 1, width, height, 0, 0, 1, levels.len() as u32, 0] {
+        file.extend(value.to_le_bytes());
+    }
+    // Empty data format descriptor, key/value and supercompression sections
+    file.extend([0u8; 32]);
+    let mut offset = file.len() + levels.len() * 24;
+    for level in levels {
+        for value in [offset, level.len(), level.len()] {
+            file.extend((value as u64).to_le_bytes());
+        }
+        offset += level.len();
+    }
+    for level in levels {
+        file.extend(level);
+    }
+    file
+}
+
+fn dds_file(
+    four_cc: &[u8; 4],
+    dxgi_format: u32,
+    width: u32,
+    height: u32,
+    levels: &[Vec<u8>],
+) -> Vec<u8> {
+    let mut header = [0u32; 31];
+    header[0] = 124;
+    header[2] = height;
+    header[3] = width;
+    header[6] = levels.len() as u32;
+    header[19] = 32;
+    header[20] = u32::from_le_bytes(*four_cc);
+    let mut file = b"DDS ".to_vec();
+    file.extend(header.iter().flat_map(|v| v.to_le_bytes()));
+    if four_cc == b"DX10" {
+        for value in [dxgi_format, 3, 0, 1, 0] {
+            file.extend(value.to_le_bytes());
+        }
+    }
+    for level in levels {
+        file.extend(level);
+    }
+    file
+}
+
+fn random_bytes(seed: u64, count: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 32) as u8
+        })
+        .collect()
+}
+
+// Lets the GPU decode the blocks by copying every texel into an RGBA8 target
+fn gpu_decode(ctx: &GpuContext, image: &CompressedImage) -> Vec<u8> {
+    let source = ctx.device.create_texture_with_data(
+        &ctx.queue,
+        &TextureDescriptor {
+            label: Some("Compressed"),
+            size: Extent3d {
+                width: image.width,
+                height: image.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: image.format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &image.levels[0],
+    );
+    let target = ctx.device.create_texture(&TextureDescriptor {
+        label: Some("Decoded"),
+        size: source.size(),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+
+    let module = ctx.device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("Decode Shader"),
+        source: ShaderSource::Wgsl(
+            "@group(0) @binding(0) var source: texture_2d<f32>;
+
+            @vertex
+            fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+                let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+                return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+            }
+
+            @fragment
+            fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+                return textureLoad(source, vec2u(position.xy), 0);
+            }"
+            .into(),
+        ),
+    });
+    let pipeline = ctx
+        .device
+        .create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Decode Pipeline"),
+            layout: None,
+            vertex: VertexState {
+                module: &module,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(TextureFormat::Rgba8Unorm.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+    let source_view = source.create_view(&TextureViewDescriptor::default());
+    let bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Decode Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(&source_view),
+        }],
+    });
+
+    let target_view = target.create_view(&TextureViewDescriptor::default());
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&CommandEncoderDescriptor { label: None });
+    {
+        let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Decode Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &target_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+    ctx.queue.submit([encoder.finish()]);
+    read_texture(ctx, &target).expect("Failed to read texture")
+}
+
+#[test]
+fn bc1_block_decodes_on_cpu() {
+    // Red and blue endpoints, the first four pixels use each palette entry in turn
+    let block = vec![0x00, 0xF8, 0x1F, 0x00, 0b1110_0100, 0, 0, 0];
+    let path = temp_path("red-blue.dds");
+    std::fs::write(&path, dds_file(b"DXT1", 0, 4, 4, &[block])).unwrap();
+
+    let image = load_compressed_image(&path).unwrap();
+    assert_eq!(image.format, TextureFormat::Bc1RgbaUnorm);
+    let levels = image.decompress().unwrap();
+    assert_eq!(levels[0].format, TextureFormat::Rgba8Unorm);
+    assert_eq!(
+        levels[0].data[..16],
+        [
+            255, 0, 0, 255, 0, 0, 255, 255, 170, 0, 85, 255, 85, 0, 170, 255
+        ]
+    );
+    assert_eq!(levels[0].data[16..20], [255, 0, 0, 255]);
+}
+
+#[test]
+fn cpu_decoders_match_the_gpu() {
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    let formats = [
+        (TextureFormat::Bc1RgbaUnorm, 8),
+        (TextureFormat::Bc2RgbaUnorm, 16),
+        (TextureFormat::Bc3RgbaUnorm, 16),
+        (TextureFormat::Bc4RUnorm, 8),
+        (TextureFormat::Bc5RgUnorm, 16),
+        (TextureFormat::Etc2Rgb8Unorm, 8),
+        (TextureFormat::Etc2Rgb8A1Unorm, 8),
+        (TextureFormat::Etc2Rgba8Unorm, 16),
+        (TextureFormat::EacR11Unorm, 8),
+        (TextureFormat::EacRg11Unorm, 16),
+    ];
+    for (i, (format, block_size)) in formats.into_iter().enumerate() {
+        if !ctx.device.features().contains(format.required_features()) {
+            println!("Skipping {format:?}, the adapter doesn't support it");
+            continue;
+        }
+        // 16x16 texels of random blocks hit every mode of every format
+        let image = CompressedImage {
+            width: 16,
+            height: 16,
+            format,
+            levels: vec![random_bytes(i as u64 + 1, 16 * block_size)],
+        };
+        let expected = gpu_decode(&ctx, &image);
+        let actual = &image.decompress().unwrap()[0].data;
+        let difference = actual
+            .iter()
+            .zip(&expected)
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap();
+        assert!(difference <= 1, "{format:?} differs by {difference}");
+    }
+}
+
+#[test]
+fn ktx2_and_dds_keep_every_mip_level() {
+    // BC7 8x8: 4 blocks, then a single (partially used) block per level
+    let levels = vec![vec![1; 64], vec![2; 16], vec![3; 16], vec![4; 16]];
+    let path = temp_path("bc7.ktx2");
+    std::fs::write(&path, ktx2_file(146, 8, 8, &levels)).unwrap();
+    let image = load_compressed_image(&path).unwrap();
+    assert_eq!((image.width, image.height), (8, 8));
+    assert_eq!(image.format, TextureFormat::Bc7RgbaUnormSrgb);
+    assert_eq!(image.levels, levels);
+    // No CPU decoder for BC7
+    assert!(image.decompress().is_none());
+
+    let path = temp_path("bc7.dds");
+    std::fs::write(&path, dds_file(b"DX10", 98, 8, 8, &levels)).unwrap();
+    let image = load_compressed_image(&path).unwrap();
+    assert_eq!(image.format, TextureFormat::Bc7RgbaUnorm);
+    assert_eq!(image.levels, levels);
+
+    let path = temp_path("astc.ktx2");
+    std::fs::write(&path, ktx2_file(173, 10, 5, &[vec![0; 16]])).unwrap();
+    assert_eq!(
+        load_compressed_image(&path).unwrap().format,
+        TextureFormat::Astc {
+            block: AstcBlock::B10x5,
+            channel: AstcChannel::Unorm,
+        }
+    );
+}
+
+#[test]
+fn broken_containers_are_image_errors() {
+    let mut file = ktx2_file(145, 4, 4, &[vec![0; 16]]);
+    // Basis Universal supercompression
+    file[44] = 1;
+    let path = temp_path("basis.ktx2");
+    std::fs::write(&path, file).unwrap();
+    let err = load_compressed_image(&path).err().unwrap();
+    assert!(
+        matches!(&err, Error::Image { message, .. } if message.contains("supercompression")),
+        "{err}"
+    );
+
+    // Level offsets and counts near the integer limits
+    let mut file = ktx2_file(145, 4, 4, &[vec![0; 16]]);
+    file[80..88].copy_from_slice(&u64::MAX.to_le_bytes());
+    let path = temp_path("offset.ktx2");
+    std::fs::write(&path, file).unwrap();
+    let err = load_compressed_image(&path).err().unwrap();
+    assert!(
+        matches!(&err, Error::Image { message, .. } if message.contains("past the end")),
+        "{err}"
+    );
+    let mut file = dds_file(b"DXT5", 0, 8, 8, &[vec![0; 64]]);
+    file[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+    let path = temp_path("levels.dds");
+    std::fs::write(&path, file).unwrap();
+    let err = load_compressed_image(&path).err().unwrap();
+    assert!(
+        matches!(&err, Error::Image { message, .. } if message.contains("mip levels")),
+        "{err}"
+    );
+
+    let path = temp_path("truncated.dds");
+    std::fs::write(&path, dds_file(b"DXT5", 0, 8, 8, &[vec![0; 40]])).unwrap();
+    assert!(matches!(
+        load_compressed_image(&path),
+        Err(Error::Image { .. })
+    ));
+}
+
+#[test]
+fn compressed_textures_fall_back_to_rgba8() {
+    // Two BC1 levels: 8x8 and 4x4
+    let levels = vec![random_bytes(7, 32), random_bytes(8, 8)];
+    let path = temp_path("bc1.ktx2");
+    std::fs::write(&path, ktx2_file(134, 8, 8, &levels)).unwrap();
+
+    let ctx = GpuContext::new_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    if ctx
+        .device
+        .features()
+        .contains(Features::TEXTURE_COMPRESSION_BC)
+    {
+        let loaded = load_compressed_texture(&ctx, &path).unwrap();
+        assert!(!loaded.decompressed);
+        assert_eq!(loaded.texture.format(), TextureFormat::Bc1RgbaUnormSrgb);
+        assert_eq!((loaded.size, loaded.rgba8_size), (40, 320));
+        assert_eq!(loaded.saved_bytes(), 280);
+    }
+
+    let ctx = GpuContextBuilder::new()
+        .backends(Backends::PRIMARY | Backends::GL)
+        .with_env()
+        .features(Features::empty())
+        .build_headless(PhysicalSize::new(4, 4), TextureFormat::Rgba8UnormSrgb);
+    let loaded = load_compressed_texture(&ctx, &path).unwrap();
+    assert!(loaded.decompressed);
+    assert_eq!(loaded.texture.format(), TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(loaded.texture.mip_level_count(), 2);
+    assert_eq!(loaded.saved_bytes(), 0);
+
+    // ASTC, BC6H and BC7 have no CPU decoder
+    for (vk_format, name) in [(157, "Astc"), (143, "Bc6hRgbUfloat"), (145, "Bc7RgbaUnorm")] {
+        let path = temp_path(&format!("{name}.ktx2"));
+        std::fs::write(&path, ktx2_file(vk_format, 4, 4, &[vec![0; 16]])).unwrap();
+        let err = load_compressed_texture(&ctx, &path).err().unwrap();
+        assert!(
+            matches!(&err, Error::Image { message, .. }
+                if message.starts_with(name) && message.contains("no CPU fallback")),
+            "{err}"
+        );
+    }
+}
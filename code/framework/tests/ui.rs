@@ -0,0 +1,43 @@
+use framework::overlay::LINE_HEIGHT;
+use framework::ui::UiState;
+use framework::{GpuContext, Input, Ui};
+use wgpu::TextureFormat;
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn widgets_with_the_same_label_keep_their_own_state() {
+    let ctx = GpuContext::new_headless(PhysicalSize::new(400, 300), TextureFormat::Rgba8UnormSrgb);
+    let mut state = UiState::default();
+    let mut input = Input::default();
+    let (mut first, mut second) = (0.0, 0.0);
+    // The panel is 320 wide at the right edge, rows are LINE_HEIGHT apart plus some spacing
+    let left = 400.0 - 320.0 - 8.0;
+    let row = |index: f64| 8.0 + (index + 0.5) * (LINE_HEIGHT as f64 + 4.0);
+
+    // Grab the first slider in the middle, then drag over the second one
+    input.set_cursor_position(Some((left + 160.0, row(0.0))));
+    input.press_mouse(0);
+    for cursor in [(left + 160.0, row(0.0)), (left + 320.0, row(1.0))] {
+        input.set_cursor_position(Some(cursor));
+        let mut ui = Ui::new(&ctx, &input, &mut state);
+        ui.slider("Reset", &mut first, 0.0..=1.0);
+        ui.slider("Reset", &mut second, 0.0..=1.0);
+        input.clear_delta();
+    }
+    assert_eq!((first, second), (1.0, 0.0));
+
+    // The same label in separate scopes works too
+    input.release_mouse(0);
+    Ui::new(&ctx, &input, &mut state);
+    input.set_cursor_position(Some((left + 160.0, row(1.0))));
+    input.press_mouse(0);
+    let (mut a, mut b) = (0.0, 0.0);
+    let mut ui = Ui::new(&ctx, &input, &mut state);
+    ui.scope("a", |ui| {
+        ui.slider("Intensity", &mut a, 0.0..=1.0);
+    });
+    ui.scope("b", |ui| {
+        ui.slider("Intensity", &mut b, 0.0..=1.0);
+    });
+    assert_eq!((a, b), (0.0, 0.5));
+}
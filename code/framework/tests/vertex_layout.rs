@@ -0,0 +1,76 @@
+use std::mem::size_of;
+
+use framework::VertexLayout;
+use glam::{Mat3, Vec2};
+use wgpu::{VertexFormat, VertexStepMode};
+
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy, VertexLayout)]
+struct Vertex {
+    position: [f32; 3],
+    uv: Vec2,
+    #[vertex(format = Unorm8x4)]
+    color: [u8; 4],
+    #[vertex(skip)]
+    id: u32,
+    #[vertex(location = 5)]
+    weight: f32,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy, VertexLayout)]
+#[vertex(step_mode = Instance, location = 3)]
+struct InstanceData {
+    model: [[f32; 4]; 4],
+    normal_matrix: Mat3,
+    #[vertex(format = Float16x4, columns = 2)]
+    packed: [u16; 8],
+}
+
+fn summary<T: VertexLayout>() -> Vec<(u64, u32, VertexFormat)> {
+    T::ATTRIBUTES
+        .iter()
+        .map(|attr| (attr.offset, attr.shader_location, attr.format))
+        .collect()
+}
+
+#[test]
+fn vertex_fields_map_to_attributes() {
+    assert_eq!(
+        summary::<Vertex>(),
+        [
+            (0, 0, VertexFormat::Float32x3),
+            (12, 1, VertexFormat::Float32x2),
+            (20, 2, VertexFormat::Unorm8x4),
+            (28, 5, VertexFormat::Float32),
+        ]
+    );
+
+    let desc = Vertex::desc();
+    assert_eq!(desc.array_stride, size_of::<Vertex>() as u64);
+    assert_eq!(desc.step_mode, VertexStepMode::Vertex);
+}
+
+#[test]
+fn matrices_take_one_location_per_column() {
+    assert_eq!(
+        summary::<InstanceData>(),
+        [
+            (0, 3, VertexFormat::Float32x4),
+            (16, 4, VertexFormat::Float32x4),
+            (32, 5, VertexFormat::Float32x4),
+            (48, 6, VertexFormat::Float32x4),
+            (64, 7, VertexFormat::Float32x3),
+            (76, 8, VertexFormat::Float32x3),
+            (88, 9, VertexFormat::Float32x3),
+            (100, 10, VertexFormat::Float16x4),
+            (108, 11, VertexFormat::Float16x4),
+        ]
+    );
+
+    let desc = InstanceData::desc();
+    assert_eq!(desc.array_stride, 116);
+    assert_eq!(desc.step_mode, VertexStepMode::Instance);
+}
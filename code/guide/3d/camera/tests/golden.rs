@@ -0,0 +1,13 @@
+#[allow(dead_code)]
+#[path = "../src/main.rs"]
+mod chapter;
+
+use framework::golden::{GoldenConfig, assert_golden};
+
+#[test]
+fn matches_golden_image() {
+    assert_golden::<chapter::CameraDemo>(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden.png"),
+        &GoldenConfig::default(),
+    );
+}
@@ -1,7 +1,5 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::f32::consts::FRAC_PI_4;
-use std::mem::size_of;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
@@ -11,54 +9,30 @@ use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendState, Buffer,
-    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
-    Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode,
-    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
-    PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
-    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderStages, StencilState,
-    StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d, Face, FilterMode,
+    FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode,
+    PrimitiveState, PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, StencilState, StoreOp, TexelCopyBufferLayout, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
 use framework::{
-    CUBE_INDICES, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input,
-    create_depth_texture, generate_checkerboard, run,
+    CUBE_INDICES, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input, Result,
+    VertexLayout, create_depth_texture, generate_checkerboard, load_shader, run, shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     uv: [f32; 2],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 2] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -71,44 +45,12 @@ const TEX_SIZE: u32 = 256;
 const CELL_SIZE: u32 = 32;
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+#[vertex(step_mode = Instance, location = 2)]
 struct InstanceData {
     model: [[f32; 4]; 4],
 }
 
-impl InstanceData {
-    const ATTRIBUTES: [VertexAttribute; 4] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 2,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 4]>() as BufferAddress,
-            shader_location: 3,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 8]>() as BufferAddress,
-            shader_location: 4,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 12]>() as BufferAddress,
-            shader_location: 5,
-            format: VertexFormat::Float32x4,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<InstanceData>() as BufferAddress,
-            step_mode: VertexStepMode::Instance,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 #[derive(ShaderType)]
 struct ShaderUniforms {
     view_proj: Mat4,
@@ -146,8 +88,9 @@ fn generate_instances() -> Vec<InstanceData> {
     instances
 }
 
-struct InstancingDemo {
+pub(crate) struct InstancingDemo {
     pipeline: RenderPipeline,
+    pipeline_layout: PipelineLayout,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     instance_buffer: Buffer,
@@ -158,12 +101,61 @@ struct InstancingDemo {
     camera: Camera,
 }
 
-impl Example for InstancingDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader_module = ctx
+impl InstancingDemo {
+    fn create_pipeline(ctx: &GpuContext, layout: &PipelineLayout) -> Result<RenderPipeline> {
+        let shader_module = load_shader(ctx, &shader!("shader.wgsl"))?;
+
+        Ok(ctx
             .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            }))
+    }
+}
 
+impl Example for InstancingDemo {
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let vertices = cube_vertices();
         let vertex_buffer = ctx
             .device
@@ -306,59 +298,15 @@ impl Example for InstancingDemo {
                 immediate_size: 0,
             });
 
-        let pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader_module,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &shader_module,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let pipeline = Self::create_pipeline(ctx, &pipeline_layout)?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
 
         let camera = Camera::new(Vec3::new(0.0, 2.0, 8.0), 0.0, -0.2);
 
-        Self {
+        Ok(Self {
             pipeline,
+            pipeline_layout,
             vertex_buffer,
             index_buffer,
             instance_buffer,
@@ -367,7 +315,7 @@ impl Example for InstancingDemo {
             depth_texture,
             depth_texture_view,
             camera,
-        }
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -382,10 +330,7 @@ impl Example for InstancingDemo {
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection =
-            glam::camera::rh::proj::directx::perspective(FRAC_PI_4, aspect, 0.1, 100.0);
-        let view_mat = self.camera.view_matrix();
-        let view_proj = projection * view_mat;
+        let view_proj = self.camera.view_proj(aspect);
 
         {
             let mut uniform_data = encase::UniformBuffer::new(Vec::new());
@@ -427,6 +372,11 @@ impl Example for InstancingDemo {
         rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.draw_indexed(0..36, 0, 0..NUM_INSTANCES as u32);
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx, &self.pipeline_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
@@ -1,8 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::f32::consts::FRAC_PI_4;
-use std::mem::size_of;
-use std::time::Instant;
+use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
 use encase::ShaderType;
@@ -10,47 +9,23 @@ use glam::Mat4;
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, BlendComponent, BlendState, Buffer, BufferAddress,
-    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
-    CommandEncoder, Face, FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState,
-    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
-    PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, ShaderStages, StoreOp, TextureView, VertexAttribute,
-    VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    BindGroupLayoutEntry, BindingType, BlendComponent, BlendState, Buffer, BufferBindingType,
+    BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder, Face,
+    FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode,
+    PrimitiveState, PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, ShaderStages, StoreOp, TextureView, VertexState,
 };
 
-use framework::{Example, GpuContext, run};
+use framework::{Example, GpuContext, Input, Result, VertexLayout, load_shader, run, shader};
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     color: [f32; 3],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 2] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: [-0.5, -0.5, 0.5],
@@ -96,21 +71,65 @@ struct ShaderUniforms {
     mvp: Mat4,
 }
 
-struct RotatingCube {
+pub(crate) struct RotatingCube {
     pipeline: RenderPipeline,
+    pipeline_layout: PipelineLayout,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     uniform_buffers: [Buffer; 3],
     bind_groups: [BindGroup; 3],
-    start_time: Instant,
+    time: f32,
 }
 
-impl Example for RotatingCube {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader_module = ctx
+impl RotatingCube {
+    fn create_pipeline(ctx: &GpuContext, layout: &PipelineLayout) -> Result<RenderPipeline> {
+        let shader_module = load_shader(ctx, &shader!("shader.wgsl"))?;
+
+        Ok(ctx
             .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            }))
+    }
+}
 
+impl Example for RotatingCube {
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let vertex_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -173,59 +192,25 @@ impl Example for RotatingCube {
                 immediate_size: 0,
             });
 
-        let pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader_module,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &shader_module,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let pipeline = Self::create_pipeline(ctx, &pipeline_layout)?;
 
-        Self {
+        Ok(Self {
             pipeline,
+            pipeline_layout,
             vertex_buffer,
             index_buffer,
             uniform_buffers,
             bind_groups,
-            start_time: Instant::now(),
-        }
+            time: 0.0,
+        })
+    }
+
+    fn update(&mut self, _ctx: &GpuContext, dt: Duration, _input: &Input) {
+        self.time += dt.as_secs_f32();
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
-        let time = self.start_time.elapsed().as_secs_f32();
+        let time = self.time;
 
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
         let projection =
@@ -280,6 +265,11 @@ impl Example for RotatingCube {
             rpass.draw_indexed(0..36, 0, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx, &self.pipeline_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
@@ -1,3 +1,5 @@
+#include <color.wgsl>
+
 struct Params {
     threshold: f32,
 }
@@ -18,7 +20,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         return;
     }
     let color = textureLoad(input_tex, vec2<i32>(id.xy), 0);
-    let brightness = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let brightness = luminance(color.rgb);
     if (brightness > params.threshold) {
         textureStore(output_tex, vec2<i32>(id.xy), color);
     } else {
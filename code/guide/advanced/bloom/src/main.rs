@@ -1,69 +1,44 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::mem::size_of;
+use std::f32::consts::FRAC_PI_4;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
 use encase::ShaderType;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 use wgpu::util::DeviceExt;
 use wgpu::{
-    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
-    BlendState, Buffer, BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color,
-    ColorTargetState, ColorWrites, CommandEncoder, CompareFunction, ComputePassDescriptor,
-    ComputePipeline, ComputePipelineDescriptor, DepthStencilState, Device, Extent3d, Face,
-    FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode, MultisampleState,
-    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
-    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
-    SamplerDescriptor, ShaderStages, StencilState, StorageTextureAccess, StoreOp,
-    TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendState, Buffer,
+    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    DepthStencilState, Extent3d, Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp,
+    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, StencilState, StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
+use framework::geometry;
+use framework::texture::{TextureImage, create_sampler, create_texture_with_mips};
 use framework::{
-    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input,
-    create_depth_texture, generate_checkerboard, run,
+    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, DepthMode, Example, GpuContext,
+    Indices, Input, Projection, Result, ShaderLayout, Ui, VertexLayout, create_depth_texture,
+    generate_checkerboard, load_shader, load_shader_with_layout, run, shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
     uv: [f32; 2],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 3] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 6]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -77,29 +52,22 @@ fn cube_vertices() -> Vec<Vertex> {
         .collect()
 }
 
-const FLOOR_VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [-5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 5.0],
-    },
-    Vertex {
-        position: [-5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 5.0],
-    },
-];
-const FLOOR_INDICES: &[u16] = &[0, 2, 1, 0, 3, 2];
+// 10x10 floor under the cubes with the texture repeated 5 times
+fn floor() -> (Vec<Vertex>, Indices) {
+    let mesh = geometry::plane(10.0, 10.0)
+        .transformed(Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)))
+        .with_uv_scale(Vec2::splat(5.0));
+    let vertices = mesh
+        .vertices
+        .iter()
+        .map(|v| Vertex {
+            position: v.position,
+            normal: v.normal,
+            uv: v.uv,
+        })
+        .collect();
+    (vertices, mesh.indices)
+}
 
 #[derive(ShaderType)]
 struct CameraUniforms {
@@ -123,14 +91,16 @@ struct BlurParams {
     direction: glam::Vec2,
 }
 
+const BLOOM_THRESHOLD: f32 = 1.0;
 const TEX_SIZE: u32 = 256;
 const CELL_SIZE: u32 = 32;
 
-struct BloomDemo {
+pub(crate) struct BloomDemo {
     scene_pipeline: RenderPipeline,
     bright_pipeline: ComputePipeline,
     blur_pipeline: ComputePipeline,
     post_pipeline: RenderPipeline,
+    scene_layout: PipelineLayout,
     cube_vertex_buffer: Buffer,
     cube_index_buffer: Buffer,
     floor_vertex_buffer: Buffer,
@@ -153,12 +123,149 @@ struct BloomDemo {
     vblur_params_buffer: Buffer,
     post_bind_group: BindGroup,
     post_sampler: Sampler,
+    bright_layout: ShaderLayout,
+    blur_layout: ShaderLayout,
+    post_layout: ShaderLayout,
     depth_texture: Texture,
     depth_texture_view: TextureView,
     camera: Camera,
+    threshold: f32,
 }
 
 impl BloomDemo {
+    fn create_pipelines(
+        ctx: &GpuContext,
+        scene_layout: &PipelineLayout,
+        bright_layout: &ShaderLayout,
+        blur_layout: &ShaderLayout,
+        post_layout: &ShaderLayout,
+        depth_mode: DepthMode,
+    ) -> Result<(
+        RenderPipeline,
+        ComputePipeline,
+        ComputePipeline,
+        RenderPipeline,
+    )> {
+        let scene_shader = load_shader(ctx, &shader!("scene.wgsl"))?;
+        let bright_shader = load_shader(ctx, &shader!("bright.wgsl"))?;
+        let blur_shader = load_shader(ctx, &shader!("blur.wgsl"))?;
+        let post_shader = load_shader(ctx, &shader!("post.wgsl"))?;
+
+        let scene_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Scene Render Pipeline"),
+                layout: Some(scene_layout),
+                vertex: VertexState {
+                    module: &scene_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &scene_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: TextureFormat::Rgba16Float,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(depth_mode.compare()),
+                    stencil: StencilState::default(),
+                    bias: Default::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        let bright_pipeline = ctx
+            .device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Bright Compute Pipeline"),
+                layout: Some(&bright_layout.pipeline_layout),
+                module: &bright_shader,
+                entry_point: Some("main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let blur_pipeline = ctx
+            .device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Blur Compute Pipeline"),
+                layout: Some(&blur_layout.pipeline_layout),
+                module: &blur_shader,
+                entry_point: Some("main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let post_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Post Process Render Pipeline"),
+                layout: Some(&post_layout.pipeline_layout),
+                vertex: VertexState {
+                    module: &post_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &post_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        Ok((
+            scene_pipeline,
+            bright_pipeline,
+            blur_pipeline,
+            post_pipeline,
+        ))
+    }
+
     fn create_hdr_tex(
         ctx: &GpuContext,
         label: &str,
@@ -182,56 +289,14 @@ impl BloomDemo {
         let view = texture.create_view(&TextureViewDescriptor::default());
         (texture, view)
     }
-    fn create_compute_bgl(device: &Device, extra_buffer: bool) -> BindGroupLayout {
-        let mut entries = vec![
-            BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: false },
-                    view_dimension: TextureViewDimension::D2,
-                    multisampled: false,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                binding: 1,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::StorageTexture {
-                    access: StorageTextureAccess::WriteOnly,
-                    format: TextureFormat::Rgba16Float,
-                    view_dimension: TextureViewDimension::D2,
-                },
-                count: None,
-            },
-        ];
-        if extra_buffer {
-            entries.push(BindGroupLayoutEntry {
-                binding: 2,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            });
-        }
-        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Compute Bind Group Layout"),
-            entries: &entries,
-        })
-    }
 }
 
 impl Example for BloomDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let scene_shader = ctx.device.create_shader_module(include_wgsl!("scene.wgsl"));
-        let bright_shader = ctx
-            .device
-            .create_shader_module(include_wgsl!("bright.wgsl"));
-        let blur_shader = ctx.device.create_shader_module(include_wgsl!("blur.wgsl"));
-        let post_shader = ctx.device.create_shader_module(include_wgsl!("post.wgsl"));
+    fn init(ctx: &GpuContext) -> Result<Self> {
+        // Only the layouts are kept, reloading a shader rebuilds the pipelines with them
+        let (_, bright_layout) = load_shader_with_layout(ctx, &shader!("bright.wgsl"), &[])?;
+        let (_, blur_layout) = load_shader_with_layout(ctx, &shader!("blur.wgsl"), &[])?;
+        let (_, post_layout) = load_shader_with_layout(ctx, &shader!("post.wgsl"), &[])?;
 
         let cube_vertex_buffer = ctx
             .device
@@ -247,18 +312,19 @@ impl Example for BloomDemo {
                 contents: bytemuck::cast_slice(&CUBE_INDICES),
                 usage: BufferUsages::INDEX,
             });
+        let (floor_vertices, floor_indices) = floor();
         let floor_vertex_buffer =
             ctx.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Floor Vertex Buffer"),
-                    contents: bytemuck::cast_slice(FLOOR_VERTICES),
+                    contents: bytemuck::cast_slice(&floor_vertices),
                     usage: BufferUsages::VERTEX,
                 });
         let floor_index_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Floor Index Buffer"),
-                contents: bytemuck::cast_slice(FLOOR_INDICES),
+                contents: floor_indices.as_bytes(),
                 usage: BufferUsages::INDEX,
             });
 
@@ -298,48 +364,17 @@ impl Example for BloomDemo {
         );
         let cube_texture_view = cube_texture.create_view(&TextureViewDescriptor::default());
 
-        let floor_pixels =
-            generate_checkerboard(TEX_SIZE, CELL_SIZE, [60, 60, 60, 255], [30, 30, 30, 255]);
-        let floor_texture = ctx.device.create_texture(&TextureDescriptor {
-            label: Some("Floor Texture"),
-            size: Extent3d {
-                width: TEX_SIZE,
-                height: TEX_SIZE,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
+        // The floor is seen at grazing angles, so it gets a full mip chain to stop the shimmering
+        let floor_image = TextureImage {
+            width: TEX_SIZE,
+            height: TEX_SIZE,
             format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        ctx.queue.write_texture(
-            floor_texture.as_image_copy(),
-            &floor_pixels,
-            TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(TEX_SIZE * 4),
-                rows_per_image: Some(TEX_SIZE),
-            },
-            Extent3d {
-                width: TEX_SIZE,
-                height: TEX_SIZE,
-                depth_or_array_layers: 1,
-            },
-        );
+            data: generate_checkerboard(TEX_SIZE, CELL_SIZE, [60, 60, 60, 255], [30, 30, 30, 255]),
+        };
+        let floor_texture = create_texture_with_mips(ctx, "Floor Texture", &floor_image);
         let floor_texture_view = floor_texture.create_view(&TextureViewDescriptor::default());
 
-        let sampler = ctx.device.create_sampler(&SamplerDescriptor {
-            label: Some("Diffuse Sampler"),
-            address_mode_u: AddressMode::Repeat,
-            address_mode_v: AddressMode::Repeat,
-            address_mode_w: AddressMode::Repeat,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: MipmapFilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = create_sampler(ctx, AddressMode::Repeat, 16);
 
         let camera_uniform_buffer = ctx.device.create_buffer(&BufferDescriptor {
             label: Some("Camera Uniform Buffer"),
@@ -459,6 +494,14 @@ impl Example for BloomDemo {
             ],
         });
 
+        // Reverse-Z keeps depth precision at the far end of the floor
+        let mut camera = Camera::new(Vec3::new(0.0, 2.5, 5.0), 0.0, -0.3);
+        camera.projection = Projection::InfiniteReverse {
+            fov_y: FRAC_PI_4,
+            near: 0.1,
+        };
+        let depth_mode = camera.projection.depth_mode();
+
         let scene_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -466,52 +509,6 @@ impl Example for BloomDemo {
                 bind_group_layouts: &[Some(&camera_bgl), Some(&scene_bgl)],
                 immediate_size: 0,
             });
-        let scene_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Scene Render Pipeline"),
-                layout: Some(&scene_layout),
-                vertex: VertexState {
-                    module: &scene_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &scene_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: TextureFormat::Rgba16Float,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: Default::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
 
         // Textures
         let (scene_texture, scene_texture_view) = Self::create_hdr_tex(
@@ -531,7 +528,6 @@ impl Example for BloomDemo {
         );
 
         // Bright extraction
-        let bright_bgl = Self::create_compute_bgl(&ctx.device, true);
         let bright_params_buffer = ctx.device.create_buffer(&BufferDescriptor {
             label: Some("Bright Parameters Buffer"),
             size: BrightParams::min_size().into(),
@@ -540,49 +536,23 @@ impl Example for BloomDemo {
         });
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
-            data.write(&BrightParams { threshold: 1.0 })
-                .expect("Failed to write uniform buffer");
+            data.write(&BrightParams {
+                threshold: BLOOM_THRESHOLD,
+            })
+            .expect("Failed to write uniform buffer");
             ctx.queue
                 .write_buffer(&bright_params_buffer, 0, &data.into_inner());
         }
-        let bright_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Bright Bind Group"),
-            layout: &bright_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&scene_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: bright_params_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let bright_pipeline = ctx
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some("Bright Compute Pipeline"),
-                layout: Some(
-                    &ctx.device
-                        .create_pipeline_layout(&PipelineLayoutDescriptor {
-                            label: Some("Bright Pipeline Layout"),
-                            bind_group_layouts: &[Some(&bright_bgl)],
-                            immediate_size: 0,
-                        }),
-                ),
-                module: &bright_shader,
-                entry_point: Some("main"),
-                compilation_options: PipelineCompilationOptions::default(),
-                cache: None,
-            });
+        let bright_bind_group = bright_layout
+            .bind_group(0)
+            .label("Bright Bind Group")
+            .texture(0, &scene_texture_view)
+            .texture(1, &bright_texture_view)
+            .buffer(2, &bright_params_buffer)
+            .build(&ctx.device)
+            .expect("Failed to create bright bind group");
 
         // Blur
-        let blur_bgl = Self::create_compute_bgl(&ctx.device, true);
         let hblur_params_buffer = ctx.device.create_buffer(&BufferDescriptor {
             label: Some("Horizontal Blur Parameters Buffer"),
             size: BlurParams::min_size().into(),
@@ -604,24 +574,14 @@ impl Example for BloomDemo {
             ctx.queue
                 .write_buffer(&hblur_params_buffer, 0, &data.into_inner());
         }
-        let hblur_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Horizontal Blur Bind Group"),
-            layout: &blur_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&blur_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: hblur_params_buffer.as_entire_binding(),
-                },
-            ],
-        });
+        let hblur_bind_group = blur_layout
+            .bind_group(0)
+            .label("Horizontal Blur Bind Group")
+            .texture(0, &bright_texture_view)
+            .texture(1, &blur_texture_view)
+            .buffer(2, &hblur_params_buffer)
+            .build(&ctx.device)
+            .expect("Failed to create blur bind group");
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
             data.write(&BlurParams {
@@ -631,41 +591,14 @@ impl Example for BloomDemo {
             ctx.queue
                 .write_buffer(&vblur_params_buffer, 0, &data.into_inner());
         }
-        let vblur_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Vertical Blur Bind Group"),
-            layout: &blur_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&blur_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: vblur_params_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let blur_pipeline = ctx
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some("Blur Compute Pipeline"),
-                layout: Some(
-                    &ctx.device
-                        .create_pipeline_layout(&PipelineLayoutDescriptor {
-                            label: Some("Blur Pipeline Layout"),
-                            bind_group_layouts: &[Some(&blur_bgl)],
-                            immediate_size: 0,
-                        }),
-                ),
-                module: &blur_shader,
-                entry_point: Some("main"),
-                compilation_options: PipelineCompilationOptions::default(),
-                cache: None,
-            });
+        let vblur_bind_group = blur_layout
+            .bind_group(0)
+            .label("Vertical Blur Bind Group")
+            .texture(0, &blur_texture_view)
+            .texture(1, &bright_texture_view)
+            .buffer(2, &vblur_params_buffer)
+            .build(&ctx.device)
+            .expect("Failed to create blur bind group");
 
         // Post (scene + bloom → screen)
         let post_sampler = ctx.device.create_sampler(&SamplerDescriptor {
@@ -674,110 +607,33 @@ impl Example for BloomDemo {
             min_filter: FilterMode::Nearest,
             ..Default::default()
         });
-        let post_bgl = ctx
-            .device
-            .create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: Some("Post Process Bind Group Layout"),
-                entries: &[
-                    BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: ShaderStages::FRAGMENT,
-                        ty: BindingType::Texture {
-                            sample_type: TextureSampleType::Float { filterable: true },
-                            view_dimension: TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: ShaderStages::FRAGMENT,
-                        ty: BindingType::Texture {
-                            sample_type: TextureSampleType::Float { filterable: true },
-                            view_dimension: TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: ShaderStages::FRAGMENT,
-                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-            });
-        let post_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Post Process Bind Group"),
-            layout: &post_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&scene_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: BindingResource::Sampler(&post_sampler),
-                },
-            ],
-        });
-        let post_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Post Process Render Pipeline"),
-                layout: Some(
-                    &ctx.device
-                        .create_pipeline_layout(&PipelineLayoutDescriptor {
-                            label: Some("Post Process Pipeline Layout"),
-                            bind_group_layouts: &[Some(&post_bgl)],
-                            immediate_size: 0,
-                        }),
-                ),
-                vertex: VertexState {
-                    module: &post_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &post_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let post_bind_group = post_layout
+            .bind_group(0)
+            .label("Post Process Bind Group")
+            .texture(0, &scene_texture_view)
+            .texture(1, &bright_texture_view)
+            .sampler(2, &post_sampler)
+            .build(&ctx.device)
+            .expect("Failed to create post process bind group");
+
+        let (scene_pipeline, bright_pipeline, blur_pipeline, post_pipeline) =
+            Self::create_pipelines(
+                ctx,
+                &scene_layout,
+                &bright_layout,
+                &blur_layout,
+                &post_layout,
+                depth_mode,
+            )?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
-        let camera = Camera::new(Vec3::new(0.0, 2.5, 5.0), 0.0, -0.3);
 
-        Self {
+        Ok(Self {
             scene_pipeline,
             bright_pipeline,
             blur_pipeline,
             post_pipeline,
+            scene_layout,
             cube_vertex_buffer,
             cube_index_buffer,
             floor_vertex_buffer,
@@ -800,10 +656,14 @@ impl Example for BloomDemo {
             vblur_params_buffer,
             post_bind_group,
             post_sampler,
+            bright_layout,
+            blur_layout,
+            post_layout,
             depth_texture,
             depth_texture_view,
             camera,
-        }
+            threshold: BLOOM_THRESHOLD,
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -829,27 +689,16 @@ impl Example for BloomDemo {
         self.blur_texture = blur_texture;
         self.blur_texture_view = blur_texture_view;
 
-        let bright_bgl = self.bright_pipeline.get_bind_group_layout(0);
-        self.bright_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Bright Bind Group"),
-            layout: &bright_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&self.scene_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&self.bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: self.bright_params_buffer.as_entire_binding(),
-                },
-            ],
-        });
+        self.bright_bind_group = self
+            .bright_layout
+            .bind_group(0)
+            .label("Bright Bind Group")
+            .texture(0, &self.scene_texture_view)
+            .texture(1, &self.bright_texture_view)
+            .buffer(2, &self.bright_params_buffer)
+            .build(&ctx.device)
+            .expect("Failed to create bright bind group");
 
-        let blur_bgl = self.blur_pipeline.get_bind_group_layout(0);
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
             data.write(&BlurParams {
@@ -859,24 +708,15 @@ impl Example for BloomDemo {
             ctx.queue
                 .write_buffer(&self.hblur_params_buffer, 0, &data.into_inner());
         }
-        self.hblur_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Horizontal Blur Bind Group"),
-            layout: &blur_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&self.bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&self.blur_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: self.hblur_params_buffer.as_entire_binding(),
-                },
-            ],
-        });
+        self.hblur_bind_group = self
+            .blur_layout
+            .bind_group(0)
+            .label("Horizontal Blur Bind Group")
+            .texture(0, &self.bright_texture_view)
+            .texture(1, &self.blur_texture_view)
+            .buffer(2, &self.hblur_params_buffer)
+            .build(&ctx.device)
+            .expect("Failed to create blur bind group");
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
             data.write(&BlurParams {
@@ -886,70 +726,57 @@ impl Example for BloomDemo {
             ctx.queue
                 .write_buffer(&self.vblur_params_buffer, 0, &data.into_inner());
         }
-        self.vblur_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Vertical Blur Bind Group"),
-            layout: &blur_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&self.blur_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&self.bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: self.vblur_params_buffer.as_entire_binding(),
-                },
-            ],
-        });
-
-        let post_bgl = self.post_pipeline.get_bind_group_layout(0);
-        self.post_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Post Process Bind Group"),
-            layout: &post_bgl,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&self.scene_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&self.bright_texture_view),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: BindingResource::Sampler(&self.post_sampler),
-                },
-            ],
-        });
+        self.vblur_bind_group = self
+            .blur_layout
+            .bind_group(0)
+            .label("Vertical Blur Bind Group")
+            .texture(0, &self.blur_texture_view)
+            .texture(1, &self.bright_texture_view)
+            .buffer(2, &self.vblur_params_buffer)
+            .build(&ctx.device)
+            .expect("Failed to create blur bind group");
+
+        self.post_bind_group = self
+            .post_layout
+            .bind_group(0)
+            .label("Post Process Bind Group")
+            .texture(0, &self.scene_texture_view)
+            .texture(1, &self.bright_texture_view)
+            .sampler(2, &self.post_sampler)
+            .build(&ctx.device)
+            .expect("Failed to create post process bind group");
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
         self.depth_texture = depth_texture;
         self.depth_texture_view = depth_texture_view;
     }
 
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.slider("Threshold", &mut self.threshold, 0.0..=4.0);
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.camera.update(dt.as_secs_f32(), input);
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let view_proj = glam::camera::rh::proj::directx::perspective(
-            std::f32::consts::FRAC_PI_4,
-            aspect,
-            0.1,
-            100.0,
-        ) * self.camera.view_matrix();
+        let view_proj = self.camera.view_proj(aspect);
+        {
+            let mut data = encase::UniformBuffer::new(Vec::new());
+            data.write(&CameraUniforms { view_proj })
+                .expect("Failed to write uniform buffer");
+            ctx.queue
+                .write_buffer(&self.camera_uniform_buffer, 0, &data.into_inner());
+        }
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
-            data.write(&CameraUniforms {
-                view_proj: view_proj,
+            data.write(&BrightParams {
+                threshold: self.threshold,
             })
             .expect("Failed to write uniform buffer");
             ctx.queue
-                .write_buffer(&self.camera_uniform_buffer, 0, &data.into_inner());
+                .write_buffer(&self.bright_params_buffer, 0, &data.into_inner());
         }
 
         // 1. Scene → HDR
@@ -968,12 +795,12 @@ impl Example for BloomDemo {
                 depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                     view: &self.depth_texture_view,
                     depth_ops: Some(Operations {
-                        load: LoadOp::Clear(1.0),
+                        load: LoadOp::Clear(self.camera.projection.depth_mode().clear_value()),
                         store: StoreOp::Store,
                     }),
                     stencil_ops: None,
                 }),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Scene Render Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -989,14 +816,14 @@ impl Example for BloomDemo {
             render_pass.draw_indexed(0..6, 0, 0..1);
         }
 
-        let workgroup_x = (ctx.surface_config.width + 15) / 16;
-        let workgroup_y = (ctx.surface_config.height + 15) / 16;
+        let workgroup_x = ctx.surface_config.width.div_ceil(16);
+        let workgroup_y = ctx.surface_config.height.div_ceil(16);
 
         // 2. Bright extraction
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("Bright Extraction Pass"),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.compute_pass("Bright Extraction Pass"),
             });
             compute_pass.set_pipeline(&self.bright_pipeline);
             compute_pass.set_bind_group(0, &self.bright_bind_group, &[]);
@@ -1007,7 +834,7 @@ impl Example for BloomDemo {
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("Horizontal Blur Pass"),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.compute_pass("Horizontal Blur Pass"),
             });
             compute_pass.set_pipeline(&self.blur_pipeline);
             compute_pass.set_bind_group(0, &self.hblur_bind_group, &[]);
@@ -1018,7 +845,7 @@ impl Example for BloomDemo {
         {
             let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("Vertical Blur Pass"),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.compute_pass("Vertical Blur Pass"),
             });
             compute_pass.set_pipeline(&self.blur_pipeline);
             compute_pass.set_bind_group(0, &self.vblur_bind_group, &[]);
@@ -1039,7 +866,7 @@ impl Example for BloomDemo {
                     depth_slice: None,
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Composite Render Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -1048,6 +875,23 @@ impl Example for BloomDemo {
             render_pass.draw(0..6, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        (
+            self.scene_pipeline,
+            self.bright_pipeline,
+            self.blur_pipeline,
+            self.post_pipeline,
+        ) = Self::create_pipelines(
+            ctx,
+            &self.scene_layout,
+            &self.bright_layout,
+            &self.blur_layout,
+            &self.post_layout,
+            self.camera.projection.depth_mode(),
+        )?;
+        Ok(())
+    }
 }
 
 fn main() {
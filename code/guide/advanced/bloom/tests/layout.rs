@@ -0,0 +1,14 @@
+use framework::layout::assert_struct_layouts;
+
+#[test]
+fn uniform_structs_match_wgsl() {
+    assert_struct_layouts(
+        env!("CARGO_MANIFEST_DIR"),
+        &[
+            ("CameraUniforms", "scene.wgsl", "CameraUniforms"),
+            ("LightUniforms", "scene.wgsl", "LightUniforms"),
+            ("BrightParams", "bright.wgsl", "Params"),
+            ("BlurParams", "blur.wgsl", "Params"),
+        ],
+    );
+}
@@ -1,70 +1,43 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::mem::size_of;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
 use encase::ShaderType;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendState, Buffer,
-    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoder, CompareFunction, ComputePassDescriptor, ComputePipeline,
+    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, CompareFunction, ComputePassDescriptor, ComputePipeline,
     ComputePipelineDescriptor, DepthStencilState, Extent3d, Face, FilterMode, FragmentState,
     FrontFace, IndexFormat, LoadOp, MipmapFilterMode, MultisampleState, Operations,
-    PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
-    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode,
+    PrimitiveState, PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
     SamplerDescriptor, ShaderStages, StencilState, StorageTextureAccess, StoreOp,
     TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
     TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    VertexState,
 };
 use winit::dpi::PhysicalSize;
 
+use framework::geometry;
 use framework::{
-    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input,
-    create_depth_texture, generate_checkerboard, run,
+    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Indices,
+    Input, Result, VertexLayout, create_depth_texture, generate_checkerboard, load_shader, run,
+    shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
     uv: [f32; 2],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 3] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 6]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -78,30 +51,22 @@ fn cube_vertices() -> Vec<Vertex> {
         .collect()
 }
 
-const FLOOR_VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [-5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 5.0],
-    },
-    Vertex {
-        position: [-5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 5.0],
-    },
-];
-
-const FLOOR_INDICES: &[u16] = &[0, 2, 1, 0, 3, 2];
+// 10x10 floor under the cubes with the texture repeated 5 times
+fn floor() -> (Vec<Vertex>, Indices) {
+    let mesh = geometry::plane(10.0, 10.0)
+        .transformed(Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)))
+        .with_uv_scale(Vec2::splat(5.0));
+    let vertices = mesh
+        .vertices
+        .iter()
+        .map(|v| Vertex {
+            position: v.position,
+            normal: v.normal,
+            uv: v.uv,
+        })
+        .collect();
+    (vertices, mesh.indices)
+}
 
 #[derive(ShaderType)]
 struct CameraUniforms {
@@ -117,10 +82,13 @@ struct LightUniforms {
 const TEX_SIZE: u32 = 256;
 const CELL_SIZE: u32 = 32;
 
-struct ComputeDemo {
+pub(crate) struct ComputeDemo {
     scene_pipeline: RenderPipeline,
     compute_pipeline: ComputePipeline,
     post_pipeline: RenderPipeline,
+    scene_layout: PipelineLayout,
+    compute_layout: PipelineLayout,
+    post_layout: PipelineLayout,
     cube_vertex_buffer: Buffer,
     cube_index_buffer: Buffer,
     floor_vertex_buffer: Buffer,
@@ -142,6 +110,115 @@ struct ComputeDemo {
 }
 
 impl ComputeDemo {
+    fn create_pipelines(
+        ctx: &GpuContext,
+        scene_layout: &PipelineLayout,
+        compute_layout: &PipelineLayout,
+        post_layout: &PipelineLayout,
+    ) -> Result<(RenderPipeline, ComputePipeline, RenderPipeline)> {
+        let scene_shader = load_shader(ctx, &shader!("scene.wgsl"))?;
+        let blur_shader = load_shader(ctx, &shader!("blur.wgsl"))?;
+        let post_shader = load_shader(ctx, &shader!("post.wgsl"))?;
+
+        let scene_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Scene Pipeline"),
+                layout: Some(scene_layout),
+                vertex: VertexState {
+                    module: &scene_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &scene_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: TextureFormat::Rgba16Float,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: Default::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        let compute_pipeline = ctx
+            .device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Blur Pipeline"),
+                layout: Some(compute_layout),
+                module: &blur_shader,
+                entry_point: Some("main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let post_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Post Process Render Pipeline"),
+                layout: Some(post_layout),
+                vertex: VertexState {
+                    module: &post_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &post_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        Ok((scene_pipeline, compute_pipeline, post_pipeline))
+    }
+
     fn create_scene_texture(ctx: &GpuContext) -> (Texture, TextureView) {
         let size = &ctx.surface_config;
         let texture = ctx.device.create_texture(&TextureDescriptor {
@@ -184,11 +261,7 @@ impl ComputeDemo {
 }
 
 impl Example for ComputeDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let scene_shader = ctx.device.create_shader_module(include_wgsl!("scene.wgsl"));
-        let blur_shader = ctx.device.create_shader_module(include_wgsl!("blur.wgsl"));
-        let post_shader = ctx.device.create_shader_module(include_wgsl!("post.wgsl"));
-
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let cube_vertex_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -203,18 +276,19 @@ impl Example for ComputeDemo {
                 contents: bytemuck::cast_slice(&CUBE_INDICES),
                 usage: BufferUsages::INDEX,
             });
+        let (floor_vertices, floor_indices) = floor();
         let floor_vertex_buffer =
             ctx.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Floor Vertex Buffer"),
-                    contents: bytemuck::cast_slice(FLOOR_VERTICES),
+                    contents: bytemuck::cast_slice(&floor_vertices),
                     usage: BufferUsages::VERTEX,
                 });
         let floor_index_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Floor Index Buffer"),
-                contents: bytemuck::cast_slice(FLOOR_INDICES),
+                contents: floor_indices.as_bytes(),
                 usage: BufferUsages::INDEX,
             });
 
@@ -416,7 +490,7 @@ impl Example for ComputeDemo {
             ],
         });
 
-        // Scene pipeline
+        // Scene pipeline layout
         let scene_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -424,52 +498,6 @@ impl Example for ComputeDemo {
                 bind_group_layouts: &[Some(&camera_bgl), Some(&scene_bgl)],
                 immediate_size: 0,
             });
-        let scene_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Scene Pipeline"),
-                layout: Some(&scene_layout),
-                vertex: VertexState {
-                    module: &scene_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &scene_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: TextureFormat::Rgba16Float,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: Default::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
 
         // Compute bind group
         let (scene_texture, scene_texture_view) = Self::create_scene_texture(ctx);
@@ -524,16 +552,6 @@ impl Example for ComputeDemo {
                 bind_group_layouts: &[Some(&compute_bgl)],
                 immediate_size: 0,
             });
-        let compute_pipeline = ctx
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some("Blur Pipeline"),
-                layout: Some(&compute_layout),
-                module: &blur_shader,
-                entry_point: Some("main"),
-                compilation_options: PipelineCompilationOptions::default(),
-                cache: None,
-            });
 
         // Post bind group (scene + blur + sampler)
         let post_sampler = ctx.device.create_sampler(&SamplerDescriptor {
@@ -601,51 +619,19 @@ impl Example for ComputeDemo {
                 bind_group_layouts: &[Some(&post_bgl)],
                 immediate_size: 0,
             });
-        let post_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Post Process Render Pipeline"),
-                layout: Some(&post_layout),
-                vertex: VertexState {
-                    module: &post_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &post_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let (scene_pipeline, compute_pipeline, post_pipeline) =
+            Self::create_pipelines(ctx, &scene_layout, &compute_layout, &post_layout)?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
         let camera = Camera::new(Vec3::new(0.0, 2.5, 5.0), 0.0, -0.3);
 
-        Self {
+        Ok(Self {
             scene_pipeline,
             compute_pipeline,
             post_pipeline,
+            scene_layout,
+            compute_layout,
+            post_layout,
             cube_vertex_buffer,
             cube_index_buffer,
             floor_vertex_buffer,
@@ -664,7 +650,7 @@ impl Example for ComputeDemo {
             depth_texture,
             depth_texture_view,
             camera,
-        }
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -722,13 +708,7 @@ impl Example for ComputeDemo {
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection = glam::camera::rh::proj::directx::perspective(
-            std::f32::consts::FRAC_PI_4,
-            aspect,
-            0.1,
-            100.0,
-        );
-        let view_proj = projection * self.camera.view_matrix();
+        let view_proj = self.camera.view_proj(aspect);
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
             data.write(&CameraUniforms { view_proj })
@@ -758,7 +738,7 @@ impl Example for ComputeDemo {
                     }),
                     stencil_ops: None,
                 }),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Scene Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -778,12 +758,12 @@ impl Example for ComputeDemo {
         {
             let mut cpass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("Blur Pass"),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.compute_pass("Blur Pass"),
             });
             cpass.set_pipeline(&self.compute_pipeline);
             cpass.set_bind_group(0, &self.compute_bind_group, &[]);
-            let wg_x = (ctx.surface_config.width + 15) / 16;
-            let wg_y = (ctx.surface_config.height + 15) / 16;
+            let wg_x = ctx.surface_config.width.div_ceil(16);
+            let wg_y = ctx.surface_config.height.div_ceil(16);
             cpass.dispatch_workgroups(wg_x, wg_y, 1);
         }
 
@@ -801,7 +781,7 @@ impl Example for ComputeDemo {
                     depth_slice: None,
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Display Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -810,6 +790,20 @@ impl Example for ComputeDemo {
             rpass.draw(0..6, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        (
+            self.scene_pipeline,
+            self.compute_pipeline,
+            self.post_pipeline,
+        ) = Self::create_pipelines(
+            ctx,
+            &self.scene_layout,
+            &self.compute_layout,
+            &self.post_layout,
+        )?;
+        Ok(())
+    }
 }
 
 fn main() {
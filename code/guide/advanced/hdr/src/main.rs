@@ -1,69 +1,42 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::mem::size_of;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
 use encase::ShaderType;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendState, Buffer,
-    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoder, CompareFunction, DepthStencilState, Extent3d, Face, FilterMode,
-    FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode, MultisampleState, Operations,
-    PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
-    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
-    SamplerDescriptor, ShaderStages, StencilState, StoreOp, TexelCopyBufferLayout, Texture,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
-    TextureView, TextureViewDescriptor, TextureViewDimension, VertexAttribute, VertexBufferLayout,
-    VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, CompareFunction, DepthStencilState, Extent3d, Face, FilterMode, FragmentState,
+    FrontFace, IndexFormat, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
+    PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, StencilState, StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
+use framework::geometry;
+use framework::texture::{TextureImage, create_sampler, create_texture_with_mips};
 use framework::{
-    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input,
-    create_depth_texture, generate_checkerboard, run,
+    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Indices,
+    Input, Result, Ui, VertexLayout, create_depth_texture, generate_checkerboard, load_shader, run,
+    shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
     uv: [f32; 2],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 3] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 6]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -77,30 +50,22 @@ fn cube_vertices() -> Vec<Vertex> {
         .collect()
 }
 
-const FLOOR_VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [-5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 5.0],
-    },
-    Vertex {
-        position: [-5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 5.0],
-    },
-];
-
-const FLOOR_INDICES: &[u16] = &[0, 2, 1, 0, 3, 2];
+// 10x10 floor under the cubes with the texture repeated 5 times
+fn floor() -> (Vec<Vertex>, Indices) {
+    let mesh = geometry::plane(10.0, 10.0)
+        .transformed(Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)))
+        .with_uv_scale(Vec2::splat(5.0));
+    let vertices = mesh
+        .vertices
+        .iter()
+        .map(|v| Vertex {
+            position: v.position,
+            normal: v.normal,
+            uv: v.uv,
+        })
+        .collect();
+    (vertices, mesh.indices)
+}
 
 #[derive(ShaderType)]
 struct CameraUniforms {
@@ -116,10 +81,14 @@ struct LightUniforms {
 
 const TEX_SIZE: u32 = 256;
 const CELL_SIZE: u32 = 32;
+const TONE_MAPPERS: [&str; 2] = ["ACES", "Reinhard"];
 
-struct HdrDemo {
+pub(crate) struct HdrDemo {
     scene_pipeline: RenderPipeline,
-    post_pipeline: RenderPipeline,
+    // One pipeline per entry of TONE_MAPPERS, selected with the TONE_MAPPER override
+    post_pipelines: Vec<RenderPipeline>,
+    scene_layout: PipelineLayout,
+    post_layout: PipelineLayout,
     cube_vertex_buffer: Buffer,
     cube_index_buffer: Buffer,
     floor_vertex_buffer: Buffer,
@@ -135,9 +104,112 @@ struct HdrDemo {
     depth_texture: Texture,
     depth_texture_view: TextureView,
     camera: Camera,
+    tone_mapper: usize,
 }
 
 impl HdrDemo {
+    fn create_pipelines(
+        ctx: &GpuContext,
+        scene_layout: &PipelineLayout,
+        post_layout: &PipelineLayout,
+    ) -> Result<(RenderPipeline, Vec<RenderPipeline>)> {
+        let scene_shader = load_shader(ctx, &shader!("scene.wgsl"))?;
+        let post_shader = load_shader(ctx, &shader!("post.wgsl"))?;
+
+        let scene_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Scene Pipeline"),
+                layout: Some(scene_layout),
+                vertex: VertexState {
+                    module: &scene_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &scene_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: TextureFormat::Rgba16Float,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: Default::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        let post_pipelines = (0..TONE_MAPPERS.len())
+            .map(|tone_mapper| {
+                ctx.device
+                    .create_render_pipeline(&RenderPipelineDescriptor {
+                        label: Some("Post Process Render Pipeline"),
+                        layout: Some(post_layout),
+                        vertex: VertexState {
+                            module: &post_shader,
+                            entry_point: Some("vs_main"),
+                            buffers: &[],
+                            compilation_options: PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(FragmentState {
+                            module: &post_shader,
+                            entry_point: Some("fs_main"),
+                            targets: &[Some(ColorTargetState {
+                                format: ctx.surface_format,
+                                blend: Some(BlendState {
+                                    color: BlendComponent::REPLACE,
+                                    alpha: BlendComponent::REPLACE,
+                                }),
+                                write_mask: ColorWrites::ALL,
+                            })],
+                            compilation_options: PipelineCompilationOptions {
+                                constants: &[("TONE_MAPPER", tone_mapper as f64)],
+                                ..Default::default()
+                            },
+                        }),
+                        primitive: PrimitiveState {
+                            topology: PrimitiveTopology::TriangleList,
+                            ..Default::default()
+                        },
+                        depth_stencil: None,
+                        multisample: MultisampleState {
+                            count: 1,
+                            mask: !0,
+                            alpha_to_coverage_enabled: false,
+                        },
+                        cache: None,
+                        multiview_mask: None,
+                    })
+            })
+            .collect();
+
+        Ok((scene_pipeline, post_pipelines))
+    }
+
     fn create_hdr_texture(ctx: &GpuContext) -> (Texture, TextureView) {
         let size = &ctx.surface_config;
         let texture = ctx.device.create_texture(&TextureDescriptor {
@@ -151,7 +223,9 @@ impl HdrDemo {
             sample_count: 1,
             dimension: TextureDimension::D2,
             format: TextureFormat::Rgba16Float,
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
             view_formats: &[],
         });
         let view = texture.create_view(&TextureViewDescriptor::default());
@@ -160,10 +234,7 @@ impl HdrDemo {
 }
 
 impl Example for HdrDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let scene_shader = ctx.device.create_shader_module(include_wgsl!("scene.wgsl"));
-        let post_shader = ctx.device.create_shader_module(include_wgsl!("post.wgsl"));
-
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let cube_vertex_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -178,18 +249,19 @@ impl Example for HdrDemo {
                 contents: bytemuck::cast_slice(&CUBE_INDICES),
                 usage: BufferUsages::INDEX,
             });
+        let (floor_vertices, floor_indices) = floor();
         let floor_vertex_buffer =
             ctx.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Floor Vertex Buffer"),
-                    contents: bytemuck::cast_slice(FLOOR_VERTICES),
+                    contents: bytemuck::cast_slice(&floor_vertices),
                     usage: BufferUsages::VERTEX,
                 });
         let floor_index_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Floor Index Buffer"),
-                contents: bytemuck::cast_slice(FLOOR_INDICES),
+                contents: floor_indices.as_bytes(),
                 usage: BufferUsages::INDEX,
             });
 
@@ -225,52 +297,22 @@ impl Example for HdrDemo {
         );
         let cube_texture_view = cube_texture.create_view(&TextureViewDescriptor::default());
 
-        let floor_pixels = generate_checkerboard(
-            TEX_SIZE,
-            CELL_SIZE,
-            [200, 200, 200, 255],
-            [100, 100, 100, 255],
-        );
-        let floor_texture = ctx.device.create_texture(&TextureDescriptor {
-            label: Some("Floor Texture"),
-            size: Extent3d {
-                width: TEX_SIZE,
-                height: TEX_SIZE,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
+        // The floor is seen at grazing angles, so it gets a full mip chain to stop the shimmering
+        let floor_image = TextureImage {
+            width: TEX_SIZE,
+            height: TEX_SIZE,
             format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        ctx.queue.write_texture(
-            floor_texture.as_image_copy(),
-            &floor_pixels,
-            TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(TEX_SIZE * 4),
-                rows_per_image: Some(TEX_SIZE),
-            },
-            Extent3d {
-                width: TEX_SIZE,
-                height: TEX_SIZE,
-                depth_or_array_layers: 1,
-            },
-        );
+            data: generate_checkerboard(
+                TEX_SIZE,
+                CELL_SIZE,
+                [200, 200, 200, 255],
+                [100, 100, 100, 255],
+            ),
+        };
+        let floor_texture = create_texture_with_mips(ctx, "Floor Texture", &floor_image);
         let floor_texture_view = floor_texture.create_view(&TextureViewDescriptor::default());
 
-        let diffuse_sampler = ctx.device.create_sampler(&SamplerDescriptor {
-            label: Some("Diffuse Sampler"),
-            address_mode_u: AddressMode::Repeat,
-            address_mode_v: AddressMode::Repeat,
-            address_mode_w: AddressMode::Repeat,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: MipmapFilterMode::Nearest,
-            ..Default::default()
-        });
+        let diffuse_sampler = create_sampler(ctx, AddressMode::Repeat, 16);
 
         let camera_uniform_buffer = ctx.device.create_buffer(&BufferDescriptor {
             label: Some("Camera Uniform Buffer"),
@@ -399,52 +441,6 @@ impl Example for HdrDemo {
                 bind_group_layouts: &[Some(&camera_bgl), Some(&scene_bgl)],
                 immediate_size: 0,
             });
-        let scene_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Scene Pipeline"),
-                layout: Some(&scene_layout),
-                vertex: VertexState {
-                    module: &scene_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &scene_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: TextureFormat::Rgba16Float,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: Default::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
 
         let (hdr_texture, hdr_texture_view) = Self::create_hdr_texture(ctx);
 
@@ -500,50 +496,17 @@ impl Example for HdrDemo {
                 bind_group_layouts: &[Some(&hdr_bgl)],
                 immediate_size: 0,
             });
-        let post_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Post Process Render Pipeline"),
-                layout: Some(&post_layout),
-                vertex: VertexState {
-                    module: &post_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &post_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let (scene_pipeline, post_pipelines) =
+            Self::create_pipelines(ctx, &scene_layout, &post_layout)?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
         let camera = Camera::new(Vec3::new(0.0, 2.5, 5.0), 0.0, -0.3);
 
-        Self {
+        Ok(Self {
             scene_pipeline,
-            post_pipeline,
+            post_pipelines,
+            scene_layout,
+            post_layout,
             cube_vertex_buffer,
             cube_index_buffer,
             floor_vertex_buffer,
@@ -559,7 +522,8 @@ impl Example for HdrDemo {
             depth_texture,
             depth_texture_view,
             camera,
-        }
+            tone_mapper: 0,
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -567,7 +531,7 @@ impl Example for HdrDemo {
         self.hdr_texture = hdr_tex;
         self.hdr_texture_view = hdr_view;
 
-        let hdr_bgl = self.post_pipeline.get_bind_group_layout(0);
+        let hdr_bgl = self.post_pipelines[0].get_bind_group_layout(0);
         self.hdr_bind_group = ctx.device.create_bind_group(&BindGroupDescriptor {
             label: Some("HDR Bind Group (Resized)"),
             layout: &hdr_bgl,
@@ -588,19 +552,17 @@ impl Example for HdrDemo {
         self.depth_texture_view = v;
     }
 
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.combo("Tone mapping", &mut self.tone_mapper, &TONE_MAPPERS);
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.camera.update(dt.as_secs_f32(), input);
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection = glam::camera::rh::proj::directx::perspective(
-            std::f32::consts::FRAC_PI_4,
-            aspect,
-            0.1,
-            100.0,
-        );
-        let view_proj = projection * self.camera.view_matrix();
+        let view_proj = self.camera.view_proj(aspect);
 
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
@@ -669,11 +631,21 @@ impl Example for HdrDemo {
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
-            rpass.set_pipeline(&self.post_pipeline);
+            rpass.set_pipeline(&self.post_pipelines[self.tone_mapper]);
             rpass.set_bind_group(0, &self.hdr_bind_group, &[]);
             rpass.draw(0..6, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        (self.scene_pipeline, self.post_pipelines) =
+            Self::create_pipelines(ctx, &self.scene_layout, &self.post_layout)?;
+        Ok(())
+    }
+
+    fn capture_targets(&self) -> Vec<(&'static str, &Texture)> {
+        vec![("hdr", &self.hdr_texture)]
+    }
 }
 
 fn main() {
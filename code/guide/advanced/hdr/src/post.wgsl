@@ -1,7 +1,8 @@
-struct VertexOutput {
-    @builtin(position) position: vec4<f32>,
-    @location(0) uv: vec2<f32>,
-}
+// 0 = ACES, 1 = Reinhard; set per pipeline from PipelineCompilationOptions::constants
+override TONE_MAPPER: u32 = 0u;
+
+#include <fullscreen.wgsl>
+#include <tonemap.wgsl>
 
 @group(0) @binding(0)
 var hdr_tex: texture_2d<f32>;
@@ -9,46 +10,14 @@ var hdr_tex: texture_2d<f32>;
 @group(0) @binding(1)
 var hdr_sampler: sampler;
 
-@vertex
-fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
-    var positions = array<vec2<f32>, 6>(
-        vec2<f32>(-1.0, -1.0),
-        vec2<f32>( 1.0, -1.0),
-        vec2<f32>( 1.0,  1.0),
-        vec2<f32>(-1.0, -1.0),
-        vec2<f32>( 1.0,  1.0),
-        vec2<f32>(-1.0,  1.0),
-    );
-    var uvs = array<vec2<f32>, 6>(
-        vec2<f32>(0.0, 1.0),
-        vec2<f32>(1.0, 1.0),
-        vec2<f32>(1.0, 0.0),
-        vec2<f32>(0.0, 1.0),
-        vec2<f32>(1.0, 0.0),
-        vec2<f32>(0.0, 0.0),
-    );
-    var output: VertexOutput;
-    output.position = vec4<f32>(positions[idx], 0.0, 1.0);
-    output.uv = uvs[idx];
-    return output;
-}
-
-fn reinhard(color: vec3<f32>) -> vec3<f32> {
-    return color / (color + vec3<f32>(1.0));
-}
-
-fn aces(x: vec3<f32>) -> vec3<f32> {
-    let a = 2.51;
-    let b = 0.03;
-    let c = 2.43;
-    let d = 0.59;
-    let e = 0.14;
-    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
-}
-
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
     let hdr = textureSample(hdr_tex, hdr_sampler, input.uv);
-    let mapped = aces(hdr.rgb);
+    var mapped: vec3<f32>;
+    if (TONE_MAPPER == 1u) {
+        mapped = reinhard(hdr.rgb);
+    } else {
+        mapped = aces(hdr.rgb);
+    }
     return vec4<f32>(mapped, 1.0);
 }
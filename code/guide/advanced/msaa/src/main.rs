@@ -1,7 +1,5 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::f32::consts::FRAC_PI_4;
-use std::mem::size_of;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
@@ -10,55 +8,35 @@ use glam::{Mat3, Mat4, Vec3};
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingType, BlendComponent, BlendState, Buffer, BufferAddress,
-    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
-    CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d, Face,
-    FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState, Operations,
-    PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
-    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages, StencilState,
-    StoreOp, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    TextureView, TextureViewDescriptor, VertexAttribute, VertexBufferLayout, VertexFormat,
-    VertexState, VertexStepMode, include_wgsl,
+    BindGroupLayoutEntry, BindingType, BlendComponent, BlendState, Buffer, BufferBindingType,
+    BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    CompareFunction, DepthBiasState, DepthStencilState, Extent3d, Face, FragmentState, FrontFace,
+    IndexFormat, LoadOp, MultisampleState, Operations, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, ShaderStages, StencilState, StoreOp, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
 use framework::{
-    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, Camera, Example, GpuContext, Input, run,
+    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, Camera, Example, GpuContext, Input, Result, Ui,
+    VertexLayout, load_shader, run, shader,
 };
 
 const SAMPLE_COUNT: u32 = 4;
+// 1 and 4 samples are the only counts every adapter has to support
+const SAMPLE_COUNTS: [u32; 2] = [1, SAMPLE_COUNT];
+const SAMPLE_COUNT_NAMES: [&str; 2] = ["Off", "4x"];
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 2] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -68,60 +46,13 @@ fn cube_vertices() -> Vec<Vertex> {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+#[vertex(step_mode = Instance, location = 2)]
 struct InstanceData {
     model: [[f32; 4]; 4],
     normal_matrix: [[f32; 3]; 3],
 }
 
-impl InstanceData {
-    const ATTRIBUTES: [VertexAttribute; 7] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 2,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 4]>() as BufferAddress,
-            shader_location: 3,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 8]>() as BufferAddress,
-            shader_location: 4,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 12]>() as BufferAddress,
-            shader_location: 5,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 16]>() as BufferAddress,
-            shader_location: 6,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 19]>() as BufferAddress,
-            shader_location: 7,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 22]>() as BufferAddress,
-            shader_location: 8,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<InstanceData>() as BufferAddress,
-            step_mode: VertexStepMode::Instance,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 #[derive(ShaderType)]
 struct ShaderUniforms {
     view_proj: Mat4,
@@ -159,14 +90,17 @@ fn generate_instances() -> Vec<InstanceData> {
     instances
 }
 
-struct MSAADemo {
-    pipeline: RenderPipeline,
+pub(crate) struct MSAADemo {
+    // One pipeline per entry of SAMPLE_COUNTS
+    pipelines: Vec<RenderPipeline>,
+    pipeline_layout: PipelineLayout,
+    sample_index: usize,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     instance_buffer: Buffer,
     uniform_buffer: Buffer,
     bind_group: BindGroup,
-    _msaa_texture: Texture,
+    msaa_texture: Texture,
     msaa_view: TextureView,
     _depth_texture: Texture,
     depth_texture_view: TextureView,
@@ -174,7 +108,62 @@ struct MSAADemo {
 }
 
 impl MSAADemo {
-    fn create_msaa_texture(ctx: &GpuContext) -> (Texture, TextureView) {
+    fn create_pipelines(ctx: &GpuContext, layout: &PipelineLayout) -> Result<Vec<RenderPipeline>> {
+        let shader = load_shader(ctx, &shader!("shader.wgsl"))?;
+
+        Ok(SAMPLE_COUNTS
+            .iter()
+            .map(|&sample_count| {
+                ctx.device
+                    .create_render_pipeline(&RenderPipelineDescriptor {
+                        label: Some("MSAA Pipeline"),
+                        layout: Some(layout),
+                        vertex: VertexState {
+                            module: &shader,
+                            entry_point: Some("vs_main"),
+                            buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
+                            compilation_options: PipelineCompilationOptions::default(),
+                        },
+                        fragment: Some(FragmentState {
+                            module: &shader,
+                            entry_point: Some("fs_main"),
+                            targets: &[Some(ColorTargetState {
+                                format: ctx.surface_format,
+                                blend: Some(BlendState {
+                                    color: BlendComponent::REPLACE,
+                                    alpha: BlendComponent::REPLACE,
+                                }),
+                                write_mask: ColorWrites::ALL,
+                            })],
+                            compilation_options: PipelineCompilationOptions::default(),
+                        }),
+                        primitive: PrimitiveState {
+                            topology: PrimitiveTopology::TriangleList,
+                            front_face: FrontFace::Ccw,
+                            polygon_mode: PolygonMode::Fill,
+                            cull_mode: Some(Face::Back),
+                            ..Default::default()
+                        },
+                        depth_stencil: Some(DepthStencilState {
+                            format: TextureFormat::Depth32Float,
+                            depth_write_enabled: Some(true),
+                            depth_compare: Some(CompareFunction::Less),
+                            stencil: StencilState::default(),
+                            bias: DepthBiasState::default(),
+                        }),
+                        multisample: MultisampleState {
+                            count: sample_count,
+                            mask: !0,
+                            alpha_to_coverage_enabled: false,
+                        },
+                        cache: None,
+                        multiview_mask: None,
+                    })
+            })
+            .collect())
+    }
+
+    fn create_msaa_texture(ctx: &GpuContext, sample_count: u32) -> (Texture, TextureView) {
         let size = &ctx.surface_config;
         let texture = ctx.device.create_texture(&TextureDescriptor {
             label: Some("MSAA Color Texture"),
@@ -184,7 +173,7 @@ impl MSAADemo {
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count: SAMPLE_COUNT,
+            sample_count,
             dimension: TextureDimension::D2,
             format: ctx.surface_format,
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -194,7 +183,7 @@ impl MSAADemo {
         (texture, view)
     }
 
-    fn create_depth_texture(ctx: &GpuContext) -> (Texture, TextureView) {
+    fn create_depth_texture(ctx: &GpuContext, sample_count: u32) -> (Texture, TextureView) {
         let size = &ctx.surface_config;
         let texture = ctx.device.create_texture(&TextureDescriptor {
             label: Some("MSAA Depth Texture"),
@@ -204,7 +193,7 @@ impl MSAADemo {
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count: SAMPLE_COUNT,
+            sample_count,
             dimension: TextureDimension::D2,
             format: TextureFormat::Depth32Float,
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -216,11 +205,7 @@ impl MSAADemo {
 }
 
 impl Example for MSAADemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader = ctx
-            .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
-
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let vertex_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -283,90 +268,50 @@ impl Example for MSAADemo {
                 immediate_size: 0,
             });
 
-        let pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("MSAA Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: SAMPLE_COUNT,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let pipelines = Self::create_pipelines(ctx, &pipeline_layout)?;
 
-        let (msaa_texture, msaa_view) = Self::create_msaa_texture(ctx);
-        let (depth_texture, depth_texture_view) = Self::create_depth_texture(ctx);
+        let (msaa_texture, msaa_view) = Self::create_msaa_texture(ctx, SAMPLE_COUNT);
+        let (depth_texture, depth_texture_view) = Self::create_depth_texture(ctx, SAMPLE_COUNT);
         let camera = Camera::new(Vec3::new(0.0, 2.0, 6.0), 0.0, -0.25);
 
-        Self {
-            pipeline,
+        Ok(Self {
+            pipelines,
+            pipeline_layout,
+            sample_index: 1,
             vertex_buffer,
             index_buffer,
             instance_buffer,
             uniform_buffer,
             bind_group,
-            _msaa_texture: msaa_texture,
+            msaa_texture,
             msaa_view,
             _depth_texture: depth_texture,
             depth_texture_view,
             camera,
-        }
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
-        let (t, v) = Self::create_msaa_texture(ctx);
-        self._msaa_texture = t;
+        let sample_count = SAMPLE_COUNTS[self.sample_index];
+        let (t, v) = Self::create_msaa_texture(ctx, sample_count);
+        self.msaa_texture = t;
         self.msaa_view = v;
-        let (d, dv) = Self::create_depth_texture(ctx);
+        let (d, dv) = Self::create_depth_texture(ctx, sample_count);
         self._depth_texture = d;
         self.depth_texture_view = dv;
     }
 
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.combo("MSAA", &mut self.sample_index, &SAMPLE_COUNT_NAMES);
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.camera.update(dt.as_secs_f32(), input);
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection =
-            glam::camera::rh::proj::directx::perspective(FRAC_PI_4, aspect, 0.1, 100.0);
-        let view_proj = projection * self.camera.view_matrix();
+        let view_proj = self.camera.view_proj(aspect);
 
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
@@ -380,11 +325,25 @@ impl Example for MSAADemo {
                 .write_buffer(&self.uniform_buffer, 0, &data.into_inner());
         }
 
+        // Attachments have to match the sample count of the selected pipeline
+        if self.msaa_texture.sample_count() != SAMPLE_COUNTS[self.sample_index] {
+            self.resize(
+                ctx,
+                PhysicalSize::new(ctx.surface_config.width, ctx.surface_config.height),
+            );
+        }
+        // Without multisampling there is nothing to resolve, the pass draws straight to the surface
+        let (color_view, resolve_target) = if SAMPLE_COUNTS[self.sample_index] == 1 {
+            (view, None)
+        } else {
+            (&self.msaa_view, Some(view))
+        };
+
         let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("MSAA Pass"),
             color_attachments: &[Some(RenderPassColorAttachment {
-                view: &self.msaa_view,
-                resolve_target: Some(view),
+                view: color_view,
+                resolve_target,
                 ops: Operations {
                     load: LoadOp::Clear(Color::BLACK),
                     store: StoreOp::Store,
@@ -403,13 +362,18 @@ impl Example for MSAADemo {
             occlusion_query_set: None,
             multiview_mask: None,
         });
-        rpass.set_pipeline(&self.pipeline);
+        rpass.set_pipeline(&self.pipelines[self.sample_index]);
         rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
         rpass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
         rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.draw_indexed(0..36, 0, 0..NUM_INSTANCES as u32);
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipelines = Self::create_pipelines(ctx, &self.pipeline_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
@@ -1,114 +1,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::f32::consts::{FRAC_PI_4, PI};
-use std::mem::size_of;
 use std::time::Duration;
 
-use bytemuck::{Pod, Zeroable};
 use encase::ShaderType;
 use glam::{Mat3, Mat4, Vec3, Vec4};
 use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendState, Buffer,
-    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
-    Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode,
-    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
-    PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
-    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderStages, StencilState,
-    StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d, Face, FilterMode,
+    FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode,
+    PrimitiveState, PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, StencilState, StoreOp, TexelCopyBufferLayout, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
+use framework::golden::Image;
 use framework::{
-    Camera, Example, GpuContext, Input, create_depth_texture, generate_checkerboard, run,
+    ArcballCamera, Camera, CameraController, CameraSwitcher, Example, GpuContext, Indices, Input,
+    MeshVertex, OrbitCamera, Projection, Result, TurntableCamera, Ui, VertexLayout,
+    create_depth_texture, load_gltf, load_shader, run, shader,
 };
 
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-struct Vertex {
-    position: [f32; 3],
-    normal: [f32; 3],
-    uv: [f32; 2],
-}
-
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 3] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 6]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
-fn generate_sphere(stacks: u32, slices: u32, radius: f32) -> (Vec<Vertex>, Vec<u16>) {
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
-
-    for stack in 0..=stacks {
-        let phi = PI * stack as f32 / stacks as f32;
-        let sin_phi = phi.sin();
-        let cos_phi = phi.cos();
-
-        for slice in 0..=slices {
-            let theta = 2.0 * PI * slice as f32 / slices as f32;
-            let sin_theta = theta.sin();
-            let cos_theta = theta.cos();
-
-            let x = cos_theta * sin_phi;
-            let y = cos_phi;
-            let z = sin_theta * sin_phi;
-
-            vertices.push(Vertex {
-                position: [x * radius, y * radius, z * radius],
-                normal: [x, y, z],
-                uv: [slice as f32 / slices as f32, stack as f32 / stacks as f32],
-            });
-        }
-    }
-
-    for stack in 0..stacks {
-        for slice in 0..slices {
-            let a = (stack * (slices + 1) + slice) as u16;
-            let b = a + slices as u16 + 1;
-            indices.push(a);
-            indices.push(a + 1);
-            indices.push(b);
-            indices.push(a + 1);
-            indices.push(b + 1);
-            indices.push(b);
-        }
-    }
-
-    (vertices, indices)
-}
-
-const TEX_SIZE: u32 = 256;
-const CELL_SIZE: u32 = 32;
-
 #[derive(ShaderType)]
 struct CameraUniforms {
     view_proj: Mat4,
@@ -126,6 +44,7 @@ struct MeshUniforms {
 struct MeshDraw {
     vertex_buffer: Buffer,
     index_buffer: Buffer,
+    index_format: IndexFormat,
     index_count: u32,
     bind_group: BindGroup,
     model: Mat4,
@@ -134,22 +53,80 @@ struct MeshDraw {
     base_color: Vec4,
 }
 
-struct ModelLoadingDemo {
+fn solid_image(color: [u8; 4]) -> Image {
+    Image {
+        width: 1,
+        height: 1,
+        pixels: color.to_vec(),
+    }
+}
+
+pub(crate) struct ModelLoadingDemo {
     pipeline: RenderPipeline,
+    pipeline_layout: PipelineLayout,
     meshes: Vec<MeshDraw>,
     depth_texture: Texture,
     depth_texture_view: TextureView,
-    camera: Camera,
+    camera: CameraSwitcher,
     camera_uniform_buffer: Buffer,
     camera_bind_group: BindGroup,
 }
 
-impl Example for ModelLoadingDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader = ctx
+impl ModelLoadingDemo {
+    fn create_pipeline(ctx: &GpuContext, layout: &PipelineLayout) -> Result<RenderPipeline> {
+        let shader = load_shader(ctx, &shader!("shader.wgsl"))?;
+
+        Ok(ctx
             .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(MeshVertex::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            }))
+    }
+}
 
+impl Example for ModelLoadingDemo {
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let camera_bgl = ctx
             .device
             .create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -197,6 +174,16 @@ impl Example for ModelLoadingDemo {
                         ty: BindingType::Sampler(SamplerBindingType::Filtering),
                         count: None,
                     },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -208,52 +195,7 @@ impl Example for ModelLoadingDemo {
                 immediate_size: 0,
             });
 
-        let pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let pipeline = Self::create_pipeline(ctx, &pipeline_layout)?;
 
         let sampler = ctx.device.create_sampler(&SamplerDescriptor {
             label: Some("Diffuse Sampler"),
@@ -282,43 +224,39 @@ impl Example for ModelLoadingDemo {
             }],
         });
 
-        let create_texture = |r: u8, g: u8, b: u8| {
-            let pixels =
-                generate_checkerboard(TEX_SIZE, CELL_SIZE, [255, 255, 255, 255], [r, g, b, 255]);
+        let create_texture = |image: &Image, format: TextureFormat| {
+            let size = Extent3d {
+                width: image.width,
+                height: image.height,
+                depth_or_array_layers: 1,
+            };
             let tex = ctx.device.create_texture(&TextureDescriptor {
                 label: Some("Mesh Texture"),
-                size: Extent3d {
-                    width: TEX_SIZE,
-                    height: TEX_SIZE,
-                    depth_or_array_layers: 1,
-                },
+                size,
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
                 view_formats: &[],
             });
             ctx.queue.write_texture(
                 tex.as_image_copy(),
-                &pixels,
+                &image.pixels,
                 TexelCopyBufferLayout {
                     offset: 0,
-                    bytes_per_row: Some(TEX_SIZE * 4),
-                    rows_per_image: Some(TEX_SIZE),
-                },
-                Extent3d {
-                    width: TEX_SIZE,
-                    height: TEX_SIZE,
-                    depth_or_array_layers: 1,
+                    bytes_per_row: Some(image.width * 4),
+                    rows_per_image: Some(image.height),
                 },
+                size,
             );
             tex.create_view(&TextureViewDescriptor::default())
         };
 
-        let create_mesh = |vertices: &[Vertex],
-                           indices: &[u16],
+        let create_mesh = |vertices: &[MeshVertex],
+                           indices: &Indices,
                            tex_view: &TextureView,
+                           normal_view: &TextureView,
                            color: Vec4,
                            model: Mat4| {
             let vertex_buffer = ctx
@@ -332,7 +270,7 @@ impl Example for ModelLoadingDemo {
                 .device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Mesh Index Buffer"),
-                    contents: bytemuck::cast_slice(indices),
+                    contents: indices.as_bytes(),
                     usage: BufferUsages::INDEX,
                 });
             let uniform_buffer = ctx.device.create_buffer(&BufferDescriptor {
@@ -358,11 +296,16 @@ impl Example for ModelLoadingDemo {
                         binding: 2,
                         resource: BindingResource::Sampler(&sampler),
                     },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::TextureView(normal_view),
+                    },
                 ],
             });
             MeshDraw {
                 vertex_buffer,
                 index_buffer,
+                index_format: indices.format(),
                 index_count: indices.len() as u32,
                 bind_group,
                 model,
@@ -372,47 +315,71 @@ impl Example for ModelLoadingDemo {
             }
         };
 
-        let sphere = generate_sphere(16, 32, 1.0);
-        let tex1 = create_texture(58, 134, 173);
-        let tex2 = create_texture(173, 58, 58);
-        let tex3 = create_texture(58, 173, 87);
-
-        let meshes = vec![
-            create_mesh(
-                &sphere.0,
-                &sphere.1,
-                &tex1,
-                Vec4::new(1.0, 1.0, 1.0, 1.0),
-                Mat4::from_translation(Vec3::new(-3.0, 0.0, 0.0)),
-            ),
-            create_mesh(
-                &sphere.0,
-                &sphere.1,
-                &tex2,
-                Vec4::new(1.0, 1.0, 1.0, 1.0),
-                Mat4::from_translation(Vec3::new(0.0, 0.0, 0.0)),
-            ),
-            create_mesh(
-                &sphere.0,
-                &sphere.1,
-                &tex3,
-                Vec4::new(1.0, 1.0, 1.0, 1.0),
-                Mat4::from_translation(Vec3::new(3.0, 0.0, 0.0)),
-            ),
-        ];
+        let model = load_gltf(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/scene.gltf"))
+            .expect("Failed to load scene.gltf");
+
+        // Materials without a texture sample a 1x1 white base color and a flat normal
+        let white = create_texture(
+            &solid_image([255, 255, 255, 255]),
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let flat = create_texture(
+            &solid_image([128, 128, 255, 255]),
+            TextureFormat::Rgba8Unorm,
+        );
+        let materials: Vec<_> = model
+            .materials
+            .iter()
+            .map(|material| {
+                let base = material
+                    .base_color_texture
+                    .map(|i| create_texture(&model.images[i], TextureFormat::Rgba8UnormSrgb));
+                let normal = material
+                    .normal_texture
+                    .map(|i| create_texture(&model.images[i], TextureFormat::Rgba8Unorm));
+                (base, normal, material.base_color)
+            })
+            .collect();
+
+        let meshes = model
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let (base, normal, color) = match mesh.material.map(|i| &materials[i]) {
+                    Some((base, normal, color)) => (base.as_ref(), normal.as_ref(), *color),
+                    None => (None, None, Vec4::ONE),
+                };
+                create_mesh(
+                    &mesh.vertices,
+                    &mesh.indices,
+                    base.unwrap_or(&white),
+                    normal.unwrap_or(&flat),
+                    color,
+                    mesh.transform,
+                )
+            })
+            .collect();
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
-        let camera = Camera::new(Vec3::new(0.0, 2.0, 7.0), 0.0, -0.2);
-
-        Self {
+        // Orbiting suits inspecting a model best, the "Camera" combo in the panel switches controllers
+        let (eye, target) = (Vec3::new(0.0, 2.0, 7.0), Vec3::new(0.0, 0.5, 0.0));
+        let mut fly = Camera::new(eye, 0.0, 0.0);
+        fly.look_at(eye, target);
+        let camera = CameraSwitcher::new("Orbit", OrbitCamera::looking_at(eye, target))
+            .with("Arcball", ArcballCamera::looking_at(eye, target))
+            .with("Turntable", TurntableCamera::looking_at(eye, target))
+            .with("Fly", fly);
+
+        Ok(Self {
             pipeline,
+            pipeline_layout,
             meshes,
             depth_texture,
             depth_texture_view,
             camera,
             camera_uniform_buffer,
             camera_bind_group,
-        }
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -421,15 +388,20 @@ impl Example for ModelLoadingDemo {
         self.depth_texture_view = v;
     }
 
+    fn ui(&mut self, ui: &mut Ui) {
+        let mut active = self.camera.active();
+        if ui.combo("Camera", &mut active, &self.camera.names()) {
+            self.camera.select(active);
+        }
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.camera.update(dt.as_secs_f32(), input);
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection =
-            glam::camera::rh::proj::directx::perspective(FRAC_PI_4, aspect, 0.1, 100.0);
-        let view_proj = projection * self.camera.view_matrix();
+        let view_proj = Projection::default().matrix(aspect) * self.camera.view_matrix();
 
         let mut camera_data = encase::UniformBuffer::new(Vec::new());
         camera_data
@@ -480,11 +452,16 @@ impl Example for ModelLoadingDemo {
         rpass.set_bind_group(0, &self.camera_bind_group, &[]);
         for mesh in &self.meshes {
             rpass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-            rpass.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint16);
+            rpass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
             rpass.set_bind_group(1, &mesh.bind_group, &[]);
             rpass.draw_indexed(0..mesh.index_count, 0, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx, &self.pipeline_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
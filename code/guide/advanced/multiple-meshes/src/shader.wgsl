@@ -2,6 +2,7 @@ struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) uv: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
 }
 
 struct VertexOutput {
@@ -9,6 +10,7 @@ struct VertexOutput {
     @location(0) world_pos: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) uv: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
 }
 
 struct CameraUniforms {
@@ -35,6 +37,9 @@ var diffuse_tex: texture_2d<f32>;
 @group(1) @binding(2)
 var diffuse_sampler: sampler;
 
+@group(1) @binding(3)
+var normal_tex: texture_2d<f32>;
+
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
     var output: VertexOutput;
@@ -43,12 +48,18 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     output.world_pos = world_pos.xyz;
     output.normal = mesh.normal_matrix * input.normal;
     output.uv = input.uv;
+    output.tangent = vec4<f32>((mesh.model * vec4<f32>(input.tangent.xyz, 0.0)).xyz, input.tangent.w);
     return output;
 }
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    let normal = normalize(input.normal);
+    // Tangent-space normal from the map; w of the tangent flips the bitangent for mirrored UVs
+    let N = normalize(input.normal);
+    let T = normalize(input.tangent.xyz - N * dot(N, input.tangent.xyz));
+    let B = cross(N, T) * input.tangent.w;
+    let tangent_normal = textureSample(normal_tex, diffuse_sampler, input.uv).xyz * 2.0 - 1.0;
+    let normal = normalize(T * tangent_normal.x + B * tangent_normal.y + N * tangent_normal.z);
     let light_dir = normalize(-mesh.light_dir);
     let diffuse = max(dot(normal, light_dir), 0.0);
     let intensity = mesh.ambient + diffuse * (1.0 - mesh.ambient);
@@ -0,0 +1,12 @@
+use framework::layout::assert_struct_layouts;
+
+#[test]
+fn uniform_structs_match_wgsl() {
+    assert_struct_layouts(
+        env!("CARGO_MANIFEST_DIR"),
+        &[
+            ("CameraUniforms", "shader.wgsl", "CameraUniforms"),
+            ("MeshUniforms", "shader.wgsl", "MeshUniforms"),
+        ],
+    );
+}
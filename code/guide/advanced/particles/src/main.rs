@@ -12,14 +12,16 @@ use wgpu::{
     Buffer, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
     ColorWrites, CommandEncoder, CompareFunction, ComputePassDescriptor, ComputePipeline,
     ComputePipelineDescriptor, DepthStencilState, FragmentState, LoadOp, MultisampleState,
-    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
-    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    Operations, PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor,
+    PrimitiveState, PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages, StencilState,
-    StoreOp, Texture, TextureFormat, TextureView, VertexState, include_wgsl,
+    StoreOp, Texture, TextureFormat, TextureView, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
-use framework::{Camera, Example, GpuContext, Input, create_depth_texture, run};
+use framework::{
+    Camera, Example, GpuContext, Input, Result, Ui, create_depth_texture, load_shader, run, shader,
+};
 
 const NUM_PARTICLES: u32 = 2048;
 
@@ -44,9 +46,11 @@ struct CameraUniforms {
     max_life: f32,
 }
 
-struct ParticlesDemo {
+pub(crate) struct ParticlesDemo {
     sim_pipeline: ComputePipeline,
     render_pipeline: RenderPipeline,
+    sim_pipeline_layout: PipelineLayout,
+    render_pipeline_layout: PipelineLayout,
     particle_buffer: Buffer,
     params_buffer: Buffer,
     sim_bind_group: BindGroup,
@@ -59,11 +63,86 @@ struct ParticlesDemo {
     spawn_timer: f32,
     spawn_offset: u32,
     last_dt: f32,
+    gravity: f32,
 }
 
 impl ParticlesDemo {
+    fn create_pipelines(
+        ctx: &GpuContext,
+        sim_pipeline_layout: &PipelineLayout,
+        render_pipeline_layout: &PipelineLayout,
+    ) -> Result<(ComputePipeline, RenderPipeline)> {
+        let sim_shader = load_shader(ctx, &shader!("simulate.wgsl"))?;
+        let render_shader = load_shader(ctx, &shader!("render.wgsl"))?;
+
+        let sim_pipeline = ctx
+            .device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Simulation Compute Pipeline"),
+                layout: Some(sim_pipeline_layout),
+                module: &sim_shader,
+                entry_point: Some("main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let render_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Particle Render Pipeline"),
+                layout: Some(render_pipeline_layout),
+                vertex: VertexState {
+                    module: &render_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &render_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent {
+                                src_factor: BlendFactor::SrcAlpha,
+                                dst_factor: BlendFactor::One,
+                                operation: BlendOperation::Add,
+                            },
+                            alpha: BlendComponent {
+                                src_factor: BlendFactor::SrcAlpha,
+                                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                                operation: BlendOperation::Add,
+                            },
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: Default::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        Ok((sim_pipeline, render_pipeline))
+    }
+
     fn spawn_particles(buffer: &Buffer, ctx: &GpuContext, count: u32, offset: u32) {
-        let mut rng = rand::rng();
+        let mut rng = framework::rng();
         let new_particles: Vec<ParticleData> = (0..count)
             .map(|_| {
                 let angle = rng.random_range(0.0..std::f32::consts::TAU);
@@ -89,15 +168,8 @@ impl ParticlesDemo {
 }
 
 impl Example for ParticlesDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let sim_shader = ctx
-            .device
-            .create_shader_module(include_wgsl!("simulate.wgsl"));
-        let render_shader = ctx
-            .device
-            .create_shader_module(include_wgsl!("render.wgsl"));
-
-        let mut rng = rand::rng();
+    fn init(ctx: &GpuContext) -> Result<Self> {
+        let mut rng = framework::rng();
         let initial: Vec<ParticleData> = (0..NUM_PARTICLES)
             .map(|_| {
                 let angle = rng.random_range(0.0..std::f32::consts::TAU);
@@ -173,22 +245,12 @@ impl Example for ParticlesDemo {
             ],
         });
 
-        let sim_pipeline = ctx
+        let sim_pipeline_layout = ctx
             .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some("Simulation Compute Pipeline"),
-                layout: Some(
-                    &ctx.device
-                        .create_pipeline_layout(&PipelineLayoutDescriptor {
-                            label: Some("Simulation Pipeline Layout"),
-                            bind_group_layouts: &[Some(&sim_bgl)],
-                            immediate_size: 0,
-                        }),
-                ),
-                module: &sim_shader,
-                entry_point: Some("main"),
-                compilation_options: PipelineCompilationOptions::default(),
-                cache: None,
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Simulation Pipeline Layout"),
+                bind_group_layouts: &[Some(&sim_bgl)],
+                immediate_size: 0,
             });
 
         let render_bgl = ctx
@@ -245,71 +307,24 @@ impl Example for ParticlesDemo {
             }],
         });
 
-        let render_pipeline = ctx
+        let render_pipeline_layout = ctx
             .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Particle Render Pipeline"),
-                layout: Some(
-                    &ctx.device
-                        .create_pipeline_layout(&PipelineLayoutDescriptor {
-                            label: Some("Render Pipeline Layout"),
-                            bind_group_layouts: &[Some(&render_bgl), Some(&camera_bgl)],
-                            immediate_size: 0,
-                        }),
-                ),
-                vertex: VertexState {
-                    module: &render_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &render_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent {
-                                src_factor: BlendFactor::SrcAlpha,
-                                dst_factor: BlendFactor::One,
-                                operation: BlendOperation::Add,
-                            },
-                            alpha: BlendComponent {
-                                src_factor: BlendFactor::SrcAlpha,
-                                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                operation: BlendOperation::Add,
-                            },
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(false),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: Default::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[Some(&render_bgl), Some(&camera_bgl)],
+                immediate_size: 0,
             });
+        let (sim_pipeline, render_pipeline) =
+            Self::create_pipelines(ctx, &sim_pipeline_layout, &render_pipeline_layout)?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
         let camera = Camera::new(Vec3::new(0.0, 3.0, 8.0), 0.0, -0.2);
 
-        Self {
+        Ok(Self {
             sim_pipeline,
             render_pipeline,
+            sim_pipeline_layout,
+            render_pipeline_layout,
             particle_buffer,
             params_buffer,
             sim_bind_group,
@@ -322,7 +337,8 @@ impl Example for ParticlesDemo {
             spawn_timer: 0.0,
             spawn_offset: 0,
             last_dt: 1.0 / 60.0,
-        }
+            gravity: 9.8,
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -331,6 +347,10 @@ impl Example for ParticlesDemo {
         self.depth_texture_view = v;
     }
 
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.slider("Gravity", &mut self.gravity, -10.0..=20.0);
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.last_dt = dt.as_secs_f32();
         self.camera.update(self.last_dt, input);
@@ -349,7 +369,7 @@ impl Example for ParticlesDemo {
             let mut data = encase::UniformBuffer::new(Vec::new());
             data.write(&SimParams {
                 dt: self.last_dt,
-                gravity: 9.8,
+                gravity: self.gravity,
             })
             .unwrap();
             ctx.queue
@@ -357,14 +377,7 @@ impl Example for ParticlesDemo {
         }
 
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let proj = glam::camera::rh::proj::directx::perspective(
-            std::f32::consts::FRAC_PI_4,
-            aspect,
-            0.1,
-            100.0,
-        );
-        let view_mat = self.camera.view_matrix();
-        let vp = proj * view_mat;
+        let vp = self.camera.view_proj(aspect);
         {
             let mut d = encase::UniformBuffer::new(Vec::new());
             d.write(&CameraUniforms {
@@ -381,7 +394,7 @@ impl Example for ParticlesDemo {
         {
             let mut cpass = encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some("Simulation Pass"),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.compute_pass("Simulation Pass"),
             });
             cpass.set_pipeline(&self.sim_pipeline);
             cpass.set_bind_group(0, &self.sim_bind_group, &[]);
@@ -408,7 +421,7 @@ impl Example for ParticlesDemo {
                     }),
                     stencil_ops: None,
                 }),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Particle Render Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -418,6 +431,12 @@ impl Example for ParticlesDemo {
             rpass.draw(0..6, 0..NUM_PARTICLES);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        (self.sim_pipeline, self.render_pipeline) =
+            Self::create_pipelines(ctx, &self.sim_pipeline_layout, &self.render_pipeline_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
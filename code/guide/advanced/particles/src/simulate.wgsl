@@ -1,6 +1,5 @@
 struct Particle {
     pos: vec3<f32>,
-    _pad0: f32,
     vel: vec3<f32>,
     life: f32,
 }
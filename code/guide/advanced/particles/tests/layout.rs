@@ -0,0 +1,14 @@
+use framework::layout::assert_struct_layouts;
+
+#[test]
+fn uniform_structs_match_wgsl() {
+    assert_struct_layouts(
+        env!("CARGO_MANIFEST_DIR"),
+        &[
+            ("ParticleData", "simulate.wgsl", "Particle"),
+            ("ParticleData", "render.wgsl", "Particle"),
+            ("SimParams", "simulate.wgsl", "Params"),
+            ("CameraUniforms", "render.wgsl", "CameraUniforms"),
+        ],
+    );
+}
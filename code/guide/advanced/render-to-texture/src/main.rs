@@ -1,7 +1,5 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::f32::consts::FRAC_PI_4;
-use std::mem::size_of;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
@@ -11,56 +9,31 @@ use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
     BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
-    BlendState, Buffer, BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color,
-    ColorTargetState, ColorWrites, CommandEncoder, CompareFunction, DepthBiasState,
-    DepthStencilState, Extent3d, Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp,
-    MipmapFilterMode, MultisampleState, Operations, PipelineCompilationOptions,
+    BlendState, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
+    ColorWrites, CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
+    Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode,
+    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayout,
     PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
     RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
     RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
     ShaderStages, StencilState, StoreOp, Texture, TextureDescriptor, TextureDimension,
     TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
-    TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
-    VertexStepMode, include_wgsl,
+    TextureViewDimension, VertexState,
 };
 use winit::dpi::PhysicalSize;
-use winit::keyboard::KeyCode;
 
 use framework::{
-    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, Camera, Example, GpuContext, Input,
-    create_depth_texture, run,
+    Bindings, CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, Camera, Example, GpuContext, Input,
+    Result, VertexLayout, create_depth_texture, load_shader, run, shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 2] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -70,60 +43,13 @@ fn cube_vertices() -> Vec<Vertex> {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+#[vertex(step_mode = Instance, location = 2)]
 struct InstanceData {
     model: [[f32; 4]; 4],
     normal_matrix: [[f32; 3]; 3],
 }
 
-impl InstanceData {
-    const ATTRIBUTES: [VertexAttribute; 7] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 2,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 4]>() as BufferAddress,
-            shader_location: 3,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 8]>() as BufferAddress,
-            shader_location: 4,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 12]>() as BufferAddress,
-            shader_location: 5,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 16]>() as BufferAddress,
-            shader_location: 6,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 19]>() as BufferAddress,
-            shader_location: 7,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 22]>() as BufferAddress,
-            shader_location: 8,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<InstanceData>() as BufferAddress,
-            step_mode: VertexStepMode::Instance,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 #[derive(ShaderType)]
 struct CameraUniforms {
     view_proj: Mat4,
@@ -171,9 +97,11 @@ fn generate_instances() -> Vec<InstanceData> {
     instances
 }
 
-struct RenderToTextureDemo {
+pub(crate) struct RenderToTextureDemo {
     scene_pipeline: RenderPipeline,
     post_pipeline: RenderPipeline,
+    scene_layout: PipelineLayout,
+    post_layout: PipelineLayout,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     instance_buffer: Buffer,
@@ -194,6 +122,102 @@ struct RenderToTextureDemo {
 }
 
 impl RenderToTextureDemo {
+    fn create_pipelines(
+        ctx: &GpuContext,
+        scene_layout: &PipelineLayout,
+        post_layout: &PipelineLayout,
+    ) -> Result<(RenderPipeline, RenderPipeline)> {
+        let scene_shader = load_shader(ctx, &shader!("scene.wgsl"))?;
+        let post_shader = load_shader(ctx, &shader!("post.wgsl"))?;
+
+        let scene_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Scene Pipeline"),
+                layout: Some(scene_layout),
+                vertex: VertexState {
+                    module: &scene_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &scene_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: Self::RT_FORMAT,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        let post_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Post Process Render Pipeline"),
+                layout: Some(post_layout),
+                vertex: VertexState {
+                    module: &post_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &post_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        Ok((scene_pipeline, post_pipeline))
+    }
+
     const RT_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
 
     fn create_offscreen_texture(ctx: &GpuContext) -> (Texture, TextureView) {
@@ -218,10 +242,7 @@ impl RenderToTextureDemo {
 }
 
 impl Example for RenderToTextureDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let scene_shader = ctx.device.create_shader_module(include_wgsl!("scene.wgsl"));
-        let post_shader = ctx.device.create_shader_module(include_wgsl!("post.wgsl"));
-
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let vertex_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -313,7 +334,7 @@ impl Example for RenderToTextureDemo {
             }],
         });
 
-        // Scene pipeline
+        // Scene pipeline layout
         let scene_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -322,53 +343,6 @@ impl Example for RenderToTextureDemo {
                 immediate_size: 0,
             });
 
-        let scene_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Scene Pipeline"),
-                layout: Some(&scene_layout),
-                vertex: VertexState {
-                    module: &scene_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &scene_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: Self::RT_FORMAT,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
-
         // Post-process bind group
         let sampler = ctx.device.create_sampler(&SamplerDescriptor {
             label: Some("Post Process Sampler"),
@@ -443,7 +417,7 @@ impl Example for RenderToTextureDemo {
             ],
         });
 
-        // Post pipeline
+        // Post pipeline layout
         let post_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -452,51 +426,18 @@ impl Example for RenderToTextureDemo {
                 immediate_size: 0,
             });
 
-        let post_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Post Process Render Pipeline"),
-                layout: Some(&post_layout),
-                vertex: VertexState {
-                    module: &post_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &post_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let (scene_pipeline, post_pipeline) =
+            Self::create_pipelines(ctx, &scene_layout, &post_layout)?;
 
         let (offscreen_depth, offscreen_depth_view) = create_depth_texture(ctx, "Offscreen Depth");
 
         let camera = Camera::new(Vec3::new(0.0, 2.0, 8.0), 0.0, -0.2);
 
-        Self {
+        Ok(Self {
             scene_pipeline,
             post_pipeline,
+            scene_layout,
+            post_layout,
             vertex_buffer,
             index_buffer,
             instance_buffer,
@@ -506,15 +447,15 @@ impl Example for RenderToTextureDemo {
             light_bind_group,
             post_uniform_buffer,
             post_bind_group,
-            post_bgl: post_bgl,
-            sampler: sampler,
+            post_bgl,
+            sampler,
             offscreen_texture,
             offscreen_view,
             offscreen_depth,
             offscreen_depth_view,
             camera,
             post_mode: 0,
-        }
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -545,25 +486,28 @@ impl Example for RenderToTextureDemo {
         });
     }
 
+    fn bindings(bindings: &mut Bindings) {
+        bindings.define("post_none", &["Digit1"]);
+        bindings.define("post_grayscale", &["Digit2"]);
+        bindings.define("post_invert", &["Digit3"]);
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.camera.update(dt.as_secs_f32(), input);
-        if input.key_pressed(KeyCode::Digit1) {
+        if input.action_pressed("post_none") {
             self.post_mode = 0;
         }
-        if input.key_pressed(KeyCode::Digit2) {
+        if input.action_pressed("post_grayscale") {
             self.post_mode = 1;
         }
-        if input.key_pressed(KeyCode::Digit3) {
+        if input.action_pressed("post_invert") {
             self.post_mode = 2;
         }
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection =
-            glam::camera::rh::proj::directx::perspective(FRAC_PI_4, aspect, 0.1, 100.0);
-        let view_mat = self.camera.view_matrix();
-        let view_proj = projection * view_mat;
+        let view_proj = self.camera.view_proj(aspect);
 
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
@@ -593,6 +537,13 @@ impl Example for RenderToTextureDemo {
                 .write_buffer(&self.post_uniform_buffer, 0, &data.into_inner());
         }
 
+        let mode_name = match self.post_mode {
+            1 => "Grayscale",
+            2 => "Invert",
+            _ => "None",
+        };
+        ctx.overlay.push_line(format!("Post: {mode_name} (1/2/3)"));
+
         // Pass 1: scene -> offscreen
         {
             let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -650,6 +601,12 @@ impl Example for RenderToTextureDemo {
             rpass.draw(0..6, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        (self.scene_pipeline, self.post_pipeline) =
+            Self::create_pipelines(ctx, &self.scene_layout, &self.post_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
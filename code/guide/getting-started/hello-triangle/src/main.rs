@@ -1,25 +1,23 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use framework::{Example, GpuContext, run};
+use framework::{Example, GpuContext, Result, load_shader, run, shader};
 use wgpu::{
     BlendComponent, BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder,
     FragmentState, FrontFace, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
     PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, StoreOp, TextureView,
-    VertexState, include_wgsl,
+    VertexState,
 };
 
-struct Triangle {
+pub(crate) struct Triangle {
     pipeline: RenderPipeline,
 }
 
-impl Example for Triangle {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader_module = ctx
-            .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
+impl Triangle {
+    fn create_pipeline(ctx: &GpuContext) -> Result<RenderPipeline> {
+        let shader_module = load_shader(ctx, &shader!("shader.wgsl"))?;
 
-        let pipeline = ctx
+        Ok(ctx
             .device
             .create_render_pipeline(&RenderPipelineDescriptor {
                 label: Some("Main Render Pipeline"),
@@ -57,9 +55,15 @@ impl Example for Triangle {
                 },
                 cache: None,
                 multiview_mask: None,
-            });
+            }))
+    }
+}
 
-        Self { pipeline }
+impl Example for Triangle {
+    fn init(ctx: &GpuContext) -> Result<Self> {
+        let pipeline = Self::create_pipeline(ctx)?;
+
+        Ok(Self { pipeline })
     }
 
     fn render(&mut self, _ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
@@ -83,6 +87,11 @@ impl Example for Triangle {
         rpass.set_pipeline(&self.pipeline);
         rpass.draw(0..3, 0..1);
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx)?;
+        Ok(())
+    }
 }
 
 fn main() {
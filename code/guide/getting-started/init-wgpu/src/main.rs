@@ -260,11 +260,10 @@ fn main() {
 }
 
 fn handle_keyboard_input(event_loop: &ActiveEventLoop, event: KeyEvent) {
-    match (event.physical_key, event.state) {
-        (PhysicalKey::Code(KeyCode::Escape), ElementState::Pressed) => {
-            event_loop.exit();
-        }
-        _ => {}
+    if let (PhysicalKey::Code(KeyCode::Escape), ElementState::Pressed) =
+        (event.physical_key, event.state)
+    {
+        event_loop.exit();
     }
 }
 
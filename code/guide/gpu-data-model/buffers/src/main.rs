@@ -1,50 +1,24 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::mem::size_of;
-
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 use wgpu::{
-    BlendComponent, BlendState, Buffer, BufferAddress, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoder, FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState,
-    Operations, PipelineCompilationOptions, PolygonMode, PrimitiveState, PrimitiveTopology,
+    BlendComponent, BlendState, Buffer, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState, Operations,
+    PipelineCompilationOptions, PolygonMode, PrimitiveState, PrimitiveTopology,
     RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    StoreOp, TextureView, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
-    VertexStepMode, include_wgsl,
+    StoreOp, TextureView, VertexState,
 };
 
-use framework::{Example, GpuContext, run};
+use framework::{Example, GpuContext, Result, VertexLayout, load_shader, run, shader};
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 2],
     color: [f32; 3],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 2] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x2,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 2]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: [-0.5, -0.5],
@@ -66,35 +40,17 @@ const VERTICES: &[Vertex] = &[
 
 const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
 
-struct IndexedQuad {
+pub(crate) struct IndexedQuad {
     pipeline: RenderPipeline,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
 }
 
-impl Example for IndexedQuad {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader_module = ctx
-            .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
-
-        let vertex_buffer = ctx
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(VERTICES),
-                usage: BufferUsages::VERTEX,
-            });
-
-        let index_buffer = ctx
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(INDICES),
-                usage: BufferUsages::INDEX,
-            });
+impl IndexedQuad {
+    fn create_pipeline(ctx: &GpuContext) -> Result<RenderPipeline> {
+        let shader_module = load_shader(ctx, &shader!("shader.wgsl"))?;
 
-        let pipeline = ctx
+        Ok(ctx
             .device
             .create_render_pipeline(&RenderPipelineDescriptor {
                 label: Some("Render Pipeline"),
@@ -132,13 +88,35 @@ impl Example for IndexedQuad {
                 },
                 cache: None,
                 multiview_mask: None,
+            }))
+    }
+}
+
+impl Example for IndexedQuad {
+    fn init(ctx: &GpuContext) -> Result<Self> {
+        let vertex_buffer = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Vertex Buffer"),
+                contents: bytemuck::cast_slice(VERTICES),
+                usage: BufferUsages::VERTEX,
             });
 
-        Self {
+        let index_buffer = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: bytemuck::cast_slice(INDICES),
+                usage: BufferUsages::INDEX,
+            });
+
+        let pipeline = Self::create_pipeline(ctx)?;
+
+        Ok(Self {
             pipeline,
             vertex_buffer,
             index_buffer,
-        }
+        })
     }
 
     fn render(&mut self, _ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
@@ -164,6 +142,11 @@ impl Example for IndexedQuad {
         rpass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
         rpass.draw_indexed(0..6, 0, 0..1);
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx)?;
+        Ok(())
+    }
 }
 
 fn main() {
@@ -5,22 +5,20 @@ use wgpu::{
     FragmentState, FrontFace, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
     PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, StoreOp, TextureView,
-    VertexState, include_wgsl,
+    VertexState,
 };
 
-use framework::{Example, GpuContext, run};
+use framework::{Example, GpuContext, Result, load_shader, run, shader};
 
-struct ColoredTriangle {
+pub(crate) struct ColoredTriangle {
     pipeline: RenderPipeline,
 }
 
-impl Example for ColoredTriangle {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader_module = ctx
-            .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
+impl ColoredTriangle {
+    fn create_pipeline(ctx: &GpuContext) -> Result<RenderPipeline> {
+        let shader_module = load_shader(ctx, &shader!("shader.wgsl"))?;
 
-        let pipeline = ctx
+        Ok(ctx
             .device
             .create_render_pipeline(&RenderPipelineDescriptor {
                 label: Some("Render Pipeline"),
@@ -58,9 +56,15 @@ impl Example for ColoredTriangle {
                 },
                 cache: None,
                 multiview_mask: None,
-            });
+            }))
+    }
+}
 
-        Self { pipeline }
+impl Example for ColoredTriangle {
+    fn init(ctx: &GpuContext) -> Result<Self> {
+        let pipeline = Self::create_pipeline(ctx)?;
+
+        Ok(Self { pipeline })
     }
 
     fn render(&mut self, _ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
@@ -84,6 +88,11 @@ impl Example for ColoredTriangle {
         rpass.set_pipeline(&self.pipeline);
         rpass.draw(0..3, 0..1);
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx)?;
+        Ok(())
+    }
 }
 
 fn main() {
@@ -0,0 +1,9 @@
+use framework::layout::assert_struct_layouts;
+
+#[test]
+fn uniform_structs_match_wgsl() {
+    assert_struct_layouts(
+        env!("CARGO_MANIFEST_DIR"),
+        &[("ShaderUniforms", "shader.wgsl", "Uniforms")],
+    );
+}
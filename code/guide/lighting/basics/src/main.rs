@@ -1,7 +1,5 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::f32::consts::FRAC_PI_4;
-use std::mem::size_of;
 use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
@@ -11,60 +9,32 @@ use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendState, Buffer,
-    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
-    ColorWrites, CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
-    Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode,
-    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
-    PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
-    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderStages, StencilState,
-    StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d, Face, FilterMode,
+    FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode,
+    PrimitiveState, PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, StencilState, StoreOp, TexelCopyBufferLayout, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
 };
 use winit::dpi::PhysicalSize;
 
 use framework::{
     CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input,
-    create_depth_texture, generate_checkerboard, run,
+    Result, Ui, VertexLayout, create_depth_texture, generate_checkerboard, load_shader, run,
+    shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
     uv: [f32; 2],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 3] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 6]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn cube_vertices() -> Vec<Vertex> {
     CUBE_POSITIONS
         .iter()
@@ -79,60 +49,13 @@ fn cube_vertices() -> Vec<Vertex> {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+#[vertex(step_mode = Instance, location = 3)]
 struct InstanceData {
     model: [[f32; 4]; 4],
     normal_matrix: [[f32; 3]; 3],
 }
 
-impl InstanceData {
-    const ATTRIBUTES: [VertexAttribute; 7] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 3,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 4]>() as BufferAddress,
-            shader_location: 4,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 8]>() as BufferAddress,
-            shader_location: 5,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 12]>() as BufferAddress,
-            shader_location: 6,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 16]>() as BufferAddress,
-            shader_location: 7,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 19]>() as BufferAddress,
-            shader_location: 8,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 22]>() as BufferAddress,
-            shader_location: 9,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<InstanceData>() as BufferAddress,
-            step_mode: VertexStepMode::Instance,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 #[derive(ShaderType)]
 struct ShaderUniforms {
     view_proj: Mat4,
@@ -146,6 +69,8 @@ struct LightUniforms {
 }
 
 const GRID_SIZE: usize = 5;
+const LIGHT_DIR: Vec3 = Vec3::new(-0.5, -1.0, -0.3);
+const AMBIENT: f32 = 0.1;
 const NUM_INSTANCES: usize = GRID_SIZE * GRID_SIZE * GRID_SIZE;
 const TEX_SIZE: u32 = 256;
 const CELL_SIZE: u32 = 32;
@@ -178,27 +103,78 @@ fn generate_instances() -> Vec<InstanceData> {
     instances
 }
 
-struct LightingDemo {
+pub(crate) struct LightingDemo {
     pipeline: RenderPipeline,
+    pipeline_layout: PipelineLayout,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
     instance_buffer: Buffer,
     camera_uniform_buffer: Buffer,
     camera_bind_group: BindGroup,
-    #[expect(dead_code)]
     light_uniform_buffer: Buffer,
     light_bind_group: BindGroup,
     depth_texture: Texture,
     depth_texture_view: TextureView,
     camera: Camera,
+    light_dir: Vec3,
+    ambient: f32,
 }
 
-impl Example for LightingDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let shader_module = ctx
+impl LightingDemo {
+    fn create_pipeline(ctx: &GpuContext, layout: &PipelineLayout) -> Result<RenderPipeline> {
+        let shader_module = load_shader(ctx, &shader!("shader.wgsl"))?;
+
+        Ok(ctx
             .device
-            .create_shader_module(include_wgsl!("shader.wgsl"));
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            }))
+    }
+}
 
+impl Example for LightingDemo {
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let vertices = cube_vertices();
         let vertex_buffer = ctx
             .device
@@ -314,8 +290,8 @@ impl Example for LightingDemo {
             let mut light_data = encase::UniformBuffer::new(Vec::new());
             light_data
                 .write(&LightUniforms {
-                    light_dir: Vec3::new(-0.5, -1.0, -0.3),
-                    ambient: 0.1,
+                    light_dir: LIGHT_DIR,
+                    ambient: AMBIENT,
                     light_color: Vec3::new(1.0, 0.95, 0.85),
                 })
                 .unwrap();
@@ -387,59 +363,15 @@ impl Example for LightingDemo {
                 immediate_size: 0,
             });
 
-        let pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader_module,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &shader_module,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+        let pipeline = Self::create_pipeline(ctx, &pipeline_layout)?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
 
         let camera = Camera::new(Vec3::new(0.0, 2.0, 8.0), 0.0, -0.2);
 
-        Self {
+        Ok(Self {
             pipeline,
+            pipeline_layout,
             vertex_buffer,
             index_buffer,
             instance_buffer,
@@ -450,7 +382,9 @@ impl Example for LightingDemo {
             depth_texture,
             depth_texture_view,
             camera,
-        }
+            light_dir: LIGHT_DIR,
+            ambient: AMBIENT,
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -459,16 +393,20 @@ impl Example for LightingDemo {
         self.depth_texture_view = view;
     }
 
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.slider("Light X", &mut self.light_dir.x, -1.0..=1.0);
+        ui.slider("Light Y", &mut self.light_dir.y, -1.0..=1.0);
+        ui.slider("Light Z", &mut self.light_dir.z, -1.0..=1.0);
+        ui.slider("Ambient", &mut self.ambient, 0.0..=1.0);
+    }
+
     fn update(&mut self, _ctx: &GpuContext, dt: Duration, input: &Input) {
         self.camera.update(dt.as_secs_f32(), input);
     }
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection =
-            glam::camera::rh::proj::directx::perspective(FRAC_PI_4, aspect, 0.1, 100.0);
-        let view_mat = self.camera.view_matrix();
-        let view_proj = projection * view_mat;
+        let view_proj = self.camera.view_proj(aspect);
 
         {
             let mut uniform_data = encase::UniformBuffer::new(Vec::new());
@@ -478,6 +416,18 @@ impl Example for LightingDemo {
             ctx.queue
                 .write_buffer(&self.camera_uniform_buffer, 0, &uniform_data.into_inner());
         }
+        {
+            let mut light_data = encase::UniformBuffer::new(Vec::new());
+            light_data
+                .write(&LightUniforms {
+                    light_dir: self.light_dir,
+                    ambient: self.ambient,
+                    light_color: Vec3::new(1.0, 0.95, 0.85),
+                })
+                .expect("Failed to write uniform buffer");
+            ctx.queue
+                .write_buffer(&self.light_uniform_buffer, 0, &light_data.into_inner());
+        }
 
         let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Render Pass"),
@@ -511,6 +461,11 @@ impl Example for LightingDemo {
         rpass.set_bind_group(1, &self.light_bind_group, &[]);
         rpass.draw_indexed(0..36, 0, 0..NUM_INSTANCES as u32);
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        self.pipeline = Self::create_pipeline(ctx, &self.pipeline_layout)?;
+        Ok(())
+    }
 }
 
 fn main() {
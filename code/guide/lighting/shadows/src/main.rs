@@ -6,7 +6,7 @@ use std::time::Duration;
 
 use bytemuck::{Pod, Zeroable};
 use encase::ShaderType;
-use glam::{Mat3, Mat4, Vec3};
+use glam::{Mat3, Mat4, Vec2, Vec3};
 use wgpu::util::DeviceExt;
 use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
@@ -14,57 +14,33 @@ use wgpu::{
     BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
     ColorWrites, CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
     Face, FilterMode, FragmentState, FrontFace, IndexFormat, LoadOp, MipmapFilterMode,
-    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
-    PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
-    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderStages, StencilState,
-    StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode, include_wgsl,
+    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderStages,
+    StencilState, StoreOp, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
+    VertexStepMode,
 };
 use winit::dpi::PhysicalSize;
 
+use framework::geometry;
+use framework::texture::{TextureImage, create_sampler, create_texture_with_mips};
 use framework::{
-    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, Example, GpuContext, Input,
-    create_depth_texture, generate_checkerboard, run,
+    CUBE_INDICES, CUBE_NORMALS, CUBE_POSITIONS, CUBE_UVS, Camera, DepthMode, Example, GpuContext,
+    Indices, Input, Projection, Result, VertexLayout, create_depth_texture, generate_checkerboard,
+    load_shader, run, shader,
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
     uv: [f32; 2],
 }
 
-impl Vertex {
-    const ATTRIBUTES: [VertexAttribute; 3] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 0,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 3]>() as BufferAddress,
-            shader_location: 1,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 6]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x2,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<Vertex>() as BufferAddress,
-            step_mode: VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 fn shadow_vertex_layout() -> VertexBufferLayout<'static> {
     const ATTRS: [VertexAttribute; 1] = [VertexAttribute {
         offset: 0,
@@ -94,30 +70,22 @@ fn cube_vertices() -> Vec<Vertex> {
 const TEX_SIZE: u32 = 256;
 const CELL_SIZE: u32 = 32;
 
-const FLOOR_VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [-5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, -5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 0.0],
-    },
-    Vertex {
-        position: [5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [5.0, 5.0],
-    },
-    Vertex {
-        position: [-5.0, -0.5, 5.0],
-        normal: [0.0, 1.0, 0.0],
-        uv: [0.0, 5.0],
-    },
-];
-
-const FLOOR_INDICES: &[u16] = &[0, 2, 1, 0, 3, 2];
+// 10x10 floor under the cubes with the texture repeated 5 times
+fn floor() -> (Vec<Vertex>, Indices) {
+    let mesh = geometry::plane(10.0, 10.0)
+        .transformed(Mat4::from_translation(Vec3::new(0.0, -0.5, 0.0)))
+        .with_uv_scale(Vec2::splat(5.0));
+    let vertices = mesh
+        .vertices
+        .iter()
+        .map(|v| Vertex {
+            position: v.position,
+            normal: v.normal,
+            uv: v.uv,
+        })
+        .collect();
+    (vertices, mesh.indices)
+}
 
 const CUBE_PLACEMENTS: &[Vec3] = &[
     Vec3::new(0.0, 0.0, 0.0),
@@ -126,99 +94,20 @@ const CUBE_PLACEMENTS: &[Vec3] = &[
 ];
 
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+#[vertex(step_mode = Instance, location = 3)]
 struct InstanceData {
     model: [[f32; 4]; 4],
     normal_matrix: [[f32; 3]; 3],
 }
 
-impl InstanceData {
-    const ATTRIBUTES: [VertexAttribute; 7] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 3,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 4]>() as BufferAddress,
-            shader_location: 4,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 8]>() as BufferAddress,
-            shader_location: 5,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 12]>() as BufferAddress,
-            shader_location: 6,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 16]>() as BufferAddress,
-            shader_location: 7,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 19]>() as BufferAddress,
-            shader_location: 8,
-            format: VertexFormat::Float32x3,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 22]>() as BufferAddress,
-            shader_location: 9,
-            format: VertexFormat::Float32x3,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<InstanceData>() as BufferAddress,
-            step_mode: VertexStepMode::Instance,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 #[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+#[vertex(step_mode = Instance, location = 1)]
 struct ShadowInstanceData {
     model: [[f32; 4]; 4],
 }
 
-impl ShadowInstanceData {
-    const ATTRIBUTES: [VertexAttribute; 4] = [
-        VertexAttribute {
-            offset: 0,
-            shader_location: 1,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 4]>() as BufferAddress,
-            shader_location: 2,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 8]>() as BufferAddress,
-            shader_location: 3,
-            format: VertexFormat::Float32x4,
-        },
-        VertexAttribute {
-            offset: size_of::<[f32; 12]>() as BufferAddress,
-            shader_location: 4,
-            format: VertexFormat::Float32x4,
-        },
-    ];
-
-    fn desc() -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride: size_of::<ShadowInstanceData>() as BufferAddress,
-            step_mode: VertexStepMode::Instance,
-            attributes: &Self::ATTRIBUTES,
-        }
-    }
-}
-
 #[derive(ShaderType)]
 struct CameraUniforms {
     view_proj: Mat4,
@@ -238,9 +127,11 @@ struct SceneLightUniforms {
 
 const SHADOW_MAP_SIZE: u32 = 1024;
 
-struct ShadowsDemo {
+pub(crate) struct ShadowsDemo {
     shadow_pipeline: RenderPipeline,
     scene_pipeline: RenderPipeline,
+    shadow_layout: PipelineLayout,
+    scene_layout: PipelineLayout,
     cube_vertex_buffer: Buffer,
     cube_index_buffer: Buffer,
     floor_vertex_buffer: Buffer,
@@ -261,6 +152,103 @@ struct ShadowsDemo {
 }
 
 impl ShadowsDemo {
+    fn create_pipelines(
+        ctx: &GpuContext,
+        shadow_layout: &PipelineLayout,
+        scene_layout: &PipelineLayout,
+        depth_mode: DepthMode,
+    ) -> Result<(RenderPipeline, RenderPipeline)> {
+        let shadow_shader = load_shader(ctx, &shader!("shadow.wgsl"))?;
+        let scene_shader = load_shader(ctx, &shader!("scene.wgsl"))?;
+
+        let shadow_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Shadow Pipeline"),
+                layout: Some(shadow_layout),
+                vertex: VertexState {
+                    module: &shadow_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[
+                        Some(shadow_vertex_layout()),
+                        Some(ShadowInstanceData::desc()),
+                    ],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: None,
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Front),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(CompareFunction::Less),
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        let scene_pipeline = ctx
+            .device
+            .create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Scene Pipeline"),
+                layout: Some(scene_layout),
+                vertex: VertexState {
+                    module: &scene_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &scene_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ColorTargetState {
+                        format: ctx.surface_format,
+                        blend: Some(BlendState {
+                            color: BlendComponent::REPLACE,
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    front_face: FrontFace::Ccw,
+                    polygon_mode: PolygonMode::Fill,
+                    cull_mode: Some(Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(depth_mode.compare()),
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                cache: None,
+                multiview_mask: None,
+            });
+
+        Ok((shadow_pipeline, scene_pipeline))
+    }
+
     fn create_shadow_texture(ctx: &GpuContext) -> (Texture, TextureView) {
         let texture = ctx.device.create_texture(&TextureDescriptor {
             label: Some("Shadow Depth Texture"),
@@ -293,12 +281,7 @@ impl ShadowsDemo {
 }
 
 impl Example for ShadowsDemo {
-    fn init(ctx: &GpuContext) -> Self {
-        let shadow_shader = ctx
-            .device
-            .create_shader_module(include_wgsl!("shadow.wgsl"));
-        let scene_shader = ctx.device.create_shader_module(include_wgsl!("scene.wgsl"));
-
+    fn init(ctx: &GpuContext) -> Result<Self> {
         let cube_vertices = cube_vertices();
         let cube_vertex_buffer = ctx
             .device
@@ -314,18 +297,19 @@ impl Example for ShadowsDemo {
                 contents: bytemuck::cast_slice(&CUBE_INDICES),
                 usage: BufferUsages::INDEX,
             });
+        let (floor_vertices, floor_indices) = floor();
         let floor_vertex_buffer =
             ctx.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Floor Vertex Buffer"),
-                    contents: bytemuck::cast_slice(FLOOR_VERTICES),
+                    contents: bytemuck::cast_slice(&floor_vertices),
                     usage: BufferUsages::VERTEX,
                 });
         let floor_index_buffer = ctx
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Floor Index Buffer"),
-                contents: bytemuck::cast_slice(FLOOR_INDICES),
+                contents: floor_indices.as_bytes(),
                 usage: BufferUsages::INDEX,
             });
         let floor_instance_buffer =
@@ -412,51 +396,21 @@ impl Example for ShadowsDemo {
         );
         let texture_view = texture.create_view(&TextureViewDescriptor::default());
 
-        let floor_pixels = generate_checkerboard(
-            TEX_SIZE,
-            CELL_SIZE,
-            [200, 200, 200, 255],
-            [100, 100, 100, 255],
-        );
-        let floor_texture = ctx.device.create_texture(&TextureDescriptor {
-            label: Some("Floor Texture"),
-            size: Extent3d {
-                width: TEX_SIZE,
-                height: TEX_SIZE,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
+        // The floor is seen at grazing angles, so it gets a full mip chain to stop the shimmering
+        let floor_image = TextureImage {
+            width: TEX_SIZE,
+            height: TEX_SIZE,
             format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        ctx.queue.write_texture(
-            floor_texture.as_image_copy(),
-            &floor_pixels,
-            TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(TEX_SIZE * 4),
-                rows_per_image: Some(TEX_SIZE),
-            },
-            Extent3d {
-                width: TEX_SIZE,
-                height: TEX_SIZE,
-                depth_or_array_layers: 1,
-            },
-        );
+            data: generate_checkerboard(
+                TEX_SIZE,
+                CELL_SIZE,
+                [200, 200, 200, 255],
+                [100, 100, 100, 255],
+            ),
+        };
+        let floor_texture = create_texture_with_mips(ctx, "Floor Texture", &floor_image);
         let floor_texture_view = floor_texture.create_view(&TextureViewDescriptor::default());
-        let diffuse_sampler = ctx.device.create_sampler(&SamplerDescriptor {
-            label: Some("Diffuse Sampler"),
-            address_mode_u: AddressMode::Repeat,
-            address_mode_v: AddressMode::Repeat,
-            address_mode_w: AddressMode::Repeat,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: MipmapFilterMode::Nearest,
-            ..Default::default()
-        });
+        let diffuse_sampler = create_sampler(ctx, AddressMode::Repeat, 16);
         let shadow_sampler = ctx.device.create_sampler(&SamplerDescriptor {
             label: Some("Shadow Sampler"),
             address_mode_u: AddressMode::ClampToEdge,
@@ -660,7 +614,7 @@ impl Example for ShadowsDemo {
             ],
         });
 
-        // Shadow pipeline
+        // Shadow pipeline layout
         let shadow_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -668,45 +622,16 @@ impl Example for ShadowsDemo {
                 bind_group_layouts: &[Some(&shadow_light_bgl)],
                 immediate_size: 0,
             });
-        let shadow_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Shadow Pipeline"),
-                layout: Some(&shadow_layout),
-                vertex: VertexState {
-                    module: &shadow_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[
-                        Some(shadow_vertex_layout()),
-                        Some(ShadowInstanceData::desc()),
-                    ],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: None,
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Front),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
 
-        // Scene pipeline
+        // Reverse-Z keeps depth precision at the far end of the floor
+        let mut camera = Camera::new(Vec3::new(0.0, 2.5, 5.0), 0.0, -0.3);
+        camera.projection = Projection::InfiniteReverse {
+            fov_y: FRAC_PI_4,
+            near: 0.1,
+        };
+        let depth_mode = camera.projection.depth_mode();
+
+        // Scene pipeline layout
         let scene_layout = ctx
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -714,59 +639,17 @@ impl Example for ShadowsDemo {
                 bind_group_layouts: &[Some(&camera_bgl), Some(&scene_light_bgl)],
                 immediate_size: 0,
             });
-        let scene_pipeline = ctx
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Scene Pipeline"),
-                layout: Some(&scene_layout),
-                vertex: VertexState {
-                    module: &scene_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Some(Vertex::desc()), Some(InstanceData::desc())],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &scene_shader,
-                    entry_point: Some("fs_main"),
-                    targets: &[Some(ColorTargetState {
-                        format: ctx.surface_format,
-                        blend: Some(BlendState {
-                            color: BlendComponent::REPLACE,
-                            alpha: BlendComponent::REPLACE,
-                        }),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    front_face: FrontFace::Ccw,
-                    polygon_mode: PolygonMode::Fill,
-                    cull_mode: Some(Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: Some(true),
-                    depth_compare: Some(CompareFunction::Less),
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                cache: None,
-                multiview_mask: None,
-            });
+
+        let (shadow_pipeline, scene_pipeline) =
+            Self::create_pipelines(ctx, &shadow_layout, &scene_layout, depth_mode)?;
 
         let (depth_texture, depth_texture_view) = create_depth_texture(ctx, "Depth Texture");
-        let camera = Camera::new(Vec3::new(0.0, 2.5, 5.0), 0.0, -0.3);
 
-        Self {
+        Ok(Self {
             shadow_pipeline,
             scene_pipeline,
+            shadow_layout,
+            scene_layout,
             cube_vertex_buffer,
             cube_index_buffer,
             floor_vertex_buffer,
@@ -784,7 +667,7 @@ impl Example for ShadowsDemo {
             depth_texture,
             depth_texture_view,
             camera,
-        }
+        })
     }
 
     fn resize(&mut self, ctx: &GpuContext, _new_size: PhysicalSize<u32>) {
@@ -799,9 +682,7 @@ impl Example for ShadowsDemo {
 
     fn render(&mut self, ctx: &GpuContext, view: &TextureView, encoder: &mut CommandEncoder) {
         let aspect = ctx.surface_config.width as f32 / ctx.surface_config.height as f32;
-        let projection =
-            glam::camera::rh::proj::directx::perspective(FRAC_PI_4, aspect, 0.1, 100.0);
-        let view_proj = projection * self.camera.view_matrix();
+        let view_proj = self.camera.view_proj(aspect);
 
         {
             let mut data = encase::UniformBuffer::new(Vec::new());
@@ -824,7 +705,7 @@ impl Example for ShadowsDemo {
                     }),
                     stencil_ops: None,
                 }),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Shadow Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -852,12 +733,12 @@ impl Example for ShadowsDemo {
                 depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
                     view: &self.depth_texture_view,
                     depth_ops: Some(Operations {
-                        load: LoadOp::Clear(1.0),
+                        load: LoadOp::Clear(self.camera.projection.depth_mode().clear_value()),
                         store: StoreOp::Store,
                     }),
                     stencil_ops: None,
                 }),
-                timestamp_writes: None,
+                timestamp_writes: ctx.profiler.render_pass("Scene Pass"),
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
@@ -879,6 +760,16 @@ impl Example for ShadowsDemo {
             rpass.draw_indexed(0..6, 0, 0..1);
         }
     }
+
+    fn reload_shaders(&mut self, ctx: &GpuContext) -> Result<()> {
+        (self.shadow_pipeline, self.scene_pipeline) = Self::create_pipelines(
+            ctx,
+            &self.shadow_layout,
+            &self.scene_layout,
+            self.camera.projection.depth_mode(),
+        )?;
+        Ok(())
+    }
 }
 
 fn main() {
@@ -1,4 +1,6 @@
 // Scene pass — renders cubes with shadow comparison
+#include <shadow.wgsl>
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
@@ -72,20 +74,7 @@ fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
     let light_dir = normalize(-light.light_dir);
     let diffuse = max(dot(normal, light_dir), 0.0);
 
-    // Shadow comparison
-    let light_coords = input.light_pos;
-    let shadow_uv = vec3<f32>(
-        light_coords.x * 0.5 + 0.5,
-        1.0 - (light_coords.y * 0.5 + 0.5),
-        light_coords.z
-    );
-    var shadow = 0.0;
-    if (shadow_uv.x >= 0.0 && shadow_uv.x <= 1.0 &&
-        shadow_uv.y >= 0.0 && shadow_uv.y <= 1.0) {
-        shadow = textureSampleCompare(shadow_tex, shadow_sampler, shadow_uv.xy, shadow_uv.z);
-    } else {
-        shadow = 1.0;
-    }
+    let shadow = sample_shadow(shadow_tex, shadow_sampler, input.light_pos);
 
     let tex_color = textureSample(diffuse_tex, diffuse_sampler, input.uv);
     let intensity = light.ambient + diffuse * shadow * (1.0 - light.ambient);
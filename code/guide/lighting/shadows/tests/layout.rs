@@ -0,0 +1,13 @@
+use framework::layout::assert_struct_layouts;
+
+#[test]
+fn uniform_structs_match_wgsl() {
+    assert_struct_layouts(
+        env!("CARGO_MANIFEST_DIR"),
+        &[
+            ("CameraUniforms", "scene.wgsl", "CameraUniforms"),
+            ("SceneLightUniforms", "scene.wgsl", "LightUniforms"),
+            ("ShadowLightUniforms", "shadow.wgsl", "LightUniforms"),
+        ],
+    );
+}